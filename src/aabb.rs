@@ -0,0 +1,83 @@
+use crate::mat4::Mat4;
+use crate::ray::Ray;
+use crate::vec3::Vec3;
+
+// Axis-aligned bounding box
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Aabb {
+    pub fn new(min: Vec3, max: Vec3) -> Self {
+        Aabb { min, max }
+    }
+
+    pub fn from_points(points: &[Vec3]) -> Option<Self> {
+        let (first, rest) = points.split_first()?;
+        let mut bounds = Aabb::new(*first, *first);
+        for p in rest {
+            bounds = bounds.union(&Aabb::new(*p, *p));
+        }
+        Some(bounds)
+    }
+
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        Aabb {
+            min: Vec3::new(self.min.x().min(other.min.x()), self.min.y().min(other.min.y()), self.min.z().min(other.min.z())),
+            max: Vec3::new(self.max.x().max(other.max.x()), self.max.y().max(other.max.y()), self.max.z().max(other.max.z())),
+        }
+    }
+
+    pub fn overlaps(&self, other: &Aabb) -> bool {
+        self.min.x() <= other.max.x() && self.max.x() >= other.min.x()
+            && self.min.y() <= other.max.y() && self.max.y() >= other.min.y()
+            && self.min.z() <= other.max.z() && self.max.z() >= other.min.z()
+    }
+
+    pub fn contains(&self, p: Vec3) -> bool {
+        p.x() >= self.min.x() && p.x() <= self.max.x()
+            && p.y() >= self.min.y() && p.y() <= self.max.y()
+            && p.z() >= self.min.z() && p.z() <= self.max.z()
+    }
+
+    // Bounds of the eight transformed corners
+    pub fn transformed(&self, m: &Mat4) -> Aabb {
+        let corners: Vec<Vec3> = (0..8)
+            .map(|i| {
+                let x = if i & 1 == 0 { self.min.x() } else { self.max.x() };
+                let y = if i & 2 == 0 { self.min.y() } else { self.max.y() };
+                let z = if i & 4 == 0 { self.min.z() } else { self.max.z() };
+                m.transform_point(Vec3::new(x, y, z))
+            })
+            .collect();
+        Aabb::from_points(&corners).unwrap_or(*self)
+    }
+
+    // Slab test, true if the ray overlaps the box anywhere in [0, t_max]
+    pub fn hit(&self, ray: &Ray, t_max: f32) -> bool {
+        self.clip(ray, t_max).is_some()
+    }
+
+    // The part of [0, t_max] the ray spends inside the box, if any
+    pub fn clip(&self, ray: &Ray, t_max: f32) -> Option<(f32, f32)> {
+        let mut t0 = 0.0_f32;
+        let mut t1 = t_max;
+        let axes = [
+            (ray.origin.x(), ray.direction.x(), self.min.x(), self.max.x()),
+            (ray.origin.y(), ray.direction.y(), self.min.y(), self.max.y()),
+            (ray.origin.z(), ray.direction.z(), self.min.z(), self.max.z()),
+        ];
+        for (o, d, lo, hi) in axes {
+            let inv = 1.0 / d;
+            let (mut near, mut far) = ((lo - o) * inv, (hi - o) * inv);
+            if near > far { std::mem::swap(&mut near, &mut far); }
+            // NaN (ray parallel and on a slab boundary) leaves the interval unchanged
+            if near > t0 { t0 = near; }
+            if far < t1 { t1 = far; }
+            if t0 > t1 { return None; }
+        }
+        Some((t0, t1))
+    }
+}
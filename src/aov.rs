@@ -0,0 +1,211 @@
+use std::io;
+use std::path::{Path, PathBuf};
+use crate::environment::Rgb;
+use crate::vec3::Vec3;
+
+// Arbitrary output variables: what the camera saw at each pixel besides the final colour, for
+// compositing and for debugging the shading
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum Aov {
+    Depth,        // distance along the view axis to the hit
+    DepthPreview, // depth squeezed into 8 bits for looking at, not for compositing
+    Normal,       // world space surface normal
+    Albedo,       // surface colour before lighting
+    ObjectId,     // index of the object hit
+    ShadowMask,   // share of the lights' light that doesn't reach the hit
+    Uv,           // surface coordinates of the hit, u in red and v in green
+}
+
+impl Aov {
+    // Every pass meant for compositing; the depth preview is only written when asked for by name
+    pub(crate) const ALL: [Aov; 6] = [Aov::Depth, Aov::Normal, Aov::Albedo, Aov::ObjectId, Aov::ShadowMask, Aov::Uv];
+
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            Aov::Depth => "depth",
+            Aov::DepthPreview => "depth_preview",
+            Aov::Normal => "normal",
+            Aov::Albedo => "albedo",
+            Aov::ObjectId => "id",
+            Aov::ShadowMask => "shadow",
+            Aov::Uv => "uv",
+        }
+    }
+
+    pub(crate) fn from_name(name: &str) -> Option<Aov> {
+        Aov::ALL.into_iter().chain([Aov::DepthPreview]).find(|aov| aov.name() == name)
+    }
+}
+
+// Everything recorded for one pixel's primary hit
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) struct AovSample {
+    pub depth: f32,
+    pub normal: Vec3,
+    pub albedo: Rgb,
+    pub object_id: usize,
+    pub shadow: f32,
+    pub uv: (f32, f32),
+}
+
+// One buffer per output variable, row by row like the beauty image. Pixels where the ray hit
+// nothing have infinite depth, a zero normal, albedo and uv, no id and no shadow.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct AovBuffers {
+    resolution: (u32, u32),
+    depth: Vec<f32>,
+    normal: Vec<Vec3>,
+    albedo: Vec<Rgb>,
+    object_id: Vec<Option<usize>>,
+    shadow: Vec<f32>,
+    uv: Vec<(f32, f32)>,
+}
+
+impl AovBuffers {
+    pub(crate) fn new(resolution: (u32, u32)) -> Self {
+        let n = (resolution.0 * resolution.1) as usize;
+        AovBuffers {
+            resolution,
+            depth: vec![f32::INFINITY; n],
+            normal: vec![Vec3::new(0.0, 0.0, 0.0); n],
+            albedo: vec![[0.0; 3]; n],
+            object_id: vec![None; n],
+            shadow: vec![0.0; n],
+            uv: vec![(0.0, 0.0); n],
+        }
+    }
+
+    pub(crate) fn record(&mut self, x: u32, y: u32, sample: AovSample) {
+        let i = (y * self.resolution.0 + x) as usize;
+        self.depth[i] = sample.depth;
+        self.normal[i] = sample.normal;
+        self.albedo[i] = sample.albedo;
+        self.object_id[i] = Some(sample.object_id);
+        self.shadow[i] = sample.shadow;
+        self.uv[i] = sample.uv;
+    }
+
+    // An 8 bit RGBA picture of one variable. Depth runs from white at the nearest hit to black at
+    // the farthest in this frame, so it can't be compared between frames; normals map -1..1 to 0..255, ids get arbitrary but stable colours and the
+    // shadow mask is white where shadowed.
+    pub(crate) fn to_rgba(&self, aov: Aov) -> Vec<u8> {
+        let byte = |v: f32| (v.clamp(0.0, 1.0) * 255.0) as u8;
+        let pixels: Vec<[u8; 3]> = match aov {
+            Aov::Depth | Aov::DepthPreview => {
+                let finite = self.depth.iter().cloned().filter(|d| d.is_finite());
+                let (near, far) = finite.fold((f32::INFINITY, f32::NEG_INFINITY), |(lo, hi), d| (lo.min(d), hi.max(d)));
+                let range = (far - near).max(1e-6);
+                self.depth.iter().map(|&d| {
+                    if d.is_finite() { [byte(1.0 - (d - near) / range); 3] } else { [0; 3] }
+                }).collect()
+            }
+            Aov::Normal => self.normal.iter().map(|n| {
+                if n.length() > 0.0 { [n.x(), n.y(), n.z()].map(|c| byte(c * 0.5 + 0.5)) } else { [0; 3] }
+            }).collect(),
+            Aov::Albedo => self.albedo.iter().map(|a| a.map(byte)).collect(),
+            Aov::ObjectId => self.object_id.iter().map(|id| id.map_or([0; 3], id_color)).collect(),
+            Aov::ShadowMask => self.shadow.iter().map(|&s| [byte(s); 3]).collect(),
+            Aov::Uv => self.uv.iter().map(|&(u, v)| [byte(u), byte(v), 0]).collect(),
+        };
+        pixels.into_iter().flat_map(|[r, g, b]| [r, g, b, 255]).collect()
+    }
+
+    // Saves each of `aovs` in `directory`: depth as `{stem}_depth.exr` holding the raw camera space
+    // distance in every channel (infinite where nothing was hit), the rest as `{stem}_{name}.png`
+    pub(crate) fn write(&self, aovs: &[Aov], directory: &Path, stem: &str) -> io::Result<Vec<PathBuf>> {
+        std::fs::create_dir_all(directory)?;
+        let (width, height) = self.resolution;
+        aovs.iter()
+            .map(|aov| {
+                let path = if *aov == Aov::Depth {
+                    let path = directory.join(format!("{}_{}.exr", stem, aov.name()));
+                    let channels = self.depth.iter().flat_map(|&d| [d; 3]).collect();
+                    image::Rgb32FImage::from_raw(width, height, channels)
+                        .expect("one depth per pixel")
+                        .save(&path)
+                        .map_err(io::Error::other)?;
+                    path
+                } else {
+                    let path = directory.join(format!("{}_{}.png", stem, aov.name()));
+                    image::save_buffer(&path, &self.to_rgba(*aov), width, height, image::ColorType::Rgba8)
+                        .map_err(io::Error::other)?;
+                    path
+                };
+                Ok(path)
+            })
+            .collect()
+    }
+}
+
+// Raw passes, read back by the camera tests
+#[cfg(test)]
+impl AovBuffers {
+    pub(crate) fn depth(&self) -> &[f32] { &self.depth }
+    pub(crate) fn normal(&self) -> &[Vec3] { &self.normal }
+    pub(crate) fn albedo(&self) -> &[Rgb] { &self.albedo }
+    pub(crate) fn object_id(&self) -> &[Option<usize>] { &self.object_id }
+    pub(crate) fn shadow(&self) -> &[f32] { &self.shadow }
+    pub(crate) fn uv(&self) -> &[(f32, f32)] { &self.uv }
+}
+
+// Bright, well separated colours for neighbouring ids, never black so misses stand out
+fn id_color(id: usize) -> [u8; 3] {
+    let mut h = (id as u32).wrapping_add(1).wrapping_mul(0x9e37_79b9);
+    h ^= h >> 16;
+    h = h.wrapping_mul(0x85eb_ca6b);
+    h ^= h >> 13;
+    [h, h >> 8, h >> 16].map(|c| 64 + (c & 0xff) as u8 / 4 * 3)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pictures_of_each_variable() {
+        let mut aovs = AovBuffers::new((3, 1));
+        let hit = |depth, id, shadow| AovSample {
+            depth,
+            normal: Vec3::new(0.0, 0.0, -1.0),
+            albedo: [1.0, 0.5, 0.0],
+            object_id: id,
+            shadow,
+            uv: (1.0, 0.5),
+        };
+        aovs.record(0, 0, hit(2.0, 0, 0.0));
+        aovs.record(1, 0, hit(6.0, 1, 1.0));
+        // Pixel 2 missed
+        let depth = aovs.to_rgba(Aov::Depth);
+        assert_eq!(&depth[..12], &[255, 255, 255, 255, 0, 0, 0, 255, 0, 0, 0, 255]);
+        let normal = aovs.to_rgba(Aov::Normal);
+        assert_eq!(&normal[..4], &[127, 127, 0, 255]);
+        assert_eq!(&aovs.to_rgba(Aov::Albedo)[..4], &[255, 127, 0, 255]);
+        assert_eq!(&aovs.to_rgba(Aov::ShadowMask)[..8], &[0, 0, 0, 255, 255, 255, 255, 255]);
+        assert_eq!(&aovs.to_rgba(Aov::Uv)[..12], &[255, 127, 0, 255, 255, 127, 0, 255, 0, 0, 0, 255]);
+        let ids = aovs.to_rgba(Aov::ObjectId);
+        assert_ne!(&ids[..3], &ids[4..7]);
+        assert_ne!(&ids[..3], &[0, 0, 0]);
+        assert_eq!(&ids[8..11], &[0, 0, 0]);
+        assert_eq!(Aov::from_name("shadow"), Some(Aov::ShadowMask));
+        assert_eq!(Aov::from_name("depth_preview"), Some(Aov::DepthPreview));
+    }
+
+    #[test]
+    fn writes_one_image_per_pass() {
+        let output = std::env::temp_dir().join(format!("aov_{}", std::process::id()));
+        let mut aovs = AovBuffers::new((4, 2));
+        let hit = AovSample { depth: 123.25, normal: Vec3::new(0.0, 1.0, 0.0), albedo: [1.0; 3], object_id: 0, shadow: 0.0, uv: (0.0, 0.0) };
+        aovs.record(1, 0, hit);
+        let written = aovs.write(&[Aov::Depth, Aov::ObjectId, Aov::DepthPreview], &output, "frame_0001").unwrap();
+        let names: Vec<String> = written.iter().map(|p| p.file_name().unwrap().to_string_lossy().into_owned()).collect();
+        assert_eq!(names, ["frame_0001_depth.exr", "frame_0001_id.png", "frame_0001_depth_preview.png"]);
+        // Depth comes back exactly, not rescaled to the frame
+        let depth = image::open(&written[0]).unwrap().to_rgb32f();
+        assert_eq!((depth.width(), depth.height()), (4, 2));
+        assert_eq!(depth.get_pixel(1, 0).0, [123.25; 3]);
+        assert_eq!(depth.get_pixel(0, 0).0[0], f32::INFINITY);
+        let image = image::open(&written[1]).unwrap();
+        assert_eq!((image.width(), image.height()), (4, 2));
+        let _ = std::fs::remove_dir_all(&output);
+    }
+}
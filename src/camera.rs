@@ -1,8 +1,17 @@
-use crate::light::{AmbientLight, Light};
+use std::f32::consts::PI;
+use crate::aabb::Aabb;
+use crate::aov::{AovBuffers, AovSample};
+use crate::light::{arriving_color, AmbientLight, Light, VisibleLight};
 use crate::objects::hittable::Hittable;
 use crate::phong::{shade_multi_light};
+use crate::pbr::{direct_light, shade_pbr, Pbr};
+use crate::environment::{Environment, Rgb};
+use crate::medium::Medium;
+use crate::phong::attenuation;
+use crate::quat::Quat;
+use crate::rng::Rng;
 use crate::vec3::Vec3;
-use crate::ray::{Ray, Segment};
+use crate::ray::Ray;
 
 #[derive(Debug, Copy, Clone)]
 pub(crate) struct Camera {
@@ -10,6 +19,61 @@ pub(crate) struct Camera {
     rotation: Vec3,
     fov: f32,
     resolution: (u32, u32),
+    shutter: (f32, f32), // open and close times relative to the frame, equal for no motion blur
+    aperture_radius: f32, // lens radius, 0 for a pinhole with everything in focus
+    aperture: Aperture,
+    focus_distance: f32,  // distance along the view axis to the plane in sharp focus
+    samples: u32,         // rays per pixel when motion blur or depth of field is on
+    projection: Projection,
+    environment_samples: u32, // directions gathered from the environment per hit
+    render_mode: RenderMode,
+    volume_steps: u32,   // ray marching steps through participating media
+}
+
+// How light is gathered at each hit
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum RenderMode {
+    Direct,                           // lights, ambient and environment seen straight from the surface
+    #[allow(dead_code, reason = "only reachable through set_render_mode")]
+    PathTraced { max_bounces: u32 },  // light bouncing between surfaces too, `samples` paths per pixel
+}
+
+// How image positions map to rays
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[allow(dead_code, reason = "the demo window stays perspective; the other projections are for stills")]
+pub(crate) enum Projection {
+    Perspective,                 // pinhole, fov is the vertical field of view
+    Orthographic { height: f32 }, // parallel rays from a window this many units tall
+    Fisheye,                     // equidistant, fov is the angle across the inscribed image circle
+    Equirectangular,             // full 360 by 180 degree panorama, longitude across and latitude down
+}
+
+// Shape of the lens opening, which is the shape out of focus highlights take
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum Aperture {
+    Circle,
+    #[allow(dead_code, reason = "picked through set_aperture, which only the tests call")]
+    Blades(u32), // regular polygon with this many sides, 3 or more
+}
+
+impl Aperture {
+    // Uniform point on the opening, scaled to fit the unit circle
+    fn sample(&self, rng: &mut Rng) -> (f32, f32) {
+        match *self {
+            Aperture::Blades(sides) if sides >= 3 => {
+                // Pick one of the equal triangles fanning out from the centre, then a point in it
+                let wedge = 2.0 * std::f32::consts::PI / sides as f32;
+                let i = (rng.next_u32() % sides) as f32;
+                let (a, b) = ((i * wedge).sin_cos(), ((i + 1.0) * wedge).sin_cos());
+                let (mut u, mut v) = (rng.next_f32(), rng.next_f32());
+                if u + v > 1.0 {
+                    (u, v) = (1.0 - u, 1.0 - v);
+                }
+                (u * a.1 + v * b.1, u * a.0 + v * b.0)
+            }
+            _ => rng.in_unit_disc(),
+        }
+    }
 }
 
 // +Z is forward
@@ -17,85 +81,303 @@ pub(crate) struct Camera {
 
 impl Camera {
     pub fn new(position: Vec3, rotation: Vec3, fov: f32, resolution: (u32, u32)) -> Self {
-        Camera { position, rotation, fov, resolution }
+        Camera {
+            position,
+            rotation,
+            fov,
+            resolution,
+            shutter: (0.0, 0.0),
+            aperture_radius: 0.0,
+            aperture: Aperture::Circle,
+            focus_distance: 10.0,
+            samples: 8,
+            projection: Projection::Perspective,
+            environment_samples: 16,
+            render_mode: RenderMode::Direct,
+            volume_steps: 32,
+        }
     }
 
     pub fn set_resolution(&mut self, resolution: (u32, u32)) {
         self.resolution = resolution;
     }
-    
 
-    pub fn render_scene(&self, objects: &[Box<dyn Hittable>], lights: &[Box<dyn Light>], ambient_light: &AmbientLight) -> Vec<u8> {
-        let (width, height) = self.resolution;
-        let aspect_ratio: f32 = if height > 0 { width as f32 / height as f32 } else { 1.0 };
-        let mut buffer = vec![0u8; (width * height * 4) as usize];
-        let fov_rad = self.fov.to_radians();
+    pub fn position(&self) -> Vec3 { self.position }
+    pub fn rotation(&self) -> Vec3 { self.rotation }
+    pub fn resolution(&self) -> (u32, u32) { self.resolution }
 
+    pub fn set_position(&mut self, position: Vec3) {
+        self.position = position;
+    }
 
-        for y in 0..height {
-            for x in 0..width {
-                let px = x as f32 + 0.5;
-                let py = y as f32 + 0.5;
+    // Euler angles in degrees, as Quat::from_euler_degrees
+    pub fn set_rotation(&mut self, rotation: Vec3) {
+        self.rotation = rotation;
+    }
+
+    pub fn set_fov(&mut self, fov: f32) {
+        self.fov = fov;
+    }
+
+    pub fn shutter(&self) -> (f32, f32) { self.shutter }
+    pub fn has_motion_blur(&self) -> bool { self.shutter.1 > self.shutter.0 }
+    pub fn has_depth_of_field(&self) -> bool { self.aperture_radius > 0.0 }
+
+    #[allow(dead_code, reason = "only matters once the scene has a medium, and the demo's air is clear")]
+    pub fn set_volume_steps(&mut self, steps: u32) {
+        self.volume_steps = steps.max(1);
+    }
+
+    #[allow(dead_code, reason = "path tracing is too slow for the live demo window")]
+    pub fn set_render_mode(&mut self, mode: RenderMode) {
+        self.render_mode = mode;
+    }
+
+    pub fn set_environment_samples(&mut self, samples: u32) {
+        self.environment_samples = samples;
+    }
+
+    pub fn set_projection(&mut self, projection: Projection) {
+        self.projection = projection;
+    }
+
+    // Seconds relative to the frame time, e.g. (0, 0.5 / fps) for a 180 degree shutter
+    pub fn set_shutter(&mut self, open: f32, close: f32) {
+        assert!(open <= close, "shutter must open before it closes");
+        self.shutter = (open, close);
+    }
+
+    #[allow(dead_code, reason = "one sample per pixel keeps the demo window responsive")]
+    pub fn set_samples(&mut self, samples: u32) {
+        self.samples = samples.max(1);
+    }
+
+    #[allow(dead_code, reason = "the demo camera is a pinhole, so the whole scene stays sharp")]
+    pub fn set_aperture_radius(&mut self, radius: f32) {
+        self.aperture_radius = radius.max(0.0);
+    }
+
+    #[allow(dead_code, reason = "only matters with an open lens, which the demo never has")]
+    pub fn set_aperture(&mut self, aperture: Aperture) {
+        self.aperture = aperture;
+    }
+
+    #[allow(dead_code, reason = "a pinhole is in focus everywhere, so the demo never sets this")]
+    pub fn set_focus_distance(&mut self, distance: f32) {
+        assert!(distance > 0.0, "focus distance must be positive");
+        self.focus_distance = distance;
+    }
+
+    // Focuses on whatever is under a pixel and returns the new focus distance, or None (focus
+    // unchanged) if nothing is there
+    #[allow(dead_code, reason = "for click to focus; the demo window ignores the mouse")]
+    pub fn focus_on_pixel(&mut self, x: u32, y: u32, objects: &[Box<dyn Hittable>]) -> Option<f32> {
+        let (_origin, view) = self.view_ray(x as f32 + 0.5, y as f32 + 0.5)?;
+        let ray = self.primary_ray(x as f32 + 0.5, y as f32 + 0.5)?;
+        let bounds: Vec<Option<Aabb>> = objects.iter().map(|o| o.bounding_box()).collect();
+        let (_i, t, _p, _n) = closest_hit(&ray, objects, &bounds)?;
+        // Hit distance along the ray, in units of the view direction
+        self.focus_distance = t / view.length();
+        Some(self.focus_distance)
+    }
+
+    // World space ray through a point on the image (pixel centres at +0.5), ignoring the lens.
+    // None where the projection has no image, outside a fisheye's circle.
+    pub fn primary_ray(&self, px: f32, py: f32) -> Option<Ray> {
+        let (origin, view) = self.view_ray(px, py)?;
+        let orientation = Quat::from_euler_degrees(self.rotation);
+        Some(Ray::new(self.position + orientation.rotate(origin), orientation.rotate(view).normalized()))
+    }
+
+    // Camera space origin and direction through a point on the image. Perspective directions
+    // reach the plane z = 1, so focusing along them gives a flat focal plane; the others are unit
+    // length and focus on a sphere around the camera.
+    fn view_ray(&self, px: f32, py: f32) -> Option<(Vec3, Vec3)> {
+        let (width, height) = self.resolution;
+        let aspect_ratio: f32 = if height > 0 { width as f32 / height as f32 } else { 1.0 };
+        let origin = Vec3::new(0.0, 0.0, 0.0);
 
-                let sx = (2.0 * px / width as f32) - 1.0;
-                let sy = 1.0 - (2.0 * py / height as f32);
+        let sx = (2.0 * px / width as f32) - 1.0;
+        let sy = 1.0 - (2.0 * py / height as f32);
 
-                let half_h = (0.5 * fov_rad).tan();
+        match self.projection {
+            Projection::Perspective => {
+                let half_h = (0.5 * self.fov.to_radians()).tan();
+                let half_w = aspect_ratio * half_h;
+                Some((origin, Vec3::new(sx * half_w, sy * half_h, 1.0)))
+            }
+            Projection::Orthographic { height } => {
+                let half_h = 0.5 * height;
                 let half_w = aspect_ratio * half_h;
+                Some((Vec3::new(sx * half_w, sy * half_h, 0.0), Vec3::new(0.0, 0.0, 1.0)))
+            }
+            Projection::Fisheye => {
+                // Radius 1 at the edge of the shorter side
+                let (x, y) = if aspect_ratio >= 1.0 { (sx * aspect_ratio, sy) } else { (sx, sy / aspect_ratio) };
+                let r = (x * x + y * y).sqrt();
+                if r > 1.0 { return None; }
+                let theta = r * 0.5 * self.fov.to_radians();
+                let (sin, cos) = theta.sin_cos();
+                let (dx, dy) = if r > 0.0 { (x / r, y / r) } else { (0.0, 0.0) };
+                Some((origin, Vec3::new(sin * dx, sin * dy, cos)))
+            }
+            Projection::Equirectangular => {
+                let longitude = sx * std::f32::consts::PI;
+                let latitude = sy * std::f32::consts::FRAC_PI_2;
+                let (sin_lon, cos_lon) = longitude.sin_cos();
+                let (sin_lat, cos_lat) = latitude.sin_cos();
+                Some((origin, Vec3::new(cos_lat * sin_lon, sin_lat, cos_lat * cos_lon)))
+            }
+        }
+    }
 
-                let cx = sx * half_w;
-                let cy = sy * half_h;
-                let cz = 1.0;
+    pub fn render_scene(&self, objects: &[Box<dyn Hittable>], lights: &[Box<dyn Light>], ambient_light: &AmbientLight, environment: &Environment, media: &[Medium]) -> Vec<u8> {
+        self.render(objects, lights, ambient_light, environment, media, None)
+    }
 
-                let ray = Ray::new(self.position, Vec3::new(cx, cy, cz).normalized());
+    // The image plus depth, normal, albedo, object id and shadow passes, taken from each pixel's
+    // first sample
+    pub fn render_scene_with_aovs(&self, objects: &[Box<dyn Hittable>], lights: &[Box<dyn Light>], ambient_light: &AmbientLight, environment: &Environment, media: &[Medium]) -> (Vec<u8>, AovBuffers) {
+        let mut aovs = AovBuffers::new(self.resolution);
+        let image = self.render(objects, lights, ambient_light, environment, media, Some(&mut aovs));
+        (image, aovs)
+    }
 
-                let mut closest_t = f32::INFINITY;
-                let mut hit: Option<(usize, Vec3, Vec3)> = None; // (object index, point, normal)
+    fn render(&self, objects: &[Box<dyn Hittable>], lights: &[Box<dyn Light>], ambient_light: &AmbientLight, environment: &Environment, media: &[Medium], mut aovs: Option<&mut AovBuffers>) -> Vec<u8> {
+        let (width, height) = self.resolution;
+        let mut buffer = vec![0u8; (width * height * 4) as usize];
+        let scene = Surroundings {
+            objects,
+            // Bounding boxes let most rays skip most objects
+            bounds: objects.iter().map(|o| o.bounding_box()).collect(),
+            lights,
+            ambient_light,
+            environment,
+            media,
+            volume_steps: self.volume_steps,
+        };
+        let orientation = Quat::from_euler_degrees(self.rotation);
+        let path_traced = matches!(self.render_mode, RenderMode::PathTraced { .. });
+        let samples = if self.has_motion_blur() || self.has_depth_of_field() || path_traced { self.samples } else { 1 };
+        let (open, close) = self.shutter;
+
+        for y in 0..height {
+            for x in 0..width {
+                let idx = ((y * width + x) * 4) as usize;
+                buffer[idx + 3] = 255;
+                let Some((eye, view)) = self.view_ray(x as f32 + 0.5, y as f32 + 0.5) else { continue };
+                // Where this pixel is sharp, every lens sample aims through it
+                let focus = eye + view * self.focus_distance;
 
-                for (i, obj) in objects.iter().enumerate() {
-                    if let Some((t, p, n)) = obj.intersects_ray(&ray) {
-                        if t < closest_t {
-                            closest_t = t;
-                            hit = Some((i, p, n));
-                        }
+                // Each sample jitters within its slice of the shutter interval and picks a point
+                // on the lens. Seeded by pixel so a frame renders the same every time.
+                let mut rng = Rng::new((y * width + x) as u64);
+                let mut rgb = [0.0f32; 3];
+                for s in 0..samples {
+                    let time = if self.has_motion_blur() {
+                        open + (close - open) * (s as f32 + rng.next_f32()) / samples as f32
+                    } else {
+                        open
+                    };
+                    let lens = if self.has_depth_of_field() {
+                        let (u, v) = self.aperture.sample(&mut rng);
+                        Vec3::new(u, v, 0.0) * self.aperture_radius
+                    } else {
+                        Vec3::new(0.0, 0.0, 0.0)
+                    };
+                    let origin = self.position + orientation.rotate(eye + lens);
+                    let direction = orientation.rotate(focus - eye - lens).normalized();
+                    let ray = Ray::at_time(origin, direction, time);
+                    if s == 0
+                        && let Some(aovs) = aovs.as_deref_mut()
+                        && let Some(sample) = self.aov_sample(&ray, &scene, orientation) {
+                        aovs.record(x, y, sample);
+                    }
+                    let color = self.trace(&ray, &scene, &mut rng);
+                    for (sum, c) in rgb.iter_mut().zip(color) {
+                        *sum += c.min(1.0) / samples as f32;
                     }
                 }
 
-                let idx = ((y * width + x) * 4) as usize;
-                if let Some((obj_idx, p, n)) = hit {
-                    // get all lights that aren't blocked
-                    let mut valid_lights: Vec<&dyn Light> = Vec::new();
-                    for l in lights {
-                        let light_segment = Segment::new(p, *l.position());
-                        let mut blocked = false;
-                        for obj in objects {
-                            if let Some((_t, _p, _n)) = obj.intersects_segment(&light_segment) {
-                                // light is blocked
-                                blocked = true;
-                                break;
-                            }
-                        }
-                        if !blocked {
-                            valid_lights.push(l.as_ref());
-                        }
-                    }
+                buffer[idx] = (rgb[0] * 255.0) as u8;
+                buffer[idx + 1] = (rgb[1] * 255.0) as u8;
+                buffer[idx + 2] = (rgb[2] * 255.0) as u8;
+            }
+        }
+        buffer
+    }
+
+    // Shaded colour of the closest hit, or the environment behind if the ray escapes, seen
+    // through any media in between
+    fn trace(&self, ray: &Ray, scene: &Surroundings, rng: &mut Rng) -> Rgb {
+        if let RenderMode::PathTraced { max_bounces } = self.render_mode {
+            return path_trace(ray, scene, max_bounces, rng);
+        }
+        let hit = closest_hit(ray, scene.objects, &scene.bounds);
+        let behind = match hit {
+            Some((obj_idx, _t, p, n)) => self.shade(ray, obj_idx, p, n, scene, rng),
+            None => scene.environment.radiance(ray.direction),
+        };
+        let (scattered, transmittance) = through_media(ray, hit.map_or(f32::INFINITY, |h| h.1), scene, rng);
+        [0, 1, 2].map(|i| scattered[i] + transmittance[i] * behind[i])
+    }
+
+    // What the ray hit, for the AOV passes
+    fn aov_sample(&self, ray: &Ray, scene: &Surroundings, orientation: Quat) -> Option<AovSample> {
+        let (obj_idx, _t, p, n) = closest_hit(ray, scene.objects, &scene.bounds)?;
+        let object = &scene.objects[obj_idx];
+        let material = object.material_at(&p);
+        let albedo = material.pbr.map_or([material.diffuse.r, material.diffuse.g, material.diffuse.b], |pbr| pbr.base_color());
+        // Light each lamp would give if nothing were in the way, against what gets through
+        let strength = |visible: &VisibleLight| visible.0.intensity() * arriving_color(visible).iter().sum::<f32>() / 3.0;
+        let total: f32 = scene.lights.iter().map(|l| strength(&(l.as_ref(), [1.0; 3]))).sum();
+        let reached: f32 = visible_lights(p, ray.time, scene).iter().map(strength).sum();
+        Some(AovSample {
+            depth: orientation.conjugate().rotate(p - self.position).z(),
+            normal: n,
+            albedo,
+            object_id: obj_idx,
+            shadow: if total > 0.0 { (1.0 - reached / total).clamp(0.0, 1.0) } else { 0.0 },
+            uv: object.uv(&p),
+        })
+    }
 
-                    let view_dir = (self.position - p).normalized();
-                    let material = objects[obj_idx].material();
-                    let color = shade_multi_light(n, view_dir, p, &valid_lights, material, ambient_light);
-                    buffer[idx] = (color.r * 255.0) as u8;
-                    buffer[idx + 1] = (color.g * 255.0) as u8;
-                    buffer[idx + 2] = (color.b * 255.0) as u8;
-                    buffer[idx + 3] = 255;
-                } else {
-                    buffer[idx] = 0;
-                    buffer[idx + 1] = 0;
-                    buffer[idx + 2] = 0;
-                    buffer[idx + 3] = 255;
+    // Direct and environment light reflected towards the ray origin from a hit
+    fn shade(&self, ray: &Ray, obj_idx: usize, p: Vec3, n: Vec3, scene: &Surroundings, rng: &mut Rng) -> Rgb {
+        let valid_lights = visible_lights(p, ray.time, scene);
+        let view_dir = (ray.origin - p).normalized();
+        let material = scene.objects[obj_idx].material_at(&p);
+        let mut rgb = match &material.pbr {
+            Some(pbr) => shade_pbr(n, view_dir, p, &valid_lights, pbr, scene.ambient_light),
+            None => {
+                let color = shade_multi_light(n, view_dir, p, &valid_lights, material, scene.ambient_light);
+                [color.r, color.g, color.b]
+            }
+        };
+
+        // Light from the environment: radiance * BRDF * cos over the pdf, where the Phong
+        // material's BRDF is just its diffuse albedo / pi. Outlines stay flat.
+        let outline = material.pbr.is_none() && material.shading.model().is_outline(n, view_dir);
+        if !scene.environment.is_black() && !outline {
+            let albedo = [material.diffuse.r, material.diffuse.g, material.diffuse.b];
+            for _ in 0..self.environment_samples {
+                let Some((direction, radiance, pdf)) = scene.environment.sample(n, rng) else { break };
+                let cos = n.dot(&direction);
+                if cos <= 0.0 { continue; }
+                // Glass in the way tints the sky like it tints the lamps
+                let occluder = Ray::at_time(p, direction, ray.time);
+                let through = transmittance_along(&occluder, f32::INFINITY, scene);
+                if through.iter().all(|&t| t <= 0.0) { continue; }
+                let reflected = match &material.pbr {
+                    Some(pbr) => pbr.evaluate(n, view_dir, direction),
+                    None => albedo.map(|a| a * cos / PI),
+                };
+                for (((c, f), l), t) in rgb.iter_mut().zip(reflected).zip(radiance).zip(through) {
+                    *c += f * l * t / (pdf * self.environment_samples as f32);
                 }
             }
         }
-        buffer
+        rgb
     }
 
     // Convert a point from camera (view) space into world space (translation only for now)
@@ -109,9 +391,186 @@ impl Camera {
     }
 }
 
+// What a render can see, gathered once per frame
+struct Surroundings<'a> {
+    objects: &'a [Box<dyn Hittable>],
+    bounds: Vec<Option<Aabb>>,
+    lights: &'a [Box<dyn Light>],
+    ambient_light: &'a AmbientLight,
+    environment: &'a Environment,
+    media: &'a [Medium],
+    volume_steps: u32,
+}
+
+// Share of light, per channel, that passes every object the ray crosses within `t_max`
+fn transmittance_along(ray: &Ray, t_max: f32, scene: &Surroundings) -> Rgb {
+    let mut transmittance = [1.0f32; 3];
+    for (i, obj) in scene.objects.iter().enumerate() {
+        if let Some(b) = &scene.bounds[i]
+            && !b.hit(ray, t_max) {
+            continue;
+        }
+        if let Some((t, p, _)) = obj.intersects_ray(ray)
+            && t <= t_max {
+            let t = obj.material_at(&p).transmittance;
+            for (acc, t) in transmittance.iter_mut().zip([t.r, t.g, t.b]) {
+                *acc *= t;
+            }
+            // Nothing gets through any more, the rest can't change that
+            if transmittance.iter().all(|&t| t <= 0.0) { break; }
+        }
+    }
+    transmittance
+}
+
+// Each light reaching `point` at the given time, with the transmittance of every object and
+// medium in the way. Lights behind anything opaque are left out.
+fn visible_lights<'a>(point: Vec3, time: f32, scene: &Surroundings<'a>) -> Vec<VisibleLight<'a>> {
+    let mut visible = Vec::new();
+    for l in scene.lights {
+        let to_light = *l.position() - point;
+        let shadow_ray = Ray::at_time(point, to_light.normalized(), time);
+        let mut transmittance = transmittance_along(&shadow_ray, to_light.length(), scene);
+        for medium in scene.media {
+            let through = medium.transmittance(point, *l.position(), 8);
+            for (acc, t) in transmittance.iter_mut().zip(through) {
+                *acc *= t;
+            }
+        }
+        if transmittance.iter().any(|&t| t > 0.0) {
+            visible.push((l.as_ref(), transmittance));
+        }
+    }
+    visible
+}
+
+// Light scattered towards the ray origin by the media along the first `t_max` of the ray, and
+// the share of whatever lies beyond that gets through. Marched in steps with a jittered start,
+// each step lit by the lamps it can see, so objects cast shadows into the fog. A lamp's
+// contribution is scaled so isotropic fog of scattering 1 looks as bright per unit length as a
+// white matte surface facing it.
+fn through_media(ray: &Ray, t_max: f32, scene: &Surroundings, rng: &mut Rng) -> (Rgb, Rgb) {
+    let mut scattered = [0.0f32; 3];
+    let mut transmittance = [1.0f32; 3];
+    let spans: Vec<(f32, f32)> = scene.media.iter().filter_map(|m| m.span(ray, t_max)).collect();
+    if spans.is_empty() { return (scattered, transmittance); }
+    let start = spans.iter().map(|s| s.0).fold(f32::INFINITY, f32::min);
+    let end = spans.iter().map(|s| s.1).fold(0.0, f32::max);
+    let dt = (end - start) / scene.volume_steps as f32;
+    let jitter = rng.next_f32();
+    let ambient = scene.ambient_light;
+    for step in 0..scene.volume_steps {
+        let t = start + (step as f32 + jitter) * dt;
+        let p = ray.origin + ray.direction * t;
+        let mut extinction = [0.0f32; 3];
+        let mut in_scatter = [0.0f32; 3];
+        let mut lights: Option<Vec<VisibleLight>> = None;
+        for medium in scene.media {
+            for (e, m) in extinction.iter_mut().zip(medium.extinction_at(p)) {
+                *e += m;
+            }
+            let sigma_s = medium.scattering_at(p);
+            if sigma_s.iter().all(|&s| s <= 0.0) { continue; }
+            // Shadow rays only once per step, however many media overlap here
+            let lights = lights.get_or_insert_with(|| visible_lights(p, ray.time, scene));
+            let mut incoming = [ambient.color.r, ambient.color.g, ambient.color.b].map(|c| c * ambient.intensity);
+            for visible in lights.iter() {
+                let light = visible.0;
+                let to_light = *light.position() - p;
+                let dist = to_light.length();
+                if dist <= 0.0 { continue; }
+                let phase = medium.phase(ray.direction.dot(&(to_light / dist)));
+                let scale = 4.0 * PI * phase * light.intensity() * attenuation(dist);
+                for (i, c) in incoming.iter_mut().zip(arriving_color(visible)) {
+                    *i += c * scale;
+                }
+            }
+            for ((acc, s), l) in in_scatter.iter_mut().zip(sigma_s).zip(incoming) {
+                *acc += s * l;
+            }
+        }
+        for i in 0..3 {
+            scattered[i] += transmittance[i] * in_scatter[i] * dt;
+            transmittance[i] *= (-extinction[i] * dt).exp();
+        }
+    }
+    (scattered, transmittance)
+}
+
+// One path through the scene. Each hit adds its emission and the point lights it can see, then
+// the path continues in a direction picked from the BRDF; whatever it escapes to is the
+// environment. Phong materials bounce as plain diffuse and the ambient term isn't used, the
+// bounces are the ambient light.
+fn path_trace(ray: &Ray, scene: &Surroundings, max_bounces: u32, rng: &mut Rng) -> Rgb {
+    let mut radiance = [0.0f32; 3];
+    let mut throughput = [1.0f32; 3];
+    let (mut origin, mut direction) = (ray.origin, ray.direction);
+    for bounce in 0..=max_bounces {
+        let segment_ray = Ray::at_time(origin, direction, ray.time);
+        let hit = closest_hit(&segment_ray, scene.objects, &scene.bounds);
+        let (scattered, through) = through_media(&segment_ray, hit.map_or(f32::INFINITY, |h| h.1), scene, rng);
+        for i in 0..3 {
+            radiance[i] += throughput[i] * scattered[i];
+            throughput[i] *= through[i];
+        }
+        let Some((obj_idx, _t, p, n)) = hit else {
+            let sky = scene.environment.radiance(direction);
+            for ((r, t), l) in radiance.iter_mut().zip(throughput).zip(sky) {
+                *r += t * l;
+            }
+            break;
+        };
+        let material = scene.objects[obj_idx].material_at(&p);
+        let pbr = material.pbr.unwrap_or_else(|| Pbr::from_diffuse([material.diffuse.r, material.diffuse.g, material.diffuse.b]));
+        let view = -direction;
+        // Thin and open surfaces are seen from both sides
+        let n = if n.dot(&view) < 0.0 { -n } else { n };
+
+        let lights = visible_lights(p, ray.time, scene);
+        let direct = direct_light(n, view, p, &lights, &pbr);
+        for (i, r) in radiance.iter_mut().enumerate() {
+            *r += throughput[i] * (pbr.emission()[i] + direct[i]);
+        }
+
+        if bounce == max_bounces { break; }
+        let Some((next, weight)) = pbr.sample(n, view, rng) else { break };
+        for (t, w) in throughput.iter_mut().zip(weight) {
+            *t *= w;
+        }
+        // Russian roulette: long dim paths stop early, survivors carry the lost share
+        if bounce >= 3 {
+            let survive = throughput.iter().cloned().fold(0.0, f32::max).min(0.95);
+            if rng.next_f32() >= survive { break; }
+            throughput = throughput.map(|t| t / survive);
+        }
+        (origin, direction) = (p, next);
+    }
+    radiance
+}
+
+// (object index, t, point, normal) of the nearest object along the ray
+fn closest_hit(ray: &Ray, objects: &[Box<dyn Hittable>], bounds: &[Option<Aabb>]) -> Option<(usize, f32, Vec3, Vec3)> {
+    let mut closest: Option<(usize, f32, Vec3, Vec3)> = None;
+    for (i, obj) in objects.iter().enumerate() {
+        let closest_t = closest.map_or(f32::INFINITY, |c| c.1);
+        if let Some(b) = &bounds[i]
+            && !b.hit(ray, closest_t) {
+            continue;
+        }
+        if let Some((t, p, n)) = obj.intersects_ray(ray) {
+            if t < closest_t {
+                closest = Some((i, t, p, n));
+            }
+        }
+    }
+    closest
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::color::Color;
+    use crate::light::PointLight;
 
     #[test]
     fn camera_to_world_identity() {
@@ -145,4 +604,383 @@ mod tests {
         let p_cam2 = cam.world_to_camera(p_world);
         assert_eq!(p_cam2, p_cam);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn shutter_smears_moving_objects() {
+        use crate::light::PointLight;
+        use crate::material::Material;
+        use crate::objects::moving::Moving;
+        use crate::objects::sphere::Sphere;
+
+        // Bright sphere sweeping left to right across the middle row while the shutter is open
+        let white = Color::new(1.0, 1.0, 1.0, 1.0);
+        let material = Material::new(white, white, white, 32.0);
+        let sphere = Sphere::new(1.0, Vec3::new(-3.0, 0.0, 10.0), material);
+        let objects: Vec<Box<dyn Hittable>> = vec![Box::new(Moving::new(Box::new(sphere), Vec3::new(6.0, 0.0, 0.0), (0.0, 1.0)))];
+        let lights: Vec<Box<dyn Light>> = vec![Box::new(PointLight::new(Vec3::new(0.0, 0.0, 0.0), white, 1.0))];
+        let ambient = AmbientLight::new(white, 0.0);
+        let mut cam = Camera::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 0.0), 60.0, (32, 32));
+        let row = |cam: &Camera| -> Vec<u8> { cam.render_scene(&objects, &lights, &ambient, &Environment::Black, &[])[16 * 32 * 4..17 * 32 * 4].chunks(4).map(|p| p[0]).collect() };
+
+        // Closed shutter: sharp sphere at the start position only
+        let sharp = row(&cam);
+        let peak = *sharp.iter().max().unwrap();
+        assert!(sharp[16] == 0 && sharp[7] == peak && peak > 50);
+
+        // Open: every pixel along the path is partly covered, none as bright as the still sphere
+        cam.set_shutter(0.0, 1.0);
+        cam.set_samples(16);
+        let blurred = row(&cam);
+        for (x, value) in blurred.iter().enumerate().take(24).skip(8) {
+            assert!(*value > 5 && *value < peak / 4 * 3, "pixel {} is {}", x, value);
+        }
+        // And the same every time
+        assert_eq!(blurred, row(&cam));
+    }
+
+    #[test]
+    fn aperture_samples_stay_on_the_opening() {
+        let mut rng = Rng::new(3);
+        let hexagon = Aperture::Blades(6);
+        let apothem = (std::f32::consts::PI / 6.0).cos();
+        let mut mean = (0.0, 0.0);
+        for _ in 0..2000 {
+            let (u, v) = hexagon.sample(&mut rng);
+            // Inside every edge of the hexagon with corners on the unit circle
+            for k in 0..6 {
+                let mid = (k as f32 + 0.5) * std::f32::consts::PI / 3.0;
+                assert!(u * mid.cos() + v * mid.sin() <= apothem + 1e-5);
+            }
+            mean = (mean.0 + u / 2000.0, mean.1 + v / 2000.0);
+            let (u, v) = Aperture::Circle.sample(&mut rng);
+            assert!(u * u + v * v <= 1.0 + 1e-5);
+        }
+        assert!(mean.0.abs() < 0.05 && mean.1.abs() < 0.05);
+    }
+
+    #[test]
+    fn only_the_focal_plane_stays_sharp() {
+        use crate::light::PointLight;
+        use crate::material::Material;
+        use crate::objects::sphere::Sphere;
+
+        let white = Color::new(1.0, 1.0, 1.0, 1.0);
+        let material = Material::new(white, white, white, 32.0);
+        let objects: Vec<Box<dyn Hittable>> = vec![Box::new(Sphere::new(1.0, Vec3::new(0.0, 0.0, 10.0), material))];
+        let lights: Vec<Box<dyn Light>> = vec![Box::new(PointLight::new(Vec3::new(0.0, 0.0, 0.0), white, 1.0))];
+        let ambient = AmbientLight::new(white, 0.0);
+        let mut cam = Camera::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 0.0), 30.0, (32, 32));
+        let row = |cam: &Camera| -> Vec<i32> { cam.render_scene(&objects, &lights, &ambient, &Environment::Black, &[])[16 * 32 * 4..17 * 32 * 4].chunks(4).map(|p| p[0] as i32).collect() };
+        let sharp = row(&cam);
+        let silhouette = sharp.iter().filter(|v| **v > 0).count();
+
+        // Wide open but focused on the sphere: nearly the same picture
+        cam.set_aperture_radius(0.3);
+        cam.set_aperture(Aperture::Blades(5));
+        cam.set_samples(32);
+        let distance = cam.focus_on_pixel(16, 16, &objects).expect("sphere under the centre");
+        assert!((distance - 9.0).abs() < 0.01, "{}", distance);
+        let focused = row(&cam);
+        assert!(sharp.iter().zip(&focused).all(|(a, b)| (a - b).abs() < 40), "{:?} vs {:?}", sharp, focused);
+
+        // Focused well in front of it: the sphere spreads out over more pixels
+        cam.set_focus_distance(3.0);
+        let blurred = row(&cam);
+        assert!(blurred.iter().filter(|v| **v > 0).count() > silhouette + 2, "{:?} vs {:?}", sharp, blurred);
+
+        // Nothing under the corner pixel, focus stays put
+        assert_eq!(cam.focus_on_pixel(0, 0, &objects), None);
+        assert_eq!(cam.focus_distance, 3.0);
+    }
+
+    fn assert_ray(cam: &Camera, px: f32, py: f32, origin: Vec3, direction: Vec3) {
+        let ray = cam.primary_ray(px, py).expect("pixel has a ray");
+        assert!((ray.origin - origin).length() < 1e-4, "origin at ({}, {}) is {:?}", px, py, ray.origin);
+        assert!((ray.direction - direction.normalized()).length() < 1e-4, "direction at ({}, {}) is {:?}", px, py, ray.direction);
+    }
+
+    #[test]
+    fn perspective_pixels_map_through_the_fov() {
+        let eye = Vec3::new(1.0, 2.0, 3.0);
+        let mut cam = Camera::new(eye, Vec3::new(0.0, 0.0, 0.0), 90.0, (200, 100));
+        assert_ray(&cam, 100.0, 50.0, eye, Vec3::new(0.0, 0.0, 1.0));
+        assert_ray(&cam, 100.0, 0.0, eye, Vec3::new(0.0, 1.0, 1.0));
+        assert_ray(&cam, 200.0, 50.0, eye, Vec3::new(2.0, 0.0, 1.0));
+        // Turned to face +X
+        cam.set_rotation(Vec3::new(0.0, 90.0, 0.0));
+        assert_ray(&cam, 100.0, 50.0, eye, Vec3::new(1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn orthographic_rays_are_parallel() {
+        let eye = Vec3::new(1.0, 2.0, 3.0);
+        let mut cam = Camera::new(eye, Vec3::new(0.0, 0.0, 0.0), 90.0, (200, 100));
+        cam.set_projection(Projection::Orthographic { height: 4.0 });
+        let forward = Vec3::new(0.0, 0.0, 1.0);
+        assert_ray(&cam, 100.0, 50.0, eye, forward);
+        assert_ray(&cam, 200.0, 50.0, eye + Vec3::new(4.0, 0.0, 0.0), forward);
+        assert_ray(&cam, 0.0, 0.0, eye + Vec3::new(-4.0, 2.0, 0.0), forward);
+    }
+
+    #[test]
+    fn fisheye_angle_grows_linearly_with_radius() {
+        let eye = Vec3::new(0.0, 0.0, 0.0);
+        let mut cam = Camera::new(eye, Vec3::new(0.0, 0.0, 0.0), 180.0, (200, 100));
+        cam.set_projection(Projection::Fisheye);
+        assert_ray(&cam, 100.0, 50.0, eye, Vec3::new(0.0, 0.0, 1.0));
+        // Halfway to the rim is 45 degrees, the rim itself 90
+        assert_ray(&cam, 125.0, 50.0, eye, Vec3::new(1.0, 0.0, 1.0));
+        assert_ray(&cam, 150.0, 50.0, eye, Vec3::new(1.0, 0.0, 0.0));
+        assert_ray(&cam, 100.0, 0.0, eye, Vec3::new(0.0, 1.0, 0.0));
+        // No image outside the circle
+        assert!(cam.primary_ray(0.5, 0.5).is_none());
+        assert!(cam.primary_ray(190.0, 50.0).is_none());
+    }
+
+    #[test]
+    fn equirectangular_covers_the_whole_sphere() {
+        let eye = Vec3::new(0.0, 0.0, 0.0);
+        let mut cam = Camera::new(eye, Vec3::new(0.0, 0.0, 0.0), 60.0, (200, 100));
+        cam.set_projection(Projection::Equirectangular);
+        assert_ray(&cam, 100.0, 50.0, eye, Vec3::new(0.0, 0.0, 1.0));
+        assert_ray(&cam, 150.0, 50.0, eye, Vec3::new(1.0, 0.0, 0.0));
+        assert_ray(&cam, 50.0, 50.0, eye, Vec3::new(-1.0, 0.0, 0.0));
+        assert_ray(&cam, 0.0, 50.0, eye, Vec3::new(0.0, 0.0, -1.0));
+        assert_ray(&cam, 100.0, 0.0, eye, Vec3::new(0.0, 1.0, 0.0));
+        assert_ray(&cam, 50.0, 25.0, eye, Vec3::new(-1.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn environment_fills_the_background_and_lights_surfaces() {
+        use crate::environment::EnvironmentMap;
+        use crate::material::Material;
+        use crate::objects::sphere::Sphere;
+
+        // Uniform grey surroundings and a matte sphere, no lights
+        let environment = Environment::Map(EnvironmentMap::new(8, 4, vec![[0.5, 0.5, 0.5]; 32]));
+        let black = Color::new(0.0, 0.0, 0.0, 1.0);
+        let albedo = Color::new(0.8, 0.8, 0.8, 1.0);
+        let objects: Vec<Box<dyn Hittable>> = vec![Box::new(Sphere::new(1.0, Vec3::new(0.0, 0.0, 10.0), Material::new(black, albedo, black, 1.0)))];
+        let ambient = AmbientLight::new(black, 0.0);
+        let mut cam = Camera::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 0.0), 30.0, (9, 9));
+        cam.set_environment_samples(512);
+        let image = cam.render_scene(&objects, &[], &ambient, &environment, &[]);
+        let red = |x: usize, y: usize| image[(y * 9 + x) * 4] as f32;
+        // Background is the map itself
+        assert!((red(0, 0) - 127.0).abs() <= 1.0, "{}", red(0, 0));
+        // A diffuse surface under uniform light L reflects albedo * L
+        assert!((red(4, 4) - 0.8 * 0.5 * 255.0).abs() < 10.0, "{}", red(4, 4));
+        // Black stays black
+        let dark = cam.render_scene(&objects, &[], &ambient, &Environment::Black, &[]);
+        assert_eq!(dark[(4 * 9 + 4) * 4], 0);
+    }
+
+    #[test]
+    fn toon_outlines_stay_black_under_the_sky() {
+        use crate::environment::Sky;
+        use crate::material::Material;
+        use crate::objects::sphere::Sphere;
+        use crate::shading::{Shading, Toon};
+
+        let environment = Environment::Sky(Sky::new([0.4, 0.6, 1.0], [0.9, 0.9, 0.9], [0.3, 0.3, 0.3]));
+        let material = Material { shading: Shading::Toon(Toon { bands: 3, outline: 0.3 }), ..Material::default() };
+        let objects: Vec<Box<dyn Hittable>> = vec![Box::new(Sphere::new(1.0, Vec3::new(0.0, 0.0, 5.0), material))];
+        let ambient = AmbientLight::new(Color::new(0.0, 0.0, 0.0, 1.0), 0.0);
+        let cam = Camera::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 0.0), 30.0, (33, 33));
+        let image = cam.render_scene(&objects, &[], &ambient, &environment, &[]);
+        let rgb = |x: usize, y: usize| &image[(y * 33 + x) * 4..(y * 33 + x) * 4 + 3];
+        // The middle faces the camera and catches the sky, the rim of the sphere is outline
+        assert!(rgb(16, 16).iter().any(|&c| c > 20), "{:?}", rgb(16, 16));
+        let rim = (0..33).find(|&x| rgb(x, 16) != rgb(0, 16)).unwrap();
+        assert_eq!(rgb(rim, 16), &[0, 0, 0]);
+    }
+
+    #[test]
+    fn path_traced_diffuse_sphere_reflects_its_albedo() {
+        use crate::environment::EnvironmentMap;
+        use crate::material::Material;
+        use crate::objects::sphere::Sphere;
+
+        let environment = Environment::Map(EnvironmentMap::new(8, 4, vec![[0.5, 0.5, 0.5]; 32]));
+        let black = Color::new(0.0, 0.0, 0.0, 1.0);
+        let mut pbr = Pbr::new([0.8, 0.8, 0.8]);
+        pbr.set_roughness(1.0);
+        pbr.set_specular(0.0);
+        let objects: Vec<Box<dyn Hittable>> = vec![Box::new(Sphere::new(1.0, Vec3::new(0.0, 0.0, 10.0), Material::from_pbr(pbr)))];
+        let ambient = AmbientLight::new(black, 0.0);
+        let mut cam = Camera::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 0.0), 30.0, (9, 9));
+        cam.set_render_mode(RenderMode::PathTraced { max_bounces: 4 });
+        cam.set_samples(512);
+        let image = cam.render_scene(&objects, &[], &ambient, &environment, &[]);
+        let red = image[(4 * 9 + 4) * 4] as f32;
+        assert!((red - 0.8 * 0.5 * 255.0).abs() < 10.0, "{}", red);
+    }
+
+    #[test]
+    fn rough_pbr_matches_phong_diffuse_under_a_light() {
+        use crate::light::PointLight;
+        use crate::material::Material;
+        use crate::objects::sphere::Sphere;
+
+        let black = Color::new(0.0, 0.0, 0.0, 1.0);
+        let grey = Color::new(0.6, 0.6, 0.6, 1.0);
+        let mut pbr = Pbr::from_diffuse([0.6, 0.6, 0.6]);
+        pbr.set_emission([0.0, 0.0, 0.0]);
+        let lights: Vec<Box<dyn Light>> = vec![Box::new(PointLight::new(Vec3::new(0.0, 0.0, 0.0), Color::new(1.0, 1.0, 1.0, 1.0), 1.0))];
+        let ambient = AmbientLight::new(black, 0.0);
+        let cam = Camera::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 0.0), 30.0, (9, 9));
+        let render = |material| {
+            let objects: Vec<Box<dyn Hittable>> = vec![Box::new(Sphere::new(1.0, Vec3::new(0.0, 0.0, 5.0), material))];
+            cam.render_scene(&objects, &lights, &ambient, &Environment::Black, &[])[(4 * 9 + 4) * 4] as i32
+        };
+        let phong = render(Material::new(black, grey, black, 1.0));
+        let physical = render(Material::from_pbr(pbr));
+        assert!(phong > 50);
+        assert!((phong - physical).abs() <= 3, "{} vs {}", phong, physical);
+    }
+
+    #[test]
+    fn shadows_take_the_colour_of_what_they_pass_through() {
+        use crate::material::Material;
+        use crate::objects::sphere::Sphere;
+
+        let lights: Vec<Box<dyn Light>> = vec![Box::new(PointLight::new(Vec3::new(0.0, 0.0, 0.0), Color::new(1.0, 1.0, 1.0, 1.0), 1.0))];
+        let ambient = AmbientLight::new(Color::new(0.0, 0.0, 0.0, 1.0), 0.0);
+        let sphere = |z: f32, transmittance: Color| -> Box<dyn Hittable> {
+            let material = Material { transmittance, ..Material::default() };
+            Box::new(Sphere::new(1.0, Vec3::new(0.0, 0.0, z), material))
+        };
+        let light_at = |objects: &[Box<dyn Hittable>]| {
+            let scene = Surroundings {
+                objects,
+                bounds: objects.iter().map(|o| o.bounding_box()).collect(),
+                lights: &lights,
+                ambient_light: &ambient,
+                environment: &Environment::Black,
+                media: &[],
+                volume_steps: 1,
+            };
+            visible_lights(Vec3::new(0.0, 0.0, 20.0), 0.0, &scene).iter().map(arriving_color).collect::<Vec<_>>()
+        };
+        let amber = Color::new(1.0, 0.5, 0.0, 1.0);
+        let black = Color::new(0.0, 0.0, 0.0, 1.0);
+        assert!(light_at(&[sphere(10.0, black)]).is_empty());
+        let through_glass = light_at(&[sphere(10.0, amber)]);
+        assert_eq!(through_glass.len(), 1);
+        assert_eq!(through_glass[0], [1.0, 0.5, 0.0]);
+        // Each pane in the way filters again
+        let grey = Color::new(0.5, 0.5, 0.5, 1.0);
+        let twice = light_at(&[sphere(5.0, grey), sphere(10.0, amber)]);
+        assert_eq!(twice[0], [0.5, 0.25, 0.0]);
+    }
+
+    #[test]
+    fn glass_tints_the_sky_light_too() {
+        use crate::environment::EnvironmentMap;
+        use crate::material::Material;
+        use crate::objects::sphere::Sphere;
+
+        // A matte ball inside a bigger shell, shaded at the point facing the camera
+        let environment = Environment::Map(EnvironmentMap::new(8, 4, vec![[0.5, 0.5, 0.5]; 32]));
+        let ambient = AmbientLight::new(Color::new(0.0, 0.0, 0.0, 1.0), 0.0);
+        let mut cam = Camera::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 0.0), 30.0, (9, 9));
+        cam.set_environment_samples(64);
+        let ball = || -> Box<dyn Hittable> { Box::new(Sphere::new(1.0, Vec3::new(0.0, 0.0, 10.0), Material::default())) };
+        let shell = |transmittance| -> Box<dyn Hittable> {
+            Box::new(Sphere::new(3.0, Vec3::new(0.0, 0.0, 10.0), Material { transmittance, ..Material::default() }))
+        };
+        let lit = |objects: &[Box<dyn Hittable>]| {
+            let scene = Surroundings {
+                objects,
+                bounds: objects.iter().map(|o| o.bounding_box()).collect(),
+                lights: &[],
+                ambient_light: &ambient,
+                environment: &environment,
+                media: &[],
+                volume_steps: 1,
+            };
+            let ray = Ray::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 1.0));
+            cam.shade(&ray, 0, Vec3::new(0.0, 0.0, 9.0), Vec3::new(0.0, 0.0, -1.0), &scene, &mut Rng::new(7))[2]
+        };
+        let open = lit(&[ball()]);
+        let tinted = lit(&[ball(), shell(Color::new(0.5, 0.5, 0.5, 1.0))]);
+        let opaque = lit(&[ball(), shell(Color::new(0.0, 0.0, 0.0, 1.0))]);
+        assert!(open > 0.1, "{}", open);
+        assert!((tinted - 0.5 * open).abs() < 1e-5, "{} vs {}", tinted, open);
+        assert_eq!(opaque, 0.0);
+    }
+
+    #[test]
+    fn fog_glows_around_lights_and_objects_cast_shafts_into_it() {
+        use crate::material::Material;
+        use crate::objects::sphere::Sphere;
+
+        let lights: Vec<Box<dyn Light>> = vec![Box::new(PointLight::new(Vec3::new(0.0, 4.0, 10.0), Color::new(1.0, 1.0, 1.0, 1.0), 1.0))];
+        let ambient = AmbientLight::new(Color::new(0.0, 0.0, 0.0, 1.0), 0.0);
+        let fog = vec![Medium::fog([0.0; 3], [0.05; 3])];
+        let cam = Camera::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 0.0), 30.0, (9, 9));
+        // Empty scene: the black background glows only where there is fog to scatter the light
+        let clear = cam.render_scene(&[], &lights, &ambient, &Environment::Black, &[]);
+        let hazy = cam.render_scene(&[], &lights, &ambient, &Environment::Black, &fog);
+        assert_eq!(clear[(4 * 9 + 4) * 4], 0);
+        assert!(hazy[(4 * 9 + 4) * 4] > 10);
+
+        // A ray passing under the light, with and without a ball shading part of it
+        let scattered = |objects: &[Box<dyn Hittable>]| {
+            let scene = Surroundings {
+                objects,
+                bounds: objects.iter().map(|o| o.bounding_box()).collect(),
+                lights: &lights,
+                ambient_light: &ambient,
+                environment: &Environment::Black,
+                media: &fog,
+                volume_steps: 64,
+            };
+            let ray = Ray::new(Vec3::new(-10.0, 0.0, 10.0), Vec3::new(1.0, 0.0, 0.0));
+            through_media(&ray, 20.0, &scene, &mut Rng::new(1))
+        };
+        let (open, transmittance) = scattered(&[]);
+        let blocker: Vec<Box<dyn Hittable>> = vec![Box::new(Sphere::new(1.0, Vec3::new(0.0, 2.0, 10.0), Material::default()))];
+        let (shaded, _) = scattered(&blocker);
+        assert!(shaded[0] < open[0] * 0.9, "{} vs {}", shaded[0], open[0]);
+        // Twenty units of fog at 0.05 leave e^-1
+        assert!((transmittance[0] - (-1.0f32).exp()).abs() < 1e-3);
+    }
+
+    #[test]
+    fn aovs_record_the_primary_hit() {
+        use crate::aov::Aov;
+        use crate::material::Material;
+        use crate::objects::sphere::Sphere;
+
+        // A red ball in front of a bigger one, which it shadows from a light off to the side
+        let red = Color::new(1.0, 0.0, 0.0, 1.0);
+        let front = Material { diffuse: red, ..Material::default() };
+        let objects: Vec<Box<dyn Hittable>> = vec![
+            Box::new(Sphere::new(1.0, Vec3::new(0.0, 0.0, 5.0), front)),
+            Box::new(Sphere::new(20.0, Vec3::new(0.0, 0.0, 40.0), Material::default())),
+        ];
+        let lights: Vec<Box<dyn Light>> = vec![Box::new(PointLight::new(Vec3::new(-3.0, 0.0, 0.0), Color::new(1.0, 1.0, 1.0, 1.0), 1.0))];
+        let ambient = AmbientLight::new(Color::new(0.0, 0.0, 0.0, 1.0), 0.0);
+        let cam = Camera::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 0.0), 60.0, (9, 9));
+        let (image, aovs) = cam.render_scene_with_aovs(&objects, &lights, &ambient, &Environment::Black, &[]);
+        // The beauty pass is unchanged
+        assert_eq!(image, cam.render_scene(&objects, &lights, &ambient, &Environment::Black, &[]));
+        let centre = 4 * 9 + 4;
+        assert!((aovs.depth()[centre] - 4.0).abs() < 1e-3);
+        assert!((aovs.normal()[centre] - Vec3::new(0.0, 0.0, -1.0)).length() < 1e-3);
+        assert_eq!(aovs.albedo()[centre], [1.0, 0.0, 0.0]);
+        assert_eq!(aovs.object_id()[centre], Some(0));
+        assert_eq!(aovs.shadow()[centre], 0.0);
+        let (u, v) = aovs.uv()[centre];
+        let (eu, ev) = objects[0].uv(&Vec3::new(0.0, 0.0, 4.0));
+        assert!((u - eu).abs() < 1e-3 && (v - ev).abs() < 1e-3, "({}, {}) vs ({}, {})", u, v, eu, ev);
+        // The big ball shows around it, partly in the small one's shadow
+        let big: Vec<usize> = (0..81).filter(|&i| aovs.object_id()[i] == Some(1)).collect();
+        assert!(big.iter().all(|&i| aovs.depth()[i] > 19.0));
+        let shadowed: Vec<usize> = big.iter().cloned().filter(|&i| aovs.shadow()[i] == 1.0).collect();
+        assert!(!shadowed.is_empty() && shadowed.len() < big.len());
+        assert_eq!(aovs.to_rgba(Aov::ShadowMask)[shadowed[0] * 4], 255);
+        assert_eq!(image[shadowed[0] * 4 + 2], 0);
+    }
+}
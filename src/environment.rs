@@ -0,0 +1,280 @@
+use std::f32::consts::PI;
+use std::path::Path;
+use crate::rng::Rng;
+use crate::vec3::Vec3;
+
+// Linear radiance, unclamped so bright skies and suns can go well above 1
+pub(crate) type Rgb = [f32; 3];
+
+fn luminance(c: Rgb) -> f32 {
+    0.2126 * c[0] + 0.7152 * c[1] + 0.0722 * c[2]
+}
+
+// What rays that miss everything see, and light arriving from far away
+#[derive(Debug, Clone, Default)]
+pub(crate) enum Environment {
+    #[default]
+    Black,
+    Sky(Sky),
+    #[allow(dead_code, reason = "the demo lights with the procedural sky; main.rs notes how to swap in a photo")]
+    Map(EnvironmentMap),
+}
+
+impl Environment {
+    pub(crate) fn is_black(&self) -> bool { matches!(self, Environment::Black) }
+
+    pub(crate) fn radiance(&self, direction: Vec3) -> Rgb {
+        match self {
+            Environment::Black => [0.0; 3],
+            Environment::Sky(sky) => sky.radiance(direction),
+            Environment::Map(map) => map.radiance(direction),
+        }
+    }
+
+    // A direction to gather light from at a surface with this normal, with the radiance that
+    // way and the probability density (per steradian) of having picked it
+    pub(crate) fn sample(&self, normal: Vec3, rng: &mut Rng) -> Option<(Vec3, Rgb, f32)> {
+        match self {
+            Environment::Black => None,
+            // A smooth sky is best matched by the cosine falloff of a diffuse surface
+            Environment::Sky(sky) => {
+                let (x, y) = rng.in_unit_disc();
+                let z = (1.0 - x * x - y * y).max(0.0).sqrt();
+                let (t, b) = normal.orthonormal_basis();
+                let direction = (t * x + b * y + normal * z).normalized();
+                Some((direction, sky.radiance(direction), z.max(1e-6) / PI))
+            }
+            // Maps can have a small bright sun, so they pick their bright pixels more often
+            Environment::Map(map) => map.sample(rng),
+        }
+    }
+}
+
+// Procedural gradient from the horizon up to the zenith, with a flat ground colour below
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) struct Sky {
+    pub zenith: Rgb,
+    pub horizon: Rgb,
+    pub ground: Rgb,
+}
+
+impl Sky {
+    pub(crate) fn new(zenith: Rgb, horizon: Rgb, ground: Rgb) -> Self {
+        Sky { zenith, horizon, ground }
+    }
+
+    pub(crate) fn radiance(&self, direction: Vec3) -> Rgb {
+        let up = direction.normalized().y();
+        if up < 0.0 {
+            // Blend into the ground just below the horizon so there's no hard line
+            let t = (-up * 10.0).min(1.0);
+            return [0, 1, 2].map(|i| self.horizon[i] + (self.ground[i] - self.horizon[i]) * t);
+        }
+        let t = up.sqrt();
+        [0, 1, 2].map(|i| self.horizon[i] + (self.zenith[i] - self.horizon[i]) * t)
+    }
+}
+
+impl Default for Sky {
+    // Clear daytime sky
+    fn default() -> Self {
+        Sky::new([0.25, 0.45, 0.9], [0.85, 0.9, 1.0], [0.3, 0.27, 0.22])
+    }
+}
+
+// Equirectangular image of the surroundings, the same mapping as Projection::Equirectangular:
+// +Z in the middle, longitude across and latitude down. Holds tables for picking directions in
+// proportion to their brightness.
+#[derive(Debug, Clone)]
+pub(crate) struct EnvironmentMap {
+    width: usize,
+    height: usize,
+    pixels: Vec<Rgb>,
+    intensity: f32,
+    row_cdf: Vec<f32>,    // height + 1 entries, running total of row weights from 0 to 1
+    column_cdf: Vec<f32>, // (width + 1) per row, running totals within each row from 0 to 1
+}
+
+impl EnvironmentMap {
+    // Reads a Radiance .hdr (RGBE) file, or anything else the image crate can open
+    #[allow(dead_code, reason = "the demo ships no .hdr file to open")]
+    pub(crate) fn load(path: impl AsRef<Path>) -> image::ImageResult<Self> {
+        let image = image::open(path)?.to_rgb32f();
+        let (width, height) = image.dimensions();
+        let pixels = image.pixels().map(|p| p.0).collect();
+        Ok(EnvironmentMap::new(width as usize, height as usize, pixels))
+    }
+
+    pub(crate) fn new(width: usize, height: usize, pixels: Vec<Rgb>) -> Self {
+        assert!(width > 0 && height > 0 && pixels.len() == width * height, "pixels must fill width x height");
+        let mut map = EnvironmentMap { width, height, pixels, intensity: 1.0, row_cdf: Vec::new(), column_cdf: Vec::new() };
+        map.build_tables();
+        map
+    }
+
+    // Scales everything the map gives out, like an exposure setting
+    #[allow(dead_code, reason = "exposure for maps that come out too bright or dark; the demo has no map")]
+    pub(crate) fn set_intensity(&mut self, intensity: f32) {
+        self.intensity = intensity.max(0.0);
+    }
+
+    // Pixel weights are brightness times the solid angle the pixel covers, which shrinks
+    // towards the poles with sin(theta)
+    fn build_tables(&mut self) {
+        let (w, h) = (self.width, self.height);
+        self.column_cdf = vec![0.0; (w + 1) * h];
+        let mut row_weights = Vec::with_capacity(h);
+        for y in 0..h {
+            let sin_theta = (PI * (y as f32 + 0.5) / h as f32).sin();
+            let row = &mut self.column_cdf[y * (w + 1)..(y + 1) * (w + 1)];
+            for x in 0..w {
+                row[x + 1] = row[x] + luminance(self.pixels[y * w + x]).max(0.0) * sin_theta;
+            }
+            let total = row[w];
+            row_weights.push(total);
+            for c in row.iter_mut() {
+                *c = if total > 0.0 { *c / total } else { 0.0 };
+            }
+        }
+        self.row_cdf = std::iter::once(0.0)
+            .chain(row_weights.iter().scan(0.0, |sum, w| { *sum += w; Some(*sum) }))
+            .collect();
+        let total = self.row_cdf[h];
+        for c in self.row_cdf.iter_mut() {
+            *c = if total > 0.0 { *c / total } else { 0.0 };
+        }
+    }
+
+    fn pixel(&self, x: usize, y: usize) -> Rgb {
+        self.pixels[y.min(self.height - 1) * self.width + x.min(self.width - 1)].map(|c| c * self.intensity)
+    }
+
+    // Image coordinates in [0, 1) of a direction
+    fn uv(direction: Vec3) -> (f32, f32) {
+        let d = direction.normalized();
+        let u = 0.5 + d.x().atan2(d.z()) / (2.0 * PI);
+        let v = 0.5 - d.y().clamp(-1.0, 1.0).asin() / PI;
+        (u.rem_euclid(1.0), v.clamp(0.0, 0.999_999))
+    }
+
+    fn direction(u: f32, v: f32) -> Vec3 {
+        let longitude = (u - 0.5) * 2.0 * PI;
+        let latitude = (0.5 - v) * PI;
+        let (sin_lon, cos_lon) = longitude.sin_cos();
+        let (sin_lat, cos_lat) = latitude.sin_cos();
+        Vec3::new(cos_lat * sin_lon, sin_lat, cos_lat * cos_lon)
+    }
+
+    pub(crate) fn radiance(&self, direction: Vec3) -> Rgb {
+        let (u, v) = EnvironmentMap::uv(direction);
+        self.pixel((u * self.width as f32) as usize, (v * self.height as f32) as usize)
+    }
+
+    // Probability density per steradian that sample() picks `direction`
+    pub(crate) fn pdf(&self, direction: Vec3) -> f32 {
+        let (u, v) = EnvironmentMap::uv(direction);
+        let (x, y) = ((u * self.width as f32) as usize, (v * self.height as f32) as usize);
+        let (x, y) = (x.min(self.width - 1), y.min(self.height - 1));
+        let row = &self.column_cdf[y * (self.width + 1)..];
+        let p = (self.row_cdf[y + 1] - self.row_cdf[y]) * (row[x + 1] - row[x]);
+        let sin_theta = (PI * (y as f32 + 0.5) / self.height as f32).sin();
+        p * (self.width * self.height) as f32 / (2.0 * PI * PI * sin_theta.max(1e-6))
+    }
+
+    // Picks a row by its share of the total, then a pixel within the row, then a point within
+    // the pixel. None for an all black map.
+    pub(crate) fn sample(&self, rng: &mut Rng) -> Option<(Vec3, Rgb, f32)> {
+        if self.row_cdf[self.height] <= 0.0 { return None; }
+        let pick = |cdf: &[f32], r: f32| cdf.partition_point(|c| *c <= r).clamp(1, cdf.len() - 1) - 1;
+        let y = pick(&self.row_cdf, rng.next_f32());
+        let row = &self.column_cdf[y * (self.width + 1)..(y + 1) * (self.width + 1)];
+        let x = pick(row, rng.next_f32());
+        let u = (x as f32 + rng.next_f32()) / self.width as f32;
+        let v = (y as f32 + rng.next_f32()) / self.height as f32;
+        let direction = EnvironmentMap::direction(u, v);
+        let pdf = self.pdf(direction);
+        if pdf <= 0.0 { return None; }
+        Some((direction, self.pixel(x, y), pdf))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Dim grey everywhere except one bright pixel
+    fn sun_map() -> EnvironmentMap {
+        let (w, h) = (16, 8);
+        let mut pixels = vec![[0.1, 0.1, 0.1]; w * h];
+        pixels[2 * w + 12] = [500.0, 450.0, 400.0];
+        EnvironmentMap::new(w, h, pixels)
+    }
+
+    #[test]
+    fn loads_radiance_hdr_files() {
+        let path = std::env::temp_dir().join(format!("environment_{}.hdr", std::process::id()));
+        let (w, h) = (8, 4);
+        let pixels: Vec<image::Rgb<f32>> = (0..w * h).map(|i| image::Rgb([i as f32, 2.0, 0.5])).collect();
+        image::codecs::hdr::HdrEncoder::new(std::fs::File::create(&path).unwrap()).encode(&pixels, w, h).unwrap();
+        let map = EnvironmentMap::load(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        assert_eq!((map.width, map.height), (8, 4));
+        // RGBE keeps about 8 bits of mantissa, values above 1 survive
+        let forward = map.radiance(Vec3::new(0.0, -0.2, 1.0));
+        assert!((forward[0] - 20.0).abs() < 0.2 && (forward[1] - 2.0).abs() < 0.02, "{:?}", forward);
+    }
+
+    #[test]
+    fn directions_round_trip_through_the_image() {
+        for (u, v) in [(0.5, 0.5), (0.75, 0.5), (0.1, 0.3), (0.9, 0.8)] {
+            let (u2, v2) = EnvironmentMap::uv(EnvironmentMap::direction(u, v));
+            assert!((u - u2).abs() < 1e-4 && (v - v2).abs() < 1e-4);
+        }
+        assert!((EnvironmentMap::direction(0.5, 0.5) - Vec3::new(0.0, 0.0, 1.0)).length() < 1e-5);
+        assert!((EnvironmentMap::direction(0.75, 0.5) - Vec3::new(1.0, 0.0, 0.0)).length() < 1e-5);
+    }
+
+    #[test]
+    fn importance_sampling_finds_the_sun_and_stays_unbiased() {
+        let map = sun_map();
+        let mut rng = Rng::new(5);
+        let n = 20000;
+        let mut sun_hits = 0;
+        let mut estimate = 0.0;
+        for _ in 0..n {
+            let (direction, radiance, pdf) = map.sample(&mut rng).unwrap();
+            assert_eq!(radiance, map.radiance(direction));
+            if radiance[0] > 100.0 { sun_hits += 1; }
+            estimate += luminance(radiance) / pdf / n as f32;
+        }
+        // Most samples go to the one bright pixel
+        assert!(sun_hits > n * 9 / 10, "{}", sun_hits);
+        // Total light over the sphere matches a brute force sum
+        let mut exact = 0.0;
+        let (w, h) = (map.width, map.height);
+        for y in 0..h {
+            let theta = PI * (y as f32 + 0.5) / h as f32;
+            for x in 0..w {
+                exact += luminance(map.pixel(x, y)) * theta.sin() * (2.0 * PI / w as f32) * (PI / h as f32);
+            }
+        }
+        assert!((estimate - exact).abs() / exact < 0.02, "{} vs {}", estimate, exact);
+    }
+
+    #[test]
+    fn sky_fades_from_horizon_to_zenith() {
+        let sky = Sky::default();
+        let close = |a: Rgb, b: Rgb| a.iter().zip(b).all(|(x, y)| (x - y).abs() < 1e-5);
+        assert!(close(sky.radiance(Vec3::new(0.0, 1.0, 0.0)), sky.zenith));
+        assert!(close(sky.radiance(Vec3::new(1.0, 0.0, 0.0)), sky.horizon));
+        assert!(close(sky.radiance(Vec3::new(0.0, -1.0, 0.0)), sky.ground));
+        let environment = Environment::Sky(sky);
+        let mut rng = Rng::new(1);
+        let normal = Vec3::new(0.0, 1.0, 0.0);
+        for _ in 0..100 {
+            let (direction, _radiance, pdf) = environment.sample(normal, &mut rng).unwrap();
+            assert!(direction.dot(&normal) >= 0.0 && pdf > 0.0);
+        }
+        assert!(Environment::Black.sample(normal, &mut rng).is_none());
+    }
+}
@@ -1,10 +1,13 @@
 use crate::color::Color;
+use crate::environment::Rgb;
 use crate::vec3::Vec3;
 
 pub(crate) trait Light {
     fn position(&self) -> &Vec3;
     fn color(&self) -> &Color;
     fn intensity(&self) -> f32;
+    fn set_position(&mut self, position: Vec3);
+    fn set_intensity(&mut self, intensity: f32);
 }
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub(crate) struct PointLight {
@@ -22,6 +25,18 @@ impl Light for PointLight {
     fn position(&self) -> &Vec3 { &self.position }
     fn color(&self) -> &Color { &self.color }
     fn intensity(&self) -> f32 { self.intensity }
+    fn set_position(&mut self, position: Vec3) { self.position = position; }
+    fn set_intensity(&mut self, intensity: f32) { self.intensity = intensity; }
+}
+
+// A light as seen from a point: the light itself and the share of its colour, per channel, that
+// gets there past whatever is in between
+pub(crate) type VisibleLight<'a> = (&'a dyn Light, Rgb);
+
+// Colour of a visible light where it arrives
+pub(crate) fn arriving_color(&(light, transmittance): &VisibleLight) -> Rgb {
+    let color = light.color();
+    [color.r * transmittance[0], color.g * transmittance[1], color.b * transmittance[2]]
 }
 
 pub(crate) struct AmbientLight {
@@ -1,4 +1,8 @@
 mod vec3;
+mod mat4;
+mod quat;
+mod aabb;
+mod aov;
 mod objects;
 mod camera;
 mod ray;
@@ -6,9 +10,22 @@ mod phong;
 mod light;
 mod color;
 mod material;
+mod pbr;
+mod shading;
 mod consts;
+mod mat3;
+mod physics;
+mod roots;
+mod sdf;
+mod rng;
+mod environment;
+mod medium;
+mod scene;
+mod multiview;
+mod timeline;
 // added material module
 
+use std::time::Instant;
 use pixels::{Pixels, SurfaceTexture};
 use winit::application::ApplicationHandler;
 use winit::event::WindowEvent;
@@ -17,12 +34,15 @@ use winit::window::{Window, WindowId, WindowAttributes};
 use vec3::Vec3;
 use crate::camera::Camera;
 use crate::color::Color;
+use crate::environment::{Environment, Sky};
 use crate::light::{AmbientLight, PointLight, Light};
-use image;
 use crate::material::Material;
-use crate::objects::hittable::Hittable;
 use crate::objects::infinite_plane::InfinitePlane;
 use crate::objects::sphere::Sphere;
+use crate::physics::body::{RigidBody, Shape};
+use crate::physics::world::World;
+use crate::scene::Scene;
+use crate::timeline::{Easing, Keyframes, SequenceSettings, Timeline, Track};
 // for saving the buffer
 
 #[derive(Default)]
@@ -30,7 +50,100 @@ struct App {
     window: Option<&'static Window>,
     pixels: Option<Pixels<'static>>,
     image: Option<Vec<u8>>, // RGBA buffer from camera
-    dims: (u32, u32)
+    dims: (u32, u32),
+    scene: Option<Scene>,
+    timeline: Option<Timeline>,
+    last_frame: Option<Instant>,
+}
+
+fn build_scene(w: u32, h: u32) -> Scene {
+    // Physics bodies double as the scene objects
+    let mut world = World::new(Vec3::new(0.0, -9.81, 0.0));
+    let ball = world.add_body(RigidBody::new(
+        Shape::Sphere(
+            Sphere::new(
+                2.0,
+                Vec3::new(-3.0, 4.0, 12.0), // position
+                Material::new(
+                    Color::new(0.0, 0.0, 1.0, 1.0), // ambient
+                    Color::new(0.0, 0.0, 1.0, 1.0), // diffuse
+                    Color::new(1.0, 1.0, 1.0, 1.0), // specular
+            32.0
+                )
+            )
+        ),
+        1.0 // mass
+    ));
+    // Dropped with some sideways speed so it bounces, then rolls along the ground
+    world.body_mut(ball).velocity = Vec3::new(1.5, 0.0, 0.0);
+    world.body_mut(ball).set_restitution(0.6);
+    world.add_body(RigidBody::new_static(
+        Shape::Plane(
+            InfinitePlane::new(
+                Vec3::new(0.0, -2.0, 0.0), // position
+                Vec3::new(0.0, 1.0, 0.0), // normal
+                Material::new(
+                    Color::new(0.5, 0.5, 0.0, 1.0), // ambient
+                    Color::new(0.5, 0.5, 0.0, 1.0), // diffuse
+                    Color::new(1.0, 1.0, 1.0, 1.0), // specular
+                    32.0)
+            )
+        )
+    ));
+    let lights: Vec<Box<dyn Light>> = vec![
+        Box::new(
+            PointLight::new(
+                Vec3::new(5.0, 5.0, 0.0), // position
+                Color::new(1.0, 1.0, 1.0, 1.0), // color
+                1.0
+            )
+        ),
+        // Box::new(
+        //     PointLight::new(
+        //         Vec3::new(-5.0, 5.0, 0.0), // position
+        //         Color::new(1.0, 1.0, 1.0, 0.1), // color
+        //         1.0
+        //     )
+        // )
+    ];
+
+    let ambient_light = AmbientLight::new(Color::new(1.0, 1.0, 1.0, 1.0), 0.1);
+
+    let mut camera = Camera::new(
+        Vec3::new(0.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, 0.0),
+        60.0_f32,
+        (w, h)
+    );
+    // A few sky samples per hit keep the window responsive
+    camera.set_environment_samples(4);
+
+    // Or Environment::Map(EnvironmentMap::load("sky.hdr").expect("read sky")) to light with a photo
+    let environment = Environment::Sky(Sky::default());
+
+    // Clear air; e.g. vec![Medium::fog([0.005; 3], [0.02; 3])] for haze with light shafts
+    let media = Vec::new();
+
+    Scene { world, lights, ambient_light, environment, media, camera }
+}
+
+// Camera pulls back and tilts down to follow the ball as it rolls away
+fn build_timeline(fps: f32) -> Timeline {
+    let mut timeline = Timeline::new(fps);
+    let mut position = Keyframes::new();
+    position.insert(0.0, Vec3::new(0.0, 0.0, 0.0), Easing::Smooth);
+    position.insert(4.0, Vec3::new(1.0, 2.0, -4.0), Easing::Smooth);
+    timeline.add_track(Track::CameraPosition(position));
+    let mut rotation = Keyframes::new();
+    rotation.insert(0.0, Vec3::new(0.0, 0.0, 0.0), Easing::Smooth);
+    rotation.insert(4.0, Vec3::new(8.0, 5.0, 0.0), Easing::Smooth);
+    timeline.add_track(Track::CameraRotation(rotation));
+    // The light swings over to the other side
+    let mut light = Keyframes::new();
+    light.insert(0.0, Vec3::new(5.0, 5.0, 0.0), Easing::Smooth);
+    light.insert(4.0, Vec3::new(-5.0, 5.0, 0.0), Easing::Smooth);
+    timeline.add_track(Track::LightPosition(0, light));
+    timeline
 }
 
 impl ApplicationHandler for App {
@@ -49,61 +162,14 @@ impl ApplicationHandler for App {
         let px = Pixels::new(w, h, st).expect("create pixels");
         self.pixels = Some(px);
 
-        // Build scene objects
-        let objects: Vec<Box<dyn Hittable>> = vec![
-            Box::new(
-                Sphere::new(
-                    2.0,
-                    Vec3::new(0.0, 0.0, 10.0), // position
-                    Material::new(
-                        Color::new(0.0, 0.0, 1.0, 1.0), // ambient
-                        Color::new(0.0, 0.0, 1.0, 1.0), // diffuse
-                        Color::new(1.0, 1.0, 1.0, 1.0), // specular
-                32.0
-                    )
-                )
-            ),
-            Box::new(
-                InfinitePlane::new(
-                    Vec3::new(0.0, -2.0, 0.0), // position
-                    Vec3::new(0.0, 1.0, 0.0), // normal
-                    Material::new(
-                        Color::new(0.5, 0.5, 0.0, 1.0), // ambient
-                        Color::new(0.5, 0.5, 0.0, 1.0), // diffuse
-                        Color::new(1.0, 1.0, 1.0, 1.0), // specular
-                        32.0)
-                )
-            )
-        ];
-        let lights: Vec<Box<dyn Light>> = vec![
-            Box::new(
-                PointLight::new(
-                    Vec3::new(5.0, 5.0, 0.0), // position
-                    Color::new(1.0, 1.0, 1.0, 1.0), // color
-                    1.0
-                )
-            ),
-            // Box::new(
-            //     PointLight::new(
-            //         Vec3::new(-5.0, 5.0, 0.0), // position
-            //         Color::new(1.0, 1.0, 1.0, 0.1), // color
-            //         1.0
-            //     )
-            // )
-        ];
-
-        let ambient_light = AmbientLight::new(Color::new(1.0, 1.0, 1.0, 1.0), 0.1);
-
-        let camera = Camera::new(
-            Vec3::new(0.0, 0.0, 0.0),
-            Vec3::new(0.0, 0.0, 0.0),
-            60.0_f32,
-            (w, h)
-        );
-
-        // Render full scene (multiple objects)
-        self.image = Some(camera.render_scene(&objects, &lights, &ambient_light));
+        let scene = build_scene(w, h);
+        self.timeline = Some(build_timeline(60.0));
+
+        // Render the initial state
+        self.image = Some(scene.render());
         self.dims = (w, h);
+        self.scene = Some(scene);
+        self.last_frame = Some(Instant::now());
 
         // Save the image to disk
         if let Some(buf) = &self.image {
@@ -137,6 +203,17 @@ impl ApplicationHandler for App {
                 }
             }
             WindowEvent::RedrawRequested => {
+                // Step the simulation by the real time since the last frame and re-render
+                if let (Some(scene), Some(last)) = (self.scene.as_mut(), self.last_frame) {
+                    let now = Instant::now();
+                    if scene.world.advance((now - last).as_secs_f32()) > 0 {
+                        if let Some(timeline) = &self.timeline {
+                            timeline.apply(scene, scene.world.time());
+                        }
+                        self.image = Some(scene.render());
+                    }
+                    self.last_frame = Some(now);
+                }
                 if let (Some(pixels), Some(img)) = (&mut self.pixels, &self.image) {
                     let frame = pixels.frame_mut();
                     if frame.len() == img.len() {
@@ -144,6 +221,9 @@ impl ApplicationHandler for App {
                     }
                     pixels.render().expect("render");
                 }
+                if let Some(w) = self.window {
+                    w.request_redraw();
+                }
             }
             _ => {}
         }
@@ -151,6 +231,28 @@ impl ApplicationHandler for App {
 }
 
 fn main() {
+    // With sequence options, render frames to disk without opening a window
+    match SequenceSettings::from_args(std::env::args().skip(1)) {
+        Ok(Some(settings)) => {
+            let mut scene = build_scene(1000, 1000);
+            // Half-frame shutter, the usual film look
+            scene.camera.set_shutter(0.0, 0.5 / settings.fps);
+            let timeline = build_timeline(settings.fps);
+            match timeline.render_sequence(&mut scene, settings.frames, &settings.output, &settings.aovs) {
+                Ok(written) => println!("wrote {} images to {}", written.len(), settings.output.display()),
+                Err(e) => {
+                    eprintln!("rendering failed: {}", e);
+                    std::process::exit(1);
+                }
+            }
+            return;
+        }
+        Ok(None) => {}
+        Err(e) => {
+            eprintln!("{}\nusage: physicsEngine [--frames START-END] [--fps FPS] [--out DIR] [--aovs depth,normal,albedo,id,shadow|all]", e);
+            std::process::exit(2);
+        }
+    }
     let event_loop = EventLoop::new().expect("event loop");
     event_loop.set_control_flow(ControlFlow::Wait);
     let mut app = App::default();
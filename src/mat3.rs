@@ -0,0 +1,125 @@
+use std::ops::{Add, Mul};
+use crate::quat::Quat;
+use crate::vec3::Vec3;
+
+// Row-major 3x3 matrix, used for rotations and inertia tensors
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) struct Mat3 {
+    pub m: [[f32; 3]; 3],
+}
+
+impl Mat3 {
+    pub fn new(m: [[f32; 3]; 3]) -> Self {
+        Mat3 { m }
+    }
+
+    pub fn zero() -> Self {
+        Mat3::new([[0.0; 3]; 3])
+    }
+
+    pub fn diagonal(d: Vec3) -> Self {
+        Mat3::new([
+            [d.x(), 0.0, 0.0],
+            [0.0, d.y(), 0.0],
+            [0.0, 0.0, d.z()],
+        ])
+    }
+
+    pub fn rotation(q: Quat) -> Self {
+        let q = q.normalized();
+        let (w, x, y, z) = (q.w, q.x, q.y, q.z);
+        Mat3::new([
+            [1.0 - 2.0 * (y * y + z * z), 2.0 * (x * y - w * z), 2.0 * (x * z + w * y)],
+            [2.0 * (x * y + w * z), 1.0 - 2.0 * (x * x + z * z), 2.0 * (y * z - w * x)],
+            [2.0 * (x * z - w * y), 2.0 * (y * z + w * x), 1.0 - 2.0 * (x * x + y * y)],
+        ])
+    }
+
+    pub fn transpose(&self) -> Mat3 {
+        let m = &self.m;
+        Mat3::new([
+            [m[0][0], m[1][0], m[2][0]],
+            [m[0][1], m[1][1], m[2][1]],
+            [m[0][2], m[1][2], m[2][2]],
+        ])
+    }
+
+    pub fn determinant(&self) -> f32 {
+        let m = &self.m;
+        m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+            - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+            + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+    }
+
+    // None if singular. The cutoff scales with the entries, so tiny but well-conditioned
+    // matrices (the inertia of a small light body) still invert.
+    pub fn inverse(&self) -> Option<Mat3> {
+        let det = self.determinant();
+        let scale = self.m.iter().flatten().fold(0.0f32, |acc, v| acc.max(v.abs()));
+        if scale == 0.0 || det.abs() <= f32::EPSILON * scale * scale * scale { return None; }
+        let m = &self.m;
+        let inv_det = 1.0 / det;
+        Some(Mat3::new([
+            [
+                (m[1][1] * m[2][2] - m[1][2] * m[2][1]) * inv_det,
+                (m[0][2] * m[2][1] - m[0][1] * m[2][2]) * inv_det,
+                (m[0][1] * m[1][2] - m[0][2] * m[1][1]) * inv_det,
+            ],
+            [
+                (m[1][2] * m[2][0] - m[1][0] * m[2][2]) * inv_det,
+                (m[0][0] * m[2][2] - m[0][2] * m[2][0]) * inv_det,
+                (m[0][2] * m[1][0] - m[0][0] * m[1][2]) * inv_det,
+            ],
+            [
+                (m[1][0] * m[2][1] - m[1][1] * m[2][0]) * inv_det,
+                (m[0][1] * m[2][0] - m[0][0] * m[2][1]) * inv_det,
+                (m[0][0] * m[1][1] - m[0][1] * m[1][0]) * inv_det,
+            ],
+        ]))
+    }
+}
+
+impl Mul<Vec3> for Mat3 {
+    type Output = Vec3;
+    fn mul(self, v: Vec3) -> Vec3 {
+        let m = &self.m;
+        Vec3::new(
+            m[0][0] * v.x() + m[0][1] * v.y() + m[0][2] * v.z(),
+            m[1][0] * v.x() + m[1][1] * v.y() + m[1][2] * v.z(),
+            m[2][0] * v.x() + m[2][1] * v.y() + m[2][2] * v.z(),
+        )
+    }
+}
+
+impl Mul<Mat3> for Mat3 {
+    type Output = Mat3;
+    fn mul(self, other: Mat3) -> Mat3 {
+        let mut out = [[0.0_f32; 3]; 3];
+        for (r, row) in out.iter_mut().enumerate() {
+            for (c, value) in row.iter_mut().enumerate() {
+                *value = (0..3).map(|k| self.m[r][k] * other.m[k][c]).sum();
+            }
+        }
+        Mat3::new(out)
+    }
+}
+
+impl Mul<f32> for Mat3 {
+    type Output = Mat3;
+    fn mul(self, scalar: f32) -> Mat3 {
+        Mat3::new(self.m.map(|row| row.map(|v| v * scalar)))
+    }
+}
+
+impl Add<Mat3> for Mat3 {
+    type Output = Mat3;
+    fn add(self, other: Mat3) -> Mat3 {
+        let mut out = self.m;
+        for (r, row) in out.iter_mut().enumerate() {
+            for (c, value) in row.iter_mut().enumerate() {
+                *value += other.m[r][c];
+            }
+        }
+        Mat3::new(out)
+    }
+}
@@ -0,0 +1,168 @@
+#![allow(dead_code, reason = "transforms are only needed once objects are instanced, which the demo does not do")]
+use std::ops::Mul;
+use crate::quat::Quat;
+use crate::vec3::Vec3;
+
+// Row-major 4x4 affine transform, points are treated as column vectors (M * p)
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) struct Mat4 {
+    pub m: [[f32; 4]; 4],
+}
+
+impl Mat4 {
+    pub fn new(m: [[f32; 4]; 4]) -> Self {
+        Mat4 { m }
+    }
+
+    pub fn identity() -> Self {
+        Mat4::new([
+            [1.0, 0.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    pub fn translation(offset: Vec3) -> Self {
+        let mut m = Mat4::identity();
+        m.m[0][3] = offset.x();
+        m.m[1][3] = offset.y();
+        m.m[2][3] = offset.z();
+        m
+    }
+
+    pub fn scale(factors: Vec3) -> Self {
+        let mut m = Mat4::identity();
+        m.m[0][0] = factors.x();
+        m.m[1][1] = factors.y();
+        m.m[2][2] = factors.z();
+        m
+    }
+
+    pub fn rotation(q: Quat) -> Self {
+        let q = q.normalized();
+        let (w, x, y, z) = (q.w, q.x, q.y, q.z);
+        Mat4::new([
+            [1.0 - 2.0 * (y * y + z * z), 2.0 * (x * y - w * z), 2.0 * (x * z + w * y), 0.0],
+            [2.0 * (x * y + w * z), 1.0 - 2.0 * (x * x + z * z), 2.0 * (y * z - w * x), 0.0],
+            [2.0 * (x * z - w * y), 2.0 * (y * z + w * x), 1.0 - 2.0 * (x * x + y * y), 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    // Scale first, then rotate, then translate
+    pub fn from_trs(translation: Vec3, rotation: Quat, scale: Vec3) -> Self {
+        Mat4::translation(translation) * Mat4::rotation(rotation) * Mat4::scale(scale)
+    }
+
+    pub fn transform_point(&self, p: Vec3) -> Vec3 {
+        let m = &self.m;
+        Vec3::new(
+            m[0][0] * p.x() + m[0][1] * p.y() + m[0][2] * p.z() + m[0][3],
+            m[1][0] * p.x() + m[1][1] * p.y() + m[1][2] * p.z() + m[1][3],
+            m[2][0] * p.x() + m[2][1] * p.y() + m[2][2] * p.z() + m[2][3],
+        )
+    }
+
+    // Directions ignore the translation column
+    pub fn transform_vector(&self, v: Vec3) -> Vec3 {
+        let m = &self.m;
+        Vec3::new(
+            m[0][0] * v.x() + m[0][1] * v.y() + m[0][2] * v.z(),
+            m[1][0] * v.x() + m[1][1] * v.y() + m[1][2] * v.z(),
+            m[2][0] * v.x() + m[2][1] * v.y() + m[2][2] * v.z(),
+        )
+    }
+
+    pub fn transpose(&self) -> Mat4 {
+        let mut t = Mat4::identity();
+        for (r, row) in self.m.iter().enumerate() {
+            for (c, value) in row.iter().enumerate() {
+                t.m[c][r] = *value;
+            }
+        }
+        t
+    }
+
+    // General inverse by Gauss-Jordan elimination with partial pivoting, None if singular
+    pub fn inverse(&self) -> Option<Mat4> {
+        let mut a = self.m;
+        let mut inv = Mat4::identity().m;
+        for col in 0..4 {
+            let pivot = (col..4)
+                .max_by(|&i, &j| a[i][col].abs().total_cmp(&a[j][col].abs()))
+                .unwrap_or(col);
+            if a[pivot][col].abs() < 1e-12 { return None; }
+            a.swap(col, pivot);
+            inv.swap(col, pivot);
+
+            let p = a[col][col];
+            for c in 0..4 {
+                a[col][c] /= p;
+                inv[col][c] /= p;
+            }
+            for r in 0..4 {
+                if r == col { continue; }
+                let f = a[r][col];
+                if f == 0.0 { continue; }
+                for c in 0..4 {
+                    a[r][c] -= f * a[col][c];
+                    inv[r][c] -= f * inv[col][c];
+                }
+            }
+        }
+        Some(Mat4::new(inv))
+    }
+}
+
+impl Mul<Mat4> for Mat4 {
+    type Output = Mat4;
+    fn mul(self, other: Mat4) -> Mat4 {
+        let mut out = [[0.0_f32; 4]; 4];
+        for (r, row) in out.iter_mut().enumerate() {
+            for (c, value) in row.iter_mut().enumerate() {
+                *value = (0..4).map(|k| self.m[r][k] * other.m[k][c]).sum();
+            }
+        }
+        Mat4::new(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_vec_close(a: Vec3, b: Vec3) {
+        assert!((a - b).length() < 1e-4, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn trs_applies_scale_rotation_translation_in_order() {
+        let rot = Quat::from_axis_angle(Vec3::new(0.0, 1.0, 0.0), std::f32::consts::FRAC_PI_2);
+        let m = Mat4::from_trs(Vec3::new(1.0, 2.0, 3.0), rot, Vec3::new(2.0, 2.0, 2.0));
+        // +X scaled to 2, rotated 90 degrees about +Y onto -Z, then translated
+        assert_vec_close(m.transform_point(Vec3::new(1.0, 0.0, 0.0)), Vec3::new(1.0, 2.0, 1.0));
+        assert_vec_close(m.transform_vector(Vec3::new(1.0, 0.0, 0.0)), Vec3::new(0.0, 0.0, -2.0));
+    }
+
+    #[test]
+    fn inverse_round_trips_points() {
+        let rot = Quat::from_euler_degrees(Vec3::new(30.0, 45.0, 10.0));
+        let m = Mat4::from_trs(Vec3::new(-4.0, 0.5, 7.0), rot, Vec3::new(1.0, 3.0, 0.5));
+        let inv = m.inverse().expect("invertible");
+        let p = Vec3::new(0.3, -1.2, 5.0);
+        assert_vec_close(inv.transform_point(m.transform_point(p)), p);
+    }
+
+    #[test]
+    fn singular_matrix_has_no_inverse() {
+        assert!(Mat4::scale(Vec3::new(1.0, 0.0, 1.0)).inverse().is_none());
+    }
+
+    #[test]
+    fn rotation_matrix_matches_quaternion() {
+        let q = Quat::from_axis_angle(Vec3::new(1.0, 1.0, 0.0), 1.1);
+        let v = Vec3::new(0.2, -0.7, 1.5);
+        assert_vec_close(Mat4::rotation(q).transform_vector(v), q.rotate(v));
+    }
+}
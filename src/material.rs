@@ -1,4 +1,6 @@
 use crate::color::Color;
+use crate::pbr::Pbr;
+use crate::shading::Shading;
 
 #[derive(Debug, Copy, Clone)]
 pub struct Material {
@@ -6,11 +8,32 @@ pub struct Material {
     pub diffuse: Color,
     pub specular: Color,
     pub shininess: f32,
+    pub shading: Shading, // how the colours above respond to light
+    pub transmittance: Color, // share of light let through to shadows behind, black for opaque
+    pub pbr: Option<Pbr>, // when set, shading uses this and ignores the Phong terms above
 }
 
 impl Material {
     pub fn new(ambient: Color, diffuse: Color, specular: Color, shininess: f32) -> Self {
-        Material { ambient, diffuse, specular, shininess }
+        Material { ambient, diffuse, specular, shininess, shading: Shading::Phong, transmittance: Color::new(0.0, 0.0, 0.0, 1.0), pbr: None }
+    }
+
+    // Physically based material. The Phong terms are filled in with the nearest equivalent so
+    // code that still reads them (diffuse IBL, previews) sees something sensible.
+    #[allow(dead_code, reason = "the demo scene is still built from Phong materials")]
+    pub fn from_pbr(pbr: Pbr) -> Self {
+        let rgb = |c: [f32; 3]| Color::new(c[0], c[1], c[2], 1.0);
+        // Blinn-style mapping from GGX alpha to a Phong exponent
+        let alpha = pbr.roughness() * pbr.roughness();
+        Material {
+            ambient: rgb(pbr.base_color()),
+            diffuse: rgb(pbr.diffuse_color()),
+            specular: rgb(pbr.f0()),
+            shininess: (2.0 / (alpha * alpha) - 2.0).max(1.0),
+            shading: Shading::BlinnPhong,
+            transmittance: Color::new(0.0, 0.0, 0.0, 1.0),
+            pbr: Some(pbr),
+        }
     }
 }
 
@@ -22,6 +45,9 @@ impl Default for Material {
             diffuse: Color::new(0.0, 0.0, 0.7, 1.0),
             specular: Color::new(1.0, 1.0, 1.0, 1.0),
             shininess: 32.0,
+            shading: Shading::Phong,
+            transmittance: Color::new(0.0, 0.0, 0.0, 1.0),
+            pbr: None,
         }
     }
 }
\ No newline at end of file
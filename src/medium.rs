@@ -0,0 +1,260 @@
+#![allow(dead_code, reason = "the demo renders in clear air; main.rs notes how to add haze")]
+use std::f32::consts::PI;
+use crate::aabb::Aabb;
+use crate::environment::Rgb;
+use crate::ray::Ray;
+use crate::vec3::Vec3;
+
+// How thick a medium is from place to place, scaling its absorption and scattering
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Density {
+    Uniform,
+    Grid(DensityGrid),
+    Noise { scale: f32, seed: u32 }, // smooth blobs about `scale` across, between 0 and 1
+}
+
+// Densities on a regular lattice spanning `bounds`, blended trilinearly in between.
+// Outside the bounds the density is zero.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct DensityGrid {
+    bounds: Aabb,
+    resolution: (usize, usize, usize),
+    values: Vec<f32>, // x fastest, then y, then z
+}
+
+impl DensityGrid {
+    pub(crate) fn new(bounds: Aabb, resolution: (usize, usize, usize), values: Vec<f32>) -> Self {
+        let (nx, ny, nz) = resolution;
+        assert!(nx >= 2 && ny >= 2 && nz >= 2, "a density grid needs at least two samples along each axis");
+        assert_eq!(values.len(), nx * ny * nz, "density grid size doesn't match its resolution");
+        DensityGrid { bounds, resolution, values }
+    }
+
+    // Density at every lattice point from a function of its position
+    pub(crate) fn from_fn(bounds: Aabb, resolution: (usize, usize, usize), f: impl Fn(Vec3) -> f32) -> Self {
+        let (nx, ny, nz) = resolution;
+        let size = bounds.max - bounds.min;
+        let mut values = Vec::with_capacity(nx * ny * nz);
+        for z in 0..nz {
+            for y in 0..ny {
+                for x in 0..nx {
+                    let t = Vec3::new(x as f32 / (nx - 1) as f32, y as f32 / (ny - 1) as f32, z as f32 / (nz - 1) as f32);
+                    values.push(f(bounds.min + size * t));
+                }
+            }
+        }
+        DensityGrid::new(bounds, resolution, values)
+    }
+
+    pub(crate) fn sample(&self, p: Vec3) -> f32 {
+        let (nx, ny, nz) = self.resolution;
+        let size = self.bounds.max - self.bounds.min;
+        let t = (p - self.bounds.min) / size;
+        if [t.x(), t.y(), t.z()].iter().any(|c| !(0.0..=1.0).contains(c)) { return 0.0; }
+        // Cell and position within it along one axis
+        let axis = |t: f32, n: usize| {
+            let f = t * (n - 1) as f32;
+            let i = (f.floor() as usize).min(n - 2);
+            (i, f - i as f32)
+        };
+        let ((x, fx), (y, fy), (z, fz)) = (axis(t.x(), nx), axis(t.y(), ny), axis(t.z(), nz));
+        let at = |dx: usize, dy: usize, dz: usize| self.values[(z + dz) * nx * ny + (y + dy) * nx + x + dx];
+        let lerp = |a: f32, b: f32, t: f32| a + (b - a) * t;
+        let face = |dz| lerp(lerp(at(0, 0, dz), at(1, 0, dz), fx), lerp(at(0, 1, dz), at(1, 1, dz), fx), fy);
+        lerp(face(0), face(1), fz)
+    }
+}
+
+// Pseudo-random value in [0, 1) for a lattice point
+fn hash(x: i32, y: i32, z: i32, seed: u32) -> f32 {
+    let mut h = seed
+        ^ (x as u32).wrapping_mul(0x8da6_b343)
+        ^ (y as u32).wrapping_mul(0xd816_3841)
+        ^ (z as u32).wrapping_mul(0xcb1a_b31f);
+    h ^= h >> 13;
+    h = h.wrapping_mul(0x5bd1_e995);
+    h ^= h >> 15;
+    (h >> 8) as f32 / (1u32 << 24) as f32
+}
+
+// Smoothly interpolated lattice noise, two octaves so it doesn't look boxy
+fn value_noise(p: Vec3, seed: u32) -> f32 {
+    let octave = |p: Vec3, seed: u32| {
+        let (fx, fy, fz) = (p.x().floor(), p.y().floor(), p.z().floor());
+        let smooth = |t: f32| t * t * (3.0 - 2.0 * t);
+        let (tx, ty, tz) = (smooth(p.x() - fx), smooth(p.y() - fy), smooth(p.z() - fz));
+        let (x, y, z) = (fx as i32, fy as i32, fz as i32);
+        let lerp = |a: f32, b: f32, t: f32| a + (b - a) * t;
+        let face = |dz| lerp(
+            lerp(hash(x, y, z + dz, seed), hash(x + 1, y, z + dz, seed), tx),
+            lerp(hash(x, y + 1, z + dz, seed), hash(x + 1, y + 1, z + dz, seed), tx),
+            ty,
+        );
+        lerp(face(0), face(1), tz)
+    };
+    (2.0 * octave(p, seed) + octave(p * 2.0, seed.wrapping_add(1))) / 3.0
+}
+
+// Probability density of light turning by an angle with this cosine. g > 0 favours carrying on
+// forwards (haze, mist), g < 0 bouncing back, 0 is the same in every direction.
+pub(crate) fn henyey_greenstein(cos_theta: f32, g: f32) -> f32 {
+    let denom = 1.0 + g * g - 2.0 * g * cos_theta;
+    (1.0 - g * g) / (4.0 * PI * denom * denom.sqrt())
+}
+
+// Participating medium such as fog, smoke or dusty air. Light passing through is absorbed and
+// scattered aside in proportion to the density, and light from the lamps is scattered towards
+// the viewer, which is what makes shafts of light visible.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Medium {
+    absorption: Rgb, // per unit length at density 1
+    scattering: Rgb,
+    anisotropy: f32, // Henyey-Greenstein g
+    density: Density,
+    region: Option<Aabb>, // None fills the whole scene
+}
+
+// How far rays look into fog that fills the scene before giving up
+pub(crate) const MAX_FOG_DISTANCE: f32 = 200.0;
+
+impl Medium {
+    // Fog filling the whole scene
+    pub(crate) fn fog(absorption: Rgb, scattering: Rgb) -> Self {
+        Medium { absorption, scattering, anisotropy: 0.0, density: Density::Uniform, region: None }
+    }
+
+    // A volume confined to a box
+    pub(crate) fn bounded(region: Aabb, absorption: Rgb, scattering: Rgb) -> Self {
+        Medium { absorption, scattering, anisotropy: 0.0, density: Density::Uniform, region: Some(region) }
+    }
+
+    pub(crate) fn set_anisotropy(&mut self, g: f32) {
+        self.anisotropy = g.clamp(-0.99, 0.99);
+    }
+
+    pub(crate) fn set_density(&mut self, density: Density) {
+        self.density = density;
+    }
+
+    pub(crate) fn density_at(&self, p: Vec3) -> f32 {
+        if let Some(region) = &self.region
+            && !region.contains(p) {
+            return 0.0;
+        }
+        match &self.density {
+            Density::Uniform => 1.0,
+            Density::Grid(grid) => grid.sample(p),
+            Density::Noise { scale, seed } => value_noise(p / scale.max(1e-3), *seed),
+        }
+    }
+
+    // Stretch of the ray within [0, t_max] that passes through the medium
+    pub(crate) fn span(&self, ray: &Ray, t_max: f32) -> Option<(f32, f32)> {
+        match &self.region {
+            Some(region) => region.clip(ray, t_max),
+            None => Some((0.0, t_max.min(MAX_FOG_DISTANCE))),
+        }
+    }
+
+    // Light lost per unit length at p, to absorption and scattering
+    pub(crate) fn extinction_at(&self, p: Vec3) -> Rgb {
+        let d = self.density_at(p);
+        [0, 1, 2].map(|i| (self.absorption[i] + self.scattering[i]) * d)
+    }
+
+    // Light scattered per unit length at p
+    pub(crate) fn scattering_at(&self, p: Vec3) -> Rgb {
+        let d = self.density_at(p);
+        self.scattering.map(|s| s * d)
+    }
+
+    pub(crate) fn phase(&self, cos_theta: f32) -> f32 {
+        henyey_greenstein(cos_theta, self.anisotropy)
+    }
+
+    // Fraction of light that makes it from `from` to `to` through this medium. Uniform media are
+    // exact, others are marched in `steps` pieces.
+    pub(crate) fn transmittance(&self, from: Vec3, to: Vec3, steps: u32) -> Rgb {
+        let length = (to - from).length();
+        if length <= 0.0 { return [1.0; 3]; }
+        let ray = Ray::new(from, (to - from) / length);
+        let Some((t0, t1)) = self.span(&ray, length) else { return [1.0; 3] };
+        let depth = match self.density {
+            Density::Uniform => [0, 1, 2].map(|i| (self.absorption[i] + self.scattering[i]) * (t1 - t0)),
+            _ => {
+                let steps = steps.max(1);
+                let dt = (t1 - t0) / steps as f32;
+                let mut depth = [0.0; 3];
+                for s in 0..steps {
+                    let p = from + ray.direction * (t0 + (s as f32 + 0.5) * dt);
+                    for (d, e) in depth.iter_mut().zip(self.extinction_at(p)) {
+                        *d += e * dt;
+                    }
+                }
+                depth
+            }
+        };
+        depth.map(|d| (-d).exp())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn phase_function_is_a_distribution() {
+        for g in [-0.5, 0.0, 0.3, 0.8] {
+            // Integrated over the sphere it comes to one
+            let steps = 2000;
+            let total: f32 = (0..steps)
+                .map(|i| {
+                    let cos = -1.0 + 2.0 * (i as f32 + 0.5) / steps as f32;
+                    henyey_greenstein(cos, g) * 2.0 * PI * 2.0 / steps as f32
+                })
+                .sum();
+            assert!((total - 1.0).abs() < 1e-2, "g {}: {}", g, total);
+        }
+        assert!((henyey_greenstein(0.3, 0.0) - 1.0 / (4.0 * PI)).abs() < 1e-6);
+        assert!(henyey_greenstein(1.0, 0.6) > henyey_greenstein(-1.0, 0.6));
+    }
+
+    #[test]
+    fn uniform_fog_follows_beer_lambert() {
+        let fog = Medium::fog([0.05, 0.1, 0.2], [0.05, 0.0, 0.0]);
+        let t = fog.transmittance(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 10.0), 1);
+        assert!((t[0] - (-1.0f32).exp()).abs() < 1e-5);
+        assert!((t[1] - (-1.0f32).exp()).abs() < 1e-5);
+        assert!((t[2] - (-2.0f32).exp()).abs() < 1e-5);
+
+        // A bounded volume only counts the part of the path inside it
+        let region = Aabb::new(Vec3::new(-1.0, -1.0, 2.0), Vec3::new(1.0, 1.0, 4.0));
+        let mut smoke = Medium::bounded(region, [0.5; 3], [0.0; 3]);
+        let t = smoke.transmittance(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 10.0), 1);
+        assert!((t[0] - (-1.0f32).exp()).abs() < 1e-5);
+        // Marching a grid of the same density agrees
+        smoke.set_density(Density::Grid(DensityGrid::from_fn(region, (3, 3, 3), |_| 1.0)));
+        let marched = smoke.transmittance(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 10.0), 16);
+        assert!((marched[0] - t[0]).abs() < 1e-4);
+    }
+
+    #[test]
+    fn grid_blends_between_samples_and_noise_stays_in_range() {
+        let bounds = Aabb::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(2.0, 2.0, 2.0));
+        let grid = DensityGrid::from_fn(bounds, (3, 3, 3), |p| p.x());
+        assert!((grid.sample(Vec3::new(0.5, 0.3, 1.7)) - 0.5).abs() < 1e-5);
+        assert!((grid.sample(Vec3::new(2.0, 2.0, 2.0)) - 2.0).abs() < 1e-5);
+        assert_eq!(grid.sample(Vec3::new(3.0, 1.0, 1.0)), 0.0);
+
+        let mut cloud = Medium::fog([0.0; 3], [1.0; 3]);
+        cloud.set_density(Density::Noise { scale: 2.0, seed: 7 });
+        let mut seen = (f32::MAX, f32::MIN);
+        for i in 0..500 {
+            let d = cloud.density_at(Vec3::new(i as f32 * 0.37, i as f32 * 0.11, 3.0));
+            assert!((0.0..1.0).contains(&d));
+            seen = (seen.0.min(d), seen.1.max(d));
+        }
+        // Actually varies
+        assert!(seen.1 - seen.0 > 0.3);
+    }
+}
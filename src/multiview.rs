@@ -0,0 +1,277 @@
+#![allow(dead_code, reason = "stereo pairs and cube maps are offline renders; the demo window only shows one live view")]
+use std::io;
+use std::path::{Path, PathBuf};
+use crate::camera::Projection;
+use crate::quat::Quat;
+use crate::scene::Scene;
+use crate::vec3::Vec3;
+
+// Renders made of several views of a scene: stereo pairs and cube maps. Images are RGBA
+// buffers with their (width, height).
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum StereoLayout {
+    SideBySide, // left eye on the left, twice as wide
+    OverUnder,  // left eye on top, twice as tall
+}
+
+// Left and right eye views from either side of the scene camera. The eyes look along parallel
+// axes, `interocular` apart (about 0.065 for human scale scenes).
+pub(crate) fn render_stereo(scene: &Scene, interocular: f32, layout: StereoLayout) -> (Vec<u8>, (u32, u32)) {
+    let (width, height) = scene.camera.resolution();
+    let right = Quat::from_euler_degrees(scene.camera.rotation()).rotate(Vec3::new(1.0, 0.0, 0.0));
+    let eye = |side: f32| {
+        let mut camera = scene.camera;
+        camera.set_position(scene.camera.position() + right * (0.5 * side * interocular));
+        scene.render_with(&camera)
+    };
+    let (left, right) = (eye(-1.0), eye(1.0));
+    match layout {
+        StereoLayout::SideBySide => {
+            let mut image = vec![0u8; (width * height * 8) as usize];
+            blit(&mut image, 2 * width, &left, (width, height), (0, 0));
+            blit(&mut image, 2 * width, &right, (width, height), (width, 0));
+            (image, (2 * width, height))
+        }
+        StereoLayout::OverUnder => {
+            // Stacked rows, so the halves are just one after the other
+            let mut image = left;
+            image.extend_from_slice(&right);
+            (image, (width, 2 * height))
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum CubeFace {
+    PositiveX,
+    NegativeX,
+    PositiveY,
+    NegativeY,
+    PositiveZ,
+    NegativeZ,
+}
+
+impl CubeFace {
+    pub(crate) const ALL: [CubeFace; 6] = [
+        CubeFace::PositiveX,
+        CubeFace::NegativeX,
+        CubeFace::PositiveY,
+        CubeFace::NegativeY,
+        CubeFace::PositiveZ,
+        CubeFace::NegativeZ,
+    ];
+
+    // Camera rotation looking out through the face. The side faces keep +Y up, and the top and
+    // bottom are tilted from +Z, so neighbouring faces meet edge to edge in the cross layout.
+    pub(crate) fn rotation(&self) -> Vec3 {
+        match self {
+            CubeFace::PositiveX => Vec3::new(0.0, 90.0, 0.0),
+            CubeFace::NegativeX => Vec3::new(0.0, -90.0, 0.0),
+            CubeFace::PositiveY => Vec3::new(-90.0, 0.0, 0.0),
+            CubeFace::NegativeY => Vec3::new(90.0, 0.0, 0.0),
+            CubeFace::PositiveZ => Vec3::new(0.0, 0.0, 0.0),
+            CubeFace::NegativeZ => Vec3::new(0.0, 180.0, 0.0),
+        }
+    }
+
+    // File name suffix
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            CubeFace::PositiveX => "px",
+            CubeFace::NegativeX => "nx",
+            CubeFace::PositiveY => "py",
+            CubeFace::NegativeY => "ny",
+            CubeFace::PositiveZ => "pz",
+            CubeFace::NegativeZ => "nz",
+        }
+    }
+
+    // Cell in the horizontal cross, in face sized units:
+    //        +Y
+    //   -X   +Z   +X   -Z
+    //        -Y
+    fn cross_cell(&self) -> (u32, u32) {
+        match self {
+            CubeFace::PositiveY => (1, 0),
+            CubeFace::NegativeX => (0, 1),
+            CubeFace::PositiveZ => (1, 1),
+            CubeFace::PositiveX => (2, 1),
+            CubeFace::NegativeZ => (3, 1),
+            CubeFace::NegativeY => (1, 2),
+        }
+    }
+}
+
+// The six 90 degree views from the scene camera's position, each `size` pixels square, in
+// CubeFace::ALL order. Other camera settings such as the shutter carry over.
+pub(crate) fn render_cube_faces(scene: &Scene, size: u32) -> Vec<(CubeFace, Vec<u8>)> {
+    CubeFace::ALL.iter()
+        .map(|face| {
+            let mut camera = scene.camera;
+            camera.set_projection(Projection::Perspective);
+            camera.set_fov(90.0);
+            camera.set_resolution((size, size));
+            camera.set_rotation(face.rotation());
+            (*face, scene.render_with(&camera))
+        })
+        .collect()
+}
+
+// Faces laid out as a cross, 4 by 3 faces with the unused corners left transparent
+pub(crate) fn cube_cross(faces: &[(CubeFace, Vec<u8>)], size: u32) -> (Vec<u8>, (u32, u32)) {
+    let (width, height) = (4 * size, 3 * size);
+    let mut image = vec![0u8; (width * height * 4) as usize];
+    for (face, pixels) in faces {
+        let (cx, cy) = face.cross_cell();
+        blit(&mut image, width, pixels, (size, size), (cx * size, cy * size));
+    }
+    (image, (width, height))
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum CubeMapOutput {
+    Faces, // one image per face, <stem>_px.png and so on
+    Cross, // a single <stem>.png
+}
+
+// Renders and writes the cube map around the scene camera into `directory`
+pub(crate) fn write_cube_map(scene: &Scene, size: u32, output: CubeMapOutput, directory: &Path, stem: &str) -> io::Result<Vec<PathBuf>> {
+    std::fs::create_dir_all(directory)?;
+    let faces = render_cube_faces(scene, size);
+    let save = |path: PathBuf, pixels: &[u8], (width, height): (u32, u32)| {
+        image::save_buffer(&path, pixels, width, height, image::ColorType::Rgba8).map_err(io::Error::other)?;
+        Ok(path)
+    };
+    match output {
+        CubeMapOutput::Faces => faces.iter()
+            .map(|(face, pixels)| save(directory.join(format!("{}_{}.png", stem, face.name())), pixels, (size, size)))
+            .collect(),
+        CubeMapOutput::Cross => {
+            let (pixels, dims) = cube_cross(&faces, size);
+            Ok(vec![save(directory.join(format!("{}.png", stem)), &pixels, dims)?])
+        }
+    }
+}
+
+// Copies an RGBA image into a bigger one with its top left corner at `at`
+fn blit(dst: &mut [u8], dst_width: u32, src: &[u8], (width, height): (u32, u32), at: (u32, u32)) {
+    let row = (width * 4) as usize;
+    for y in 0..height {
+        let from = (y * width * 4) as usize;
+        let to = (((at.1 + y) * dst_width + at.0) * 4) as usize;
+        dst[to..to + row].copy_from_slice(&src[from..from + row]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::Camera;
+    use crate::environment::Environment;
+    use crate::color::Color;
+    use crate::light::{AmbientLight, Light, PointLight};
+    use crate::material::Material;
+    use crate::objects::sphere::Sphere;
+    use crate::physics::body::{RigidBody, Shape};
+    use crate::physics::world::World;
+
+    // A differently coloured ball on each axis, lit from the middle, with ambient so every
+    // ball shows its colour
+    fn marker_scene() -> Scene {
+        let mut world = World::new(Vec3::new(0.0, 0.0, 0.0));
+        let markers = [
+            (Vec3::new(5.0, 0.0, 0.0), Color::new(1.0, 0.0, 0.0, 1.0)),
+            (Vec3::new(-5.0, 0.0, 0.0), Color::new(0.0, 1.0, 1.0, 1.0)),
+            (Vec3::new(0.0, 5.0, 0.0), Color::new(0.0, 1.0, 0.0, 1.0)),
+            (Vec3::new(0.0, -5.0, 0.0), Color::new(1.0, 0.0, 1.0, 1.0)),
+            (Vec3::new(0.0, 0.0, 5.0), Color::new(0.0, 0.0, 1.0, 1.0)),
+            (Vec3::new(0.0, 0.0, -5.0), Color::new(1.0, 1.0, 0.0, 1.0)),
+        ];
+        for (position, color) in markers {
+            let material = Material::new(color, color, Color::new(0.0, 0.0, 0.0, 1.0), 1.0);
+            world.add_body(RigidBody::new_static(Shape::Sphere(Sphere::new(1.0, position, material))));
+        }
+        let white = Color::new(1.0, 1.0, 1.0, 1.0);
+        let lights: Vec<Box<dyn Light>> = vec![Box::new(PointLight::new(Vec3::new(0.0, 0.0, 0.0), white, 1.0))];
+        Scene {
+            world,
+            lights,
+            environment: Environment::Black,
+            media: Vec::new(),
+            ambient_light: AmbientLight::new(white, 0.5),
+            camera: Camera::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 0.0), 60.0, (8, 8)),
+        }
+    }
+
+    fn pixel(image: &[u8], width: u32, x: u32, y: u32) -> [u8; 4] {
+        let i = ((y * width + x) * 4) as usize;
+        [image[i], image[i + 1], image[i + 2], image[i + 3]]
+    }
+
+    #[test]
+    fn each_face_looks_down_its_axis() {
+        let scene = marker_scene();
+        let faces = render_cube_faces(&scene, 9);
+        // The centre of each face is the marker on that axis, seen by its dominant channels
+        let expected = [[true, false, false], [false, true, true], [false, true, false], [true, false, true], [false, false, true], [true, true, false]];
+        for ((face, pixels), channels) in faces.iter().zip(expected) {
+            let centre = pixel(pixels, 9, 4, 4);
+            for (c, lit) in channels.iter().enumerate() {
+                assert_eq!(centre[c] > 50, *lit, "{:?} centre is {:?}", face, centre);
+            }
+        }
+    }
+
+    #[test]
+    fn cross_places_faces_edge_to_edge() {
+        let scene = marker_scene();
+        let faces = render_cube_faces(&scene, 9);
+        let (cross, (width, height)) = cube_cross(&faces, 9);
+        assert_eq!((width, height), (36, 27));
+        // Front face in the middle, top face above it and the corners unused
+        assert_eq!(pixel(&cross, width, 13, 13), pixel(&faces[4].1, 9, 4, 4));
+        assert_eq!(pixel(&cross, width, 13, 4), pixel(&faces[2].1, 9, 4, 4));
+        assert_eq!(pixel(&cross, width, 0, 0), [0, 0, 0, 0]);
+
+        // The top face's bottom edge looks towards +Z, continuing the front face upwards
+        let up = Quat::from_euler_degrees(CubeFace::PositiveY.rotation()).rotate(Vec3::new(0.0, -1.0, 1.0));
+        assert!((up.normalized() - Vec3::new(0.0, 1.0, 1.0).normalized()).length() < 1e-5);
+        // Right of the front face is +X, and right of that is -Z
+        let right = |face: CubeFace| Quat::from_euler_degrees(face.rotation()).rotate(Vec3::new(1.0, 0.0, 1.0)).normalized();
+        assert!((right(CubeFace::PositiveZ) - Vec3::new(1.0, 0.0, 1.0).normalized()).length() < 1e-5);
+        assert!((right(CubeFace::PositiveX) - Vec3::new(1.0, 0.0, -1.0).normalized()).length() < 1e-5);
+    }
+
+    #[test]
+    fn writes_faces_or_a_cross() {
+        let scene = marker_scene();
+        let directory = std::env::temp_dir().join(format!("cube_map_{}", std::process::id()));
+        let faces = write_cube_map(&scene, 4, CubeMapOutput::Faces, &directory, "sky").unwrap();
+        assert_eq!(faces.len(), 6);
+        assert!(faces[0].ends_with("sky_px.png") && faces[5].ends_with("sky_nz.png"));
+        let cross = write_cube_map(&scene, 4, CubeMapOutput::Cross, &directory, "sky").unwrap();
+        assert_eq!(image::image_dimensions(&cross[0]).unwrap(), (16, 12));
+        let _ = std::fs::remove_dir_all(&directory);
+    }
+
+    #[test]
+    fn stereo_eyes_see_from_either_side() {
+        let mut scene = marker_scene();
+        // Looking at the +Z marker, the eyes far enough apart to see it shifted
+        scene.camera.set_resolution((16, 8));
+        let (pair, (width, height)) = render_stereo(&scene, 2.0, StereoLayout::SideBySide);
+        assert_eq!((width, height), (32, 8));
+        // The left eye sees the ball right of centre, the right eye left of centre
+        let blue = |x: u32| pixel(&pair, width, x, 4)[2] > 50;
+        assert!(blue(10) && !blue(5));
+        assert!(blue(16 + 5) && !blue(16 + 10));
+
+        let (stacked, dims) = render_stereo(&scene, 2.0, StereoLayout::OverUnder);
+        assert_eq!(dims, (16, 16));
+        for x in 0..16 {
+            assert_eq!(pixel(&stacked, 16, x, 4), pixel(&pair, 32, x, 4));
+            assert_eq!(pixel(&stacked, 16, x, 12), pixel(&pair, 32, 16 + x, 4));
+        }
+    }
+}
@@ -0,0 +1,162 @@
+// filepath: src/objects/capsule.rs
+#![allow(dead_code, reason = "no capsule in the demo scene; tests cover it")]
+use crate::aabb::Aabb;
+use crate::consts::EPS;
+use crate::ray::{Ray, Segment};
+use crate::roots::solve_quadratic;
+use crate::vec3::Vec3;
+use crate::material::Material;
+use crate::objects::hittable::Hittable;
+use crate::objects::local_frame::{azimuth_u, nearest, world_hits, LocalFrame};
+
+// Capsule: all points within `radius` of the segment from `start` to `end`. With `start` and
+// `end` together it's a sphere.
+#[derive(Debug, Clone)]
+pub(crate) struct Capsule {
+    frame: LocalFrame,
+    radius: f32,
+    length: f32,
+    material: Material,
+}
+
+impl Capsule {
+    pub(crate) fn new(start: Vec3, end: Vec3, radius: f32, material: Material) -> Self {
+        let length = (end - start).length();
+        let axis = if length > EPS { end - start } else { Vec3::new(0.0, 1.0, 0.0) };
+        let length = if length > EPS { length } else { 0.0 };
+        Capsule { frame: LocalFrame::new(start, axis), radius, length, material }
+    }
+
+    // Every crossing of the ray's line with the surface as (t, local normal)
+    pub(crate) fn local_hits(&self, o: Vec3, d: Vec3) -> Vec<(f32, Vec3)> {
+        let mut hits = Vec::new();
+        let r2 = self.radius * self.radius;
+
+        // Cylindrical body between the two hemispheres
+        let a = d.x() * d.x() + d.z() * d.z();
+        let b = 2.0 * (o.x() * d.x() + o.z() * d.z());
+        let c = o.x() * o.x() + o.z() * o.z() - r2;
+        if a > EPS * EPS && self.length > 0.0 {
+            for t in solve_quadratic(a, b, c, 4.0 * EPS) {
+                let p = o + d * t;
+                if (0.0..=self.length).contains(&p.y()) {
+                    hits.push((t, Vec3::new(p.x(), 0.0, p.z()) / self.radius));
+                }
+            }
+        }
+
+        // Hemispherical end caps, each only valid beyond its end of the segment, or one whole
+        // sphere when there's no segment
+        let caps: &[(f32, f32)] = if self.length > 0.0 { &[(0.0, -1.0), (self.length, 1.0)] } else { &[(0.0, 0.0)] };
+        for &(centre_y, side) in caps {
+            let centre = Vec3::new(0.0, centre_y, 0.0);
+            let oc = o - centre;
+            let b = 2.0 * oc.dot(&d);
+            let c = oc.dot(&oc) - r2;
+            for t in solve_quadratic(d.dot(&d), b, c, 4.0 * EPS) {
+                let p = o + d * t;
+                if (p.y() - centre_y) * side >= 0.0 {
+                    hits.push((t, (p - centre) / self.radius));
+                }
+            }
+        }
+        hits
+    }
+}
+
+impl Hittable for Capsule {
+    fn intersects_ray(&self, ray: &Ray) -> Option<(f32, Vec3, Vec3)> {
+        let o = self.frame.point_to_local(ray.origin);
+        let d = self.frame.dir_to_local(ray.direction);
+        let (t, n) = nearest(&self.local_hits(o, d), EPS)?;
+        let point = ray.origin + ray.direction * t;
+        Some((t, point, self.frame.dir_to_world(n)))
+    }
+
+    fn intersects_segment(&self, segment: &Segment) -> Option<(f32, Vec3, Vec3)> {
+        let dir = (segment.end - segment.start).normalized();
+        let ray = Ray::at_time(segment.start, dir, segment.time);
+        if let Some((t, point, normal)) = self.intersects_ray(&ray) {
+            let seg_len = (segment.end - segment.start).length();
+            if t <= seg_len + EPS {
+                return Some((t, point, normal));
+            }
+        }
+        None
+    }
+
+    fn intersections(&self, ray: &Ray) -> Vec<(f32, Vec3, Vec3)> {
+        let o = self.frame.point_to_local(ray.origin);
+        let d = self.frame.dir_to_local(ray.direction);
+        world_hits(&self.frame, ray, &self.local_hits(o, d), EPS)
+    }
+
+    // u wraps around the axis, v runs from the tip of the start cap to the tip of the end cap
+    fn uv(&self, point: &Vec3) -> (f32, f32) {
+        let p = self.frame.point_to_local(*point);
+        let total = self.length + 2.0 * self.radius;
+        (azimuth_u(p), ((p.y() + self.radius) / total).clamp(0.0, 1.0))
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(self.frame.bounds(Vec3::new(-self.radius, -self.radius, -self.radius), Vec3::new(self.radius, self.length + self.radius, self.radius)))
+    }
+
+    fn material(&self) -> &Material { &self.material }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unit_capsule() -> Capsule {
+        Capsule::new(Vec3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 2.0, 5.0), 1.0, Material::default())
+    }
+
+    #[test]
+    fn hits_body() {
+        let ray = Ray::new(Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, 0.0, 1.0));
+        let (t, _p, n) = unit_capsule().intersects_ray(&ray).expect("hit");
+        assert!((t - 4.0).abs() < 1e-4);
+        assert!((n - Vec3::new(0.0, 0.0, -1.0)).length() < 1e-4);
+    }
+
+    #[test]
+    fn hits_end_cap_tip() {
+        let ray = Ray::new(Vec3::new(0.0, 10.0, 5.0), Vec3::new(0.0, -1.0, 0.0));
+        let (t, p, n) = unit_capsule().intersects_ray(&ray).expect("hit");
+        assert!((t - 7.0).abs() < 1e-4);
+        assert!((p - Vec3::new(0.0, 3.0, 5.0)).length() < 1e-4);
+        assert!((n - Vec3::new(0.0, 1.0, 0.0)).length() < 1e-4);
+    }
+
+    #[test]
+    fn grazing_ray_touches_cap() {
+        // Tangent to the start hemisphere below the segment
+        let ray = Ray::new(Vec3::new(0.0, -1.0, 0.0), Vec3::new(0.0, 0.0, 1.0));
+        let (t, _p, n) = unit_capsule().intersects_ray(&ray).expect("grazing hit");
+        assert!((t - 5.0).abs() < 1e-2);
+        assert!((n - Vec3::new(0.0, -1.0, 0.0)).length() < 1e-2);
+    }
+
+    #[test]
+    fn zero_length_capsule_is_a_sphere() {
+        let centre = Vec3::new(0.0, 0.0, 5.0);
+        let ball = Capsule::new(centre, centre, 1.0, Material::default());
+        for direction in [Vec3::new(0.0, 0.0, 1.0), Vec3::new(0.0, 1.0, 0.0), Vec3::new(1.0, 1.0, 0.0).normalized()] {
+            let ray = Ray::new(centre - direction * 3.0, direction);
+            let (t, _p, n) = ball.intersects_ray(&ray).expect("hit");
+            assert!((t - 2.0).abs() < 1e-4);
+            assert!((n + direction).length() < 1e-4, "{:?}", n);
+            assert_eq!(ball.intersections(&ray).len(), 2);
+        }
+    }
+
+    #[test]
+    fn ray_from_inside_exits_through_cap() {
+        let ray = Ray::new(Vec3::new(0.0, 1.0, 5.0), Vec3::new(0.0, 1.0, 0.0));
+        let (t, _p, n) = unit_capsule().intersects_ray(&ray).expect("exit hit");
+        assert!((t - 2.0).abs() < 1e-4);
+        assert!((n - Vec3::new(0.0, 1.0, 0.0)).length() < 1e-4);
+    }
+}
@@ -0,0 +1,158 @@
+// filepath: src/objects/cone.rs
+#![allow(dead_code, reason = "the demo scene has no cones; its tests build them")]
+use crate::aabb::Aabb;
+use crate::consts::EPS;
+use crate::ray::{Ray, Segment};
+use crate::roots::solve_quadratic;
+use crate::vec3::Vec3;
+use crate::material::Material;
+use crate::objects::hittable::Hittable;
+use crate::objects::local_frame::{azimuth_u, nearest, world_hits, LocalFrame};
+
+// Capped cone: `position` is the centre of the base disc, the apex sits `height` along `axis`
+#[derive(Debug, Clone)]
+pub(crate) struct Cone {
+    frame: LocalFrame,
+    radius: f32,
+    height: f32,
+    material: Material,
+}
+
+impl Cone {
+    pub(crate) fn new(position: Vec3, axis: Vec3, radius: f32, height: f32, material: Material) -> Self {
+        Cone { frame: LocalFrame::new(position, axis), radius, height, material }
+    }
+
+    // Every crossing of the ray's line with the surface as (t, local normal)
+    pub(crate) fn local_hits(&self, o: Vec3, d: Vec3) -> Vec<(f32, Vec3)> {
+        let mut hits = Vec::new();
+        let k = self.radius / self.height;
+        let k2 = k * k;
+
+        // Slanted wall: x^2 + z^2 = k^2 (height - y)^2 for 0 <= y <= height
+        let h = self.height - o.y();
+        let a = d.x() * d.x() + d.z() * d.z() - k2 * d.y() * d.y();
+        let b = 2.0 * (o.x() * d.x() + o.z() * d.z() + k2 * h * d.y());
+        let c = o.x() * o.x() + o.z() * o.z() - k2 * h * h;
+        for t in solve_quadratic(a, b, c, 4.0 * EPS) {
+            let p = o + d * t;
+            if (0.0..=self.height).contains(&p.y()) {
+                let normal = Vec3::new(p.x(), k2 * (self.height - p.y()), p.z());
+                // The apex has no slope of its own, it points along the axis
+                let normal = if normal.length() > EPS * EPS { normal.normalized() } else { Vec3::new(0.0, 1.0, 0.0) };
+                hits.push((t, normal));
+            }
+        }
+
+        // Base cap
+        if d.y().abs() > EPS * EPS {
+            let t = -o.y() / d.y();
+            let p = o + d * t;
+            if p.x() * p.x() + p.z() * p.z() <= self.radius * self.radius {
+                hits.push((t, Vec3::new(0.0, -1.0, 0.0)));
+            }
+        }
+        hits
+    }
+}
+
+impl Hittable for Cone {
+    fn intersects_ray(&self, ray: &Ray) -> Option<(f32, Vec3, Vec3)> {
+        let o = self.frame.point_to_local(ray.origin);
+        let d = self.frame.dir_to_local(ray.direction);
+        let (t, n) = nearest(&self.local_hits(o, d), EPS)?;
+        let point = ray.origin + ray.direction * t;
+        Some((t, point, self.frame.dir_to_world(n)))
+    }
+
+    fn intersects_segment(&self, segment: &Segment) -> Option<(f32, Vec3, Vec3)> {
+        let dir = (segment.end - segment.start).normalized();
+        let ray = Ray::at_time(segment.start, dir, segment.time);
+        if let Some((t, point, normal)) = self.intersects_ray(&ray) {
+            let seg_len = (segment.end - segment.start).length();
+            if t <= seg_len + EPS {
+                return Some((t, point, normal));
+            }
+        }
+        None
+    }
+
+    fn intersections(&self, ray: &Ray) -> Vec<(f32, Vec3, Vec3)> {
+        let o = self.frame.point_to_local(ray.origin);
+        let d = self.frame.dir_to_local(ray.direction);
+        world_hits(&self.frame, ray, &self.local_hits(o, d), EPS)
+    }
+
+    fn uv(&self, point: &Vec3) -> (f32, f32) {
+        let p = self.frame.point_to_local(*point);
+        if p.y() > EPS {
+            (azimuth_u(p), p.y() / self.height)
+        } else {
+            // Planar mapping of the base disc
+            (0.5 + 0.5 * p.x() / self.radius, 0.5 + 0.5 * p.z() / self.radius)
+        }
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(self.frame.bounds(Vec3::new(-self.radius, 0.0, -self.radius), Vec3::new(self.radius, self.height, self.radius)))
+    }
+
+    fn material(&self) -> &Material { &self.material }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Base radius 1 at y = 0, apex at y = 2, centred on z = 5
+    fn unit_cone() -> Cone {
+        Cone::new(Vec3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 1.0, 0.0), 1.0, 2.0, Material::default())
+    }
+
+    #[test]
+    fn hits_slanted_wall() {
+        // At y = 1 the radius is 0.5
+        let ray = Ray::new(Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, 0.0, 1.0));
+        let (t, _p, n) = unit_cone().intersects_ray(&ray).expect("hit");
+        assert!((t - 4.5).abs() < 1e-4);
+        let expected = Vec3::new(0.0, 0.5, -1.0).normalized();
+        assert!((n - expected).length() < 1e-4);
+    }
+
+    #[test]
+    fn hits_base_from_below() {
+        let ray = Ray::new(Vec3::new(0.0, -3.0, 5.0), Vec3::new(0.0, 1.0, 0.0));
+        let (t, _p, n) = unit_cone().intersects_ray(&ray).expect("hit");
+        assert!((t - 3.0).abs() < 1e-4);
+        assert!((n - Vec3::new(0.0, -1.0, 0.0)).length() < 1e-4);
+    }
+
+    #[test]
+    fn grazing_ray_touches_wall() {
+        let ray = Ray::new(Vec3::new(0.5, 1.0, 0.0), Vec3::new(0.0, 0.0, 1.0));
+        let (t, _p, _n) = unit_cone().intersects_ray(&ray).expect("grazing hit");
+        assert!((t - 5.0).abs() < 1e-2);
+    }
+
+    #[test]
+    fn ray_from_inside_exits_through_base() {
+        let ray = Ray::new(Vec3::new(0.0, 0.5, 5.0), Vec3::new(0.0, -1.0, 0.0));
+        let (t, _p, n) = unit_cone().intersects_ray(&ray).expect("exit hit");
+        assert!((t - 0.5).abs() < 1e-4);
+        assert!((n - Vec3::new(0.0, -1.0, 0.0)).length() < 1e-4);
+    }
+
+    #[test]
+    fn apex_normal_is_the_axis() {
+        let ray = Ray::new(Vec3::new(0.0, 5.0, 5.0), Vec3::new(0.0, -1.0, 0.0));
+        let (t, _p, n) = unit_cone().intersects_ray(&ray).expect("apex hit");
+        assert!((t - 3.0).abs() < 1e-3);
+        assert_eq!(n, Vec3::new(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn ignores_mirrored_nappe_above_apex() {
+        let ray = Ray::new(Vec3::new(0.0, 3.0, 0.0), Vec3::new(0.0, 0.0, 1.0));
+        assert!(unit_cone().intersects_ray(&ray).is_none());
+    }
+}
@@ -0,0 +1,268 @@
+// filepath: src/objects/csg.rs
+#![allow(dead_code, reason = "CSG trees are assembled by scene code; the demo has none")]
+use crate::aabb::Aabb;
+use crate::consts::EPS;
+use crate::ray::{Ray, Segment};
+use crate::vec3::Vec3;
+use crate::material::Material;
+use crate::objects::hittable::Hittable;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) enum CsgOp {
+    Union,
+    Intersection,
+    Difference, // left minus right
+}
+
+impl CsgOp {
+    fn contains(&self, in_left: bool, in_right: bool) -> bool {
+        match self {
+            CsgOp::Union => in_left || in_right,
+            CsgOp::Intersection => in_left && in_right,
+            CsgOp::Difference => in_left && !in_right,
+        }
+    }
+}
+
+// Constructive solid geometry node over two closed children. Each child's crossings are turned
+// into inside intervals along the ray, and the intervals are combined with the boolean op.
+// Surfaces take the material of the child they came from.
+pub(crate) struct Csg {
+    op: CsgOp,
+    left: Box<dyn Hittable>,
+    right: Box<dyn Hittable>,
+}
+
+// (t, point, normal) where the ray enters or leaves a solid
+type Boundary = (f32, Vec3, Vec3);
+
+impl Csg {
+    pub(crate) fn new(op: CsgOp, left: Box<dyn Hittable>, right: Box<dyn Hittable>) -> Self {
+        Csg { op, left, right }
+    }
+
+    pub(crate) fn union(left: Box<dyn Hittable>, right: Box<dyn Hittable>) -> Self {
+        Csg::new(CsgOp::Union, left, right)
+    }
+
+    pub(crate) fn intersection(left: Box<dyn Hittable>, right: Box<dyn Hittable>) -> Self {
+        Csg::new(CsgOp::Intersection, left, right)
+    }
+
+    pub(crate) fn difference(left: Box<dyn Hittable>, right: Box<dyn Hittable>) -> Self {
+        Csg::new(CsgOp::Difference, left, right)
+    }
+
+    // The child whose surface a point of the combined surface lies on
+    fn child_at(&self, point: Vec3) -> &dyn Hittable {
+        if !on_surface(self.left.as_ref(), point) && on_surface(self.right.as_ref(), point) {
+            self.right.as_ref()
+        } else {
+            self.left.as_ref()
+        }
+    }
+
+    // Boundaries of the combined solid in front of the ray origin, sorted by t
+    fn boundaries(&self, ray: &Ray) -> Vec<Boundary> {
+        // Sweep over every interval endpoint of both children, tracking which solids we are in
+        let mut events: Vec<(Boundary, bool, bool)> = Vec::new(); // (boundary, is_left, entering)
+        for (is_left, child) in [(true, &self.left), (false, &self.right)] {
+            for (enter, exit) in inside_intervals(ray, &child.intersections(ray)) {
+                events.push((enter, is_left, true));
+                events.push((exit, is_left, false));
+            }
+        }
+        events.sort_by(|a, b| a.0.0.total_cmp(&b.0.0));
+
+        let (mut in_left, mut in_right) = (false, false);
+        let mut inside = false;
+        let mut out = Vec::new();
+        for ((t, point, normal), is_left, entering) in events {
+            if is_left { in_left = entering; } else { in_right = entering; }
+            let now_inside = self.op.contains(in_left, in_right);
+            if now_inside != inside {
+                inside = now_inside;
+                if t > EPS && t.is_finite() {
+                    // A subtracted solid's surface faces into the result
+                    let flip = self.op == CsgOp::Difference && !is_left;
+                    out.push((t, point, if flip { -normal } else { normal }));
+                }
+            }
+        }
+        out
+    }
+}
+
+// Pairs sorted crossings into (enter, exit) intervals using the normal to tell entering from leaving.
+// A leading exit means the ray started inside, a trailing entry means the solid is unbounded.
+fn inside_intervals(ray: &Ray, hits: &[(f32, Vec3, Vec3)]) -> Vec<(Boundary, Boundary)> {
+    let mut intervals = Vec::new();
+    let mut open: Option<Boundary> = None;
+    let starts_inside = hits.first().is_some_and(|(_, _, n)| n.dot(&ray.direction) > 0.0);
+    if starts_inside {
+        open = Some((f32::NEG_INFINITY, ray.origin, Vec3::new(0.0, 0.0, 0.0)));
+    }
+    for &hit in hits {
+        let entering = hit.2.dot(&ray.direction) < 0.0;
+        if entering {
+            if open.is_none() { open = Some(hit); }
+        } else if let Some(enter) = open.take() {
+            intervals.push((enter, hit));
+        }
+    }
+    if let Some(enter) = open {
+        intervals.push((enter, (f32::INFINITY, ray.origin, Vec3::new(0.0, 0.0, 0.0))));
+    }
+    intervals
+}
+
+// Whether a child has a surface crossing at `point`, probed by short rays along each axis so at
+// least one of them isn't tangent to the surface there
+fn on_surface(child: &dyn Hittable, point: Vec3) -> bool {
+    let axes = [Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, 0.0, 1.0)];
+    axes.into_iter().any(|axis| {
+        let probe = Ray::new(point - axis, axis);
+        child.intersections(&probe).iter().any(|h| (h.0 - 1.0).abs() < 1e-3)
+    })
+}
+
+impl Hittable for Csg {
+    fn intersects_ray(&self, ray: &Ray) -> Option<(f32, Vec3, Vec3)> {
+        self.boundaries(ray).into_iter().next()
+    }
+
+    fn intersects_segment(&self, segment: &Segment) -> Option<(f32, Vec3, Vec3)> {
+        let dir = (segment.end - segment.start).normalized();
+        let ray = Ray::at_time(segment.start, dir, segment.time);
+        if let Some((t, point, normal)) = self.intersects_ray(&ray) {
+            let seg_len = (segment.end - segment.start).length();
+            if t <= seg_len + EPS {
+                return Some((t, point, normal));
+            }
+        }
+        None
+    }
+
+    fn intersections(&self, ray: &Ray) -> Vec<(f32, Vec3, Vec3)> {
+        self.boundaries(ray)
+    }
+
+    // Parameterised by whichever child's surface the point is on
+    fn uv(&self, point: &Vec3) -> (f32, f32) {
+        self.child_at(*point).uv(point)
+    }
+
+    // The result never extends beyond the left child, except for unions
+    fn bounding_box(&self) -> Option<Aabb> {
+        match self.op {
+            CsgOp::Union => Some(self.left.bounding_box()?.union(&self.right.bounding_box()?)),
+            CsgOp::Intersection => self.left.bounding_box().or(self.right.bounding_box()),
+            CsgOp::Difference => self.left.bounding_box(),
+        }
+    }
+
+    // The left child's, for callers without a hit point
+    fn material(&self) -> &Material { self.left.material() }
+
+    fn material_at(&self, point: &Vec3) -> &Material {
+        self.child_at(*point).material_at(point)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+    use crate::objects::cylinder::Cylinder;
+    use crate::objects::sphere::Sphere;
+
+    fn sphere(x: f32, radius: f32) -> Box<dyn Hittable> {
+        Box::new(Sphere::new(radius, Vec3::new(x, 0.0, 10.0), Material::default()))
+    }
+
+    // Unit sphere at z = 10 with a vertical hole of radius 0.25 drilled through it
+    fn drilled_sphere() -> Csg {
+        let hole = Cylinder::new(Vec3::new(0.0, -5.0, 10.0), Vec3::new(0.0, 1.0, 0.0), 0.25, 10.0, Material::default());
+        Csg::difference(sphere(0.0, 1.0), Box::new(hole))
+    }
+
+    #[test]
+    fn ray_down_the_hole_misses() {
+        let ray = Ray::new(Vec3::new(0.0, 5.0, 10.0), Vec3::new(0.0, -1.0, 0.0));
+        assert!(drilled_sphere().intersects_ray(&ray).is_none());
+    }
+
+    #[test]
+    fn ray_across_the_hole_hits_the_inner_wall() {
+        let ray = Ray::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 1.0));
+        let hits = drilled_sphere().intersections(&ray);
+        let ts: Vec<f32> = hits.iter().map(|h| h.0).collect();
+        assert_eq!(ts.len(), 4, "{:?}", ts);
+        for (t, expected) in ts.iter().zip([9.0, 9.75, 10.25, 11.0]) {
+            assert!((t - expected).abs() < 1e-4, "{:?}", ts);
+        }
+        // Leaving the solid into the hole: the wall faces into the hole, along the ray
+        assert!((hits[1].2 - Vec3::new(0.0, 0.0, 1.0)).length() < 1e-4);
+    }
+
+    #[test]
+    fn intersection_of_overlapping_spheres_is_a_lens() {
+        let lens = Csg::intersection(sphere(-0.5, 1.0), sphere(0.5, 1.0));
+        let ray = Ray::new(Vec3::new(-5.0, 0.0, 10.0), Vec3::new(1.0, 0.0, 0.0));
+        let (t, p, n) = lens.intersects_ray(&ray).expect("hit");
+        assert!((t - 4.5).abs() < 1e-4);
+        assert!((p - Vec3::new(-0.5, 0.0, 10.0)).length() < 1e-4);
+        assert!((n - Vec3::new(-1.0, 0.0, 0.0)).length() < 1e-4);
+    }
+
+    #[test]
+    fn half_space_holds_a_ray_that_never_leaves_it() {
+        use crate::objects::infinite_plane::InfinitePlane;
+        // The part of the sphere below y = 0, seen from below the plane looking along it
+        let floor = InfinitePlane::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0), Material::default());
+        let cap = Csg::intersection(sphere(0.0, 1.0), Box::new(floor));
+        let along = Ray::new(Vec3::new(0.0, -0.5, 0.0), Vec3::new(0.0, 0.0, 1.0));
+        let (t, _p, n) = cap.intersects_ray(&along).expect("hits the sphere below the plane");
+        assert!((t - (10.0 - 0.75f32.sqrt())).abs() < 1e-4);
+        assert!(n.z() < 0.0);
+        // Heading deeper into the half-space and still passing through the sphere
+        let down = Ray::new(Vec3::new(0.0, -0.2, 0.0), Vec3::new(0.0, -0.5, 10.0).normalized());
+        assert!(cap.intersects_ray(&down).is_some());
+        // Above the plane the sphere is cut away
+        let above = Ray::new(Vec3::new(0.0, 0.5, 0.0), Vec3::new(0.0, 0.0, 1.0));
+        assert!(cap.intersects_ray(&above).is_none());
+    }
+
+    #[test]
+    fn uv_comes_from_the_child_that_was_hit() {
+        let blob = Csg::union(sphere(-0.5, 1.0), sphere(2.5, 1.0));
+        let ray = Ray::new(Vec3::new(2.5, 5.0, 10.0), Vec3::new(0.0, -1.0, 0.0));
+        let (_t, p, _n) = blob.intersects_ray(&ray).expect("top of the right sphere");
+        assert_eq!(blob.uv(&p), sphere(2.5, 1.0).uv(&p));
+        assert_ne!(blob.uv(&p), sphere(-0.5, 1.0).uv(&p));
+    }
+
+    #[test]
+    fn cut_face_shows_the_cutter_material() {
+        let red = Material::new(Color::new(0.4, 0.0, 0.0, 1.0), Color::new(0.8, 0.0, 0.0, 1.0), Color::new(1.0, 1.0, 1.0, 1.0), 16.0);
+        let cutter = Box::new(Sphere::new(1.0, Vec3::new(0.0, 0.0, 9.0), red));
+        let bitten = Csg::difference(sphere(0.0, 1.0), cutter);
+        // Straight in: the bite out of the front is the cutter's surface
+        let ray = Ray::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 1.0));
+        let (_t, p, _n) = bitten.intersects_ray(&ray).expect("hits the bite");
+        assert_eq!(bitten.material_at(&p).diffuse.r, 0.8);
+        // From the back the left sphere's own surface is hit
+        let back = Ray::new(Vec3::new(0.0, 0.0, 20.0), Vec3::new(0.0, 0.0, -1.0));
+        let (_t, p, _n) = bitten.intersects_ray(&back).expect("hits the back");
+        assert_eq!(bitten.material_at(&p).diffuse.r, 0.0);
+    }
+
+    #[test]
+    fn union_from_inside_exits_the_far_shape() {
+        let blob = Csg::union(sphere(-0.5, 1.0), sphere(0.5, 1.0));
+        let ray = Ray::new(Vec3::new(-0.5, 0.0, 10.0), Vec3::new(1.0, 0.0, 0.0));
+        let (t, _p, n) = blob.intersects_ray(&ray).expect("exit hit");
+        assert!((t - 2.0).abs() < 1e-4);
+        assert!((n - Vec3::new(1.0, 0.0, 0.0)).length() < 1e-4);
+    }
+}
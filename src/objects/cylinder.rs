@@ -0,0 +1,149 @@
+// filepath: src/objects/cylinder.rs
+#![allow(dead_code, reason = "not placed in the demo scene, which is a sphere on a plane")]
+use crate::aabb::Aabb;
+use crate::consts::EPS;
+use crate::ray::{Ray, Segment};
+use crate::roots::solve_quadratic;
+use crate::vec3::Vec3;
+use crate::material::Material;
+use crate::objects::hittable::Hittable;
+use crate::objects::local_frame::{azimuth_u, nearest, world_hits, LocalFrame};
+
+// Capped cylinder: `position` is the centre of the base cap, the body extends `height` along `axis`
+#[derive(Debug, Clone)]
+pub(crate) struct Cylinder {
+    frame: LocalFrame,
+    radius: f32,
+    height: f32,
+    material: Material,
+}
+
+impl Cylinder {
+    pub(crate) fn new(position: Vec3, axis: Vec3, radius: f32, height: f32, material: Material) -> Self {
+        Cylinder { frame: LocalFrame::new(position, axis), radius, height, material }
+    }
+
+    // Every crossing of the ray's line with the surface as (t, local normal)
+    pub(crate) fn local_hits(&self, o: Vec3, d: Vec3) -> Vec<(f32, Vec3)> {
+        let mut hits = Vec::new();
+        let r2 = self.radius * self.radius;
+
+        // Side wall: x^2 + z^2 = r^2 for 0 <= y <= height
+        let a = d.x() * d.x() + d.z() * d.z();
+        let b = 2.0 * (o.x() * d.x() + o.z() * d.z());
+        let c = o.x() * o.x() + o.z() * o.z() - r2;
+        if a > EPS * EPS {
+            for t in solve_quadratic(a, b, c, 4.0 * EPS) {
+                let p = o + d * t;
+                if (0.0..=self.height).contains(&p.y()) {
+                    hits.push((t, Vec3::new(p.x(), 0.0, p.z()) / self.radius));
+                }
+            }
+        }
+
+        // Caps
+        if d.y().abs() > EPS * EPS {
+            for (cap_y, normal_y) in [(0.0, -1.0), (self.height, 1.0)] {
+                let t = (cap_y - o.y()) / d.y();
+                let p = o + d * t;
+                if p.x() * p.x() + p.z() * p.z() <= r2 {
+                    hits.push((t, Vec3::new(0.0, normal_y, 0.0)));
+                }
+            }
+        }
+        hits
+    }
+}
+
+impl Hittable for Cylinder {
+    fn intersects_ray(&self, ray: &Ray) -> Option<(f32, Vec3, Vec3)> {
+        let o = self.frame.point_to_local(ray.origin);
+        let d = self.frame.dir_to_local(ray.direction);
+        let (t, n) = nearest(&self.local_hits(o, d), EPS)?;
+        let point = ray.origin + ray.direction * t;
+        Some((t, point, self.frame.dir_to_world(n)))
+    }
+
+    fn intersects_segment(&self, segment: &Segment) -> Option<(f32, Vec3, Vec3)> {
+        let dir = (segment.end - segment.start).normalized();
+        let ray = Ray::at_time(segment.start, dir, segment.time);
+        if let Some((t, point, normal)) = self.intersects_ray(&ray) {
+            let seg_len = (segment.end - segment.start).length();
+            if t <= seg_len + EPS {
+                return Some((t, point, normal));
+            }
+        }
+        None
+    }
+
+    fn intersections(&self, ray: &Ray) -> Vec<(f32, Vec3, Vec3)> {
+        let o = self.frame.point_to_local(ray.origin);
+        let d = self.frame.dir_to_local(ray.direction);
+        world_hits(&self.frame, ray, &self.local_hits(o, d), EPS)
+    }
+
+    fn uv(&self, point: &Vec3) -> (f32, f32) {
+        let p = self.frame.point_to_local(*point);
+        let on_side = p.x() * p.x() + p.z() * p.z() >= self.radius * self.radius * (1.0 - EPS);
+        if on_side && p.y() > EPS && p.y() < self.height - EPS {
+            (azimuth_u(p), p.y() / self.height)
+        } else {
+            // Planar mapping of the cap disc
+            (0.5 + 0.5 * p.x() / self.radius, 0.5 + 0.5 * p.z() / self.radius)
+        }
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(self.frame.bounds(Vec3::new(-self.radius, 0.0, -self.radius), Vec3::new(self.radius, self.height, self.radius)))
+    }
+
+    fn material(&self) -> &Material { &self.material }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unit_cylinder() -> Cylinder {
+        Cylinder::new(Vec3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 1.0, 0.0), 1.0, 2.0, Material::default())
+    }
+
+    #[test]
+    fn hits_side_wall() {
+        let ray = Ray::new(Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, 0.0, 1.0));
+        let (t, p, n) = unit_cylinder().intersects_ray(&ray).expect("hit");
+        assert!((t - 4.0).abs() < 1e-4);
+        assert!((p - Vec3::new(0.0, 1.0, 4.0)).length() < 1e-4);
+        assert!((n - Vec3::new(0.0, 0.0, -1.0)).length() < 1e-4);
+    }
+
+    #[test]
+    fn hits_top_cap_from_above() {
+        let ray = Ray::new(Vec3::new(0.2, 10.0, 5.0), Vec3::new(0.0, -1.0, 0.0));
+        let (t, _p, n) = unit_cylinder().intersects_ray(&ray).expect("hit");
+        assert!((t - 8.0).abs() < 1e-4);
+        assert!((n - Vec3::new(0.0, 1.0, 0.0)).length() < 1e-4);
+    }
+
+    #[test]
+    fn grazing_ray_touches_wall() {
+        let ray = Ray::new(Vec3::new(1.0, 1.0, 0.0), Vec3::new(0.0, 0.0, 1.0));
+        let (t, _p, n) = unit_cylinder().intersects_ray(&ray).expect("grazing hit");
+        assert!((t - 5.0).abs() < 1e-2);
+        assert!((n - Vec3::new(1.0, 0.0, 0.0)).length() < 1e-2);
+    }
+
+    #[test]
+    fn ray_from_inside_exits_through_wall() {
+        let ray = Ray::new(Vec3::new(0.0, 1.0, 5.0), Vec3::new(1.0, 0.0, 0.0));
+        let (t, _p, n) = unit_cylinder().intersects_ray(&ray).expect("exit hit");
+        assert!((t - 1.0).abs() < 1e-4);
+        assert!((n - Vec3::new(1.0, 0.0, 0.0)).length() < 1e-4);
+    }
+
+    #[test]
+    fn misses_above_the_top() {
+        let ray = Ray::new(Vec3::new(0.0, 3.0, 0.0), Vec3::new(0.0, 0.0, 1.0));
+        assert!(unit_cylinder().intersects_ray(&ray).is_none());
+    }
+}
@@ -0,0 +1,114 @@
+// filepath: src/objects/disc.rs
+use crate::aabb::Aabb;
+use crate::consts::EPS;
+use crate::ray::{Ray, Segment};
+use crate::vec3::Vec3;
+use crate::material::Material;
+use crate::objects::hittable::Hittable;
+
+// Flat circular disc, single sided like InfinitePlane. Turned towards the camera it makes a
+// cheap billboard for point-like things such as particles.
+#[derive(Debug, Clone)]
+pub(crate) struct Disc {
+    position: Vec3,
+    normal: Vec3,
+    radius: f32,
+    material: Material,
+}
+
+impl Disc {
+    pub(crate) fn new(position: Vec3, normal: Vec3, radius: f32, material: Material) -> Self {
+        Disc { position, normal: normal.normalized(), radius, material }
+    }
+
+    // Disc at `position` facing `eye`
+    pub(crate) fn billboard(position: Vec3, eye: Vec3, radius: f32, material: Material) -> Self {
+        Disc::new(position, eye - position, radius, material)
+    }
+}
+
+impl Hittable for Disc {
+    fn intersects_ray(&self, ray: &Ray) -> Option<(f32, Vec3, Vec3)> {
+        let denom = self.normal.dot(&ray.direction);
+        if denom.abs() < EPS { return None; }
+        let t = (self.position - ray.origin).dot(&self.normal) / denom;
+        if t <= EPS { return None; }
+        let point = ray.origin + ray.direction * t;
+        if (point - self.position).length() > self.radius { return None; }
+        Some((t, point, self.normal))
+    }
+
+    fn intersects_segment(&self, segment: &Segment) -> Option<(f32, Vec3, Vec3)> {
+        let dir = (segment.end - segment.start).normalized();
+        let ray = Ray::at_time(segment.start, dir, segment.time);
+        if let Some((t, point, normal)) = self.intersects_ray(&ray) {
+            let seg_len = (segment.end - segment.start).length();
+            if t <= seg_len + EPS {
+                return Some((t, point, normal));
+            }
+        }
+        None
+    }
+
+    // A disc encloses nothing, so to CSG it is a solid of zero thickness: entered and left at
+    // the same t. A lone crossing would read as entering something never left.
+    fn intersections(&self, ray: &Ray) -> Vec<(f32, Vec3, Vec3)> {
+        let Some((t, point, normal)) = self.intersects_ray(ray) else { return Vec::new() };
+        let facing = if normal.dot(&ray.direction) < 0.0 { normal } else { -normal };
+        vec![(t, point, facing), (t, point, -facing)]
+    }
+
+    // Polar mapping: u around the rim, v from the centre out
+    fn uv(&self, point: &Vec3) -> (f32, f32) {
+        let (tangent, bitangent) = self.normal.orthonormal_basis();
+        let local = *point - self.position;
+        let u = 0.5 + local.dot(&bitangent).atan2(local.dot(&tangent)) / (2.0 * std::f32::consts::PI);
+        let v = (local.length() / self.radius).clamp(0.0, 1.0);
+        (u, v)
+    }
+
+    // Tight per axis: the disc reaches radius * sqrt(1 - n_i^2) along axis i
+    fn bounding_box(&self) -> Option<Aabb> {
+        let n = self.normal;
+        let extent = Vec3::new(
+            self.radius * (1.0 - n.x() * n.x()).max(0.0).sqrt(),
+            self.radius * (1.0 - n.y() * n.y()).max(0.0).sqrt(),
+            self.radius * (1.0 - n.z() * n.z()).max(0.0).sqrt(),
+        );
+        Some(Aabb::new(self.position - extent, self.position + extent))
+    }
+
+    fn material(&self) -> &Material { &self.material }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn billboard_faces_the_eye() {
+        let eye = Vec3::new(0.0, 0.0, 0.0);
+        let disc = Disc::billboard(Vec3::new(0.0, 0.0, 10.0), eye, 0.5, Material::default());
+        let ray = Ray::new(eye, Vec3::new(0.0, 0.04, 1.0).normalized());
+        let (t, _p, n) = disc.intersects_ray(&ray).expect("hit");
+        assert!((t - 10.008).abs() < 1e-3);
+        assert!((n - Vec3::new(0.0, 0.0, -1.0)).length() < 1e-6);
+        // Outside the rim
+        let ray = Ray::new(eye, Vec3::new(0.0, 0.06, 1.0).normalized());
+        assert!(disc.intersects_ray(&ray).is_none());
+    }
+
+    #[test]
+    fn tilted_disc_bounds_contain_its_rim() {
+        let disc = Disc::new(Vec3::new(1.0, 2.0, 3.0), Vec3::new(1.0, 1.0, 0.0), 2.0, Material::default());
+        let bounds = disc.bounding_box().unwrap();
+        let (t, b) = disc.normal.orthonormal_basis();
+        for i in 0..16 {
+            let a = i as f32 * std::f32::consts::PI / 8.0;
+            let p = disc.position + (t * a.cos() + b * a.sin()) * disc.radius;
+            assert!(p.x() >= bounds.min.x() - 1e-5 && p.x() <= bounds.max.x() + 1e-5);
+            assert!(p.y() >= bounds.min.y() - 1e-5 && p.y() <= bounds.max.y() + 1e-5);
+            assert!(p.z() >= bounds.min.z() - 1e-5 && p.z() <= bounds.max.z() + 1e-5);
+        }
+    }
+}
@@ -0,0 +1,183 @@
+// filepath: src/objects/distance_field.rs
+#![allow(dead_code, reason = "SDF objects are for prototyping shapes in scene code")]
+use crate::aabb::Aabb;
+use crate::consts::EPS;
+use crate::ray::{Ray, Segment};
+use crate::vec3::Vec3;
+use crate::material::Material;
+use crate::objects::hittable::Hittable;
+
+// Surface defined implicitly by a signed distance function (see `sdf.rs`), rendered by sphere tracing.
+// Handy for shapes with no closed-form intersection.
+pub(crate) struct DistanceField {
+    distance: Box<dyn Fn(Vec3) -> f32>,
+    material: Material,
+    max_steps: usize,
+    max_distance: f32,
+    hit_epsilon: f32,
+    // Fraction of the distance bound taken per step, < 1 for fields that overestimate (smooth ops, repeats)
+    step_scale: f32,
+    bounds: Option<Aabb>,
+}
+
+impl DistanceField {
+    pub(crate) fn new(distance: impl Fn(Vec3) -> f32 + 'static, material: Material) -> Self {
+        DistanceField {
+            distance: Box::new(distance),
+            material,
+            max_steps: 256,
+            max_distance: 1000.0,
+            hit_epsilon: 1e-4,
+            step_scale: 1.0,
+            bounds: None,
+        }
+    }
+
+    pub(crate) fn set_march_limits(&mut self, max_steps: usize, max_distance: f32) {
+        self.max_steps = max_steps;
+        self.max_distance = max_distance;
+    }
+
+    pub(crate) fn set_hit_epsilon(&mut self, hit_epsilon: f32) {
+        self.hit_epsilon = hit_epsilon;
+    }
+
+    pub(crate) fn set_step_scale(&mut self, step_scale: f32) {
+        self.step_scale = step_scale.clamp(0.01, 1.0);
+    }
+
+    // Optional box known to contain the surface, lets rays that miss it skip marching
+    pub(crate) fn set_bounds(&mut self, bounds: Aabb) {
+        self.bounds = Some(bounds);
+    }
+
+    pub(crate) fn distance(&self, p: Vec3) -> f32 {
+        (self.distance)(p)
+    }
+
+    // Gradient by central differences
+    pub(crate) fn normal_at(&self, p: Vec3) -> Vec3 {
+        let h = (self.hit_epsilon * 2.0).max(1e-4);
+        let dx = Vec3::new(h, 0.0, 0.0);
+        let dy = Vec3::new(0.0, h, 0.0);
+        let dz = Vec3::new(0.0, 0.0, h);
+        Vec3::new(
+            self.distance(p + dx) - self.distance(p - dx),
+            self.distance(p + dy) - self.distance(p - dy),
+            self.distance(p + dz) - self.distance(p - dz),
+        ).normalized()
+    }
+
+    // Sphere traces along the ray and reports surface crossings, stopping after `max_hits`.
+    // A crossing only counts once the march has left the surface band it started in, so rays
+    // leaving a surface (shadow rays) don't hit themselves.
+    fn march(&self, ray: &Ray, max_hits: usize) -> Vec<(f32, Vec3, Vec3)> {
+        let mut hits = Vec::new();
+        if let Some(bounds) = &self.bounds
+            && !bounds.hit(ray, f32::INFINITY) {
+            return hits;
+        }
+        let speed = ray.direction.length();
+        if speed == 0.0 { return hits; }
+        let dir = ray.direction / speed;
+
+        let mut s = EPS; // distance travelled along the unit direction
+        let mut left_surface = false;
+        for _ in 0..self.max_steps {
+            if s > self.max_distance { break; }
+            let p = ray.origin + dir * s;
+            let d = self.distance(p);
+            if d.abs() < self.hit_epsilon {
+                if left_surface {
+                    hits.push((s / speed, p, self.normal_at(p)));
+                    if hits.len() >= max_hits { break; }
+                    left_surface = false;
+                }
+            } else {
+                left_surface = true;
+            }
+            s += (d.abs() * self.step_scale).max(self.hit_epsilon);
+        }
+        hits
+    }
+}
+
+impl Hittable for DistanceField {
+    fn intersects_ray(&self, ray: &Ray) -> Option<(f32, Vec3, Vec3)> {
+        self.march(ray, 1).into_iter().next()
+    }
+
+    fn intersects_segment(&self, segment: &Segment) -> Option<(f32, Vec3, Vec3)> {
+        let dir = (segment.end - segment.start).normalized();
+        let ray = Ray::at_time(segment.start, dir, segment.time);
+        if let Some((t, point, normal)) = self.intersects_ray(&ray) {
+            let seg_len = (segment.end - segment.start).length();
+            if t <= seg_len + EPS {
+                return Some((t, point, normal));
+            }
+        }
+        None
+    }
+
+    fn intersections(&self, ray: &Ray) -> Vec<(f32, Vec3, Vec3)> {
+        self.march(ray, usize::MAX)
+    }
+
+    // No natural parameterisation, map the normal direction like a sphere
+    fn uv(&self, point: &Vec3) -> (f32, f32) {
+        let n = self.normal_at(*point);
+        let u = 0.5 + n.z().atan2(n.x()) / (2.0 * std::f32::consts::PI);
+        let v = 0.5 + n.y().clamp(-1.0, 1.0).asin() / std::f32::consts::PI;
+        (u, v)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> { self.bounds }
+
+    fn material(&self) -> &Material { &self.material }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sdf::{op_subtract, sd_box, sd_sphere};
+
+    #[test]
+    fn traces_a_sphere() {
+        let field = DistanceField::new(sd_sphere(Vec3::new(0.0, 0.0, 10.0), 2.0), Material::default());
+        let ray = Ray::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 1.0));
+        let (t, _p, n) = field.intersects_ray(&ray).expect("hit");
+        assert!((t - 8.0).abs() < 1e-3);
+        assert!((n - Vec3::new(0.0, 0.0, -1.0)).length() < 1e-3);
+    }
+
+    #[test]
+    fn ray_from_inside_finds_the_exit() {
+        let field = DistanceField::new(sd_sphere(Vec3::new(0.0, 0.0, 0.0), 2.0), Material::default());
+        let ray = Ray::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        let (t, _p, n) = field.intersects_ray(&ray).expect("exit hit");
+        assert!((t - 2.0).abs() < 1e-3);
+        assert!((n - Vec3::new(1.0, 0.0, 0.0)).length() < 1e-3);
+    }
+
+    #[test]
+    fn reports_every_crossing_of_a_hollow_box() {
+        let shell = op_subtract(
+            sd_box(Vec3::new(0.0, 0.0, 10.0), Vec3::new(2.0, 2.0, 2.0)),
+            sd_box(Vec3::new(0.0, 0.0, 10.0), Vec3::new(1.0, 1.0, 1.0)),
+        );
+        let field = DistanceField::new(shell, Material::default());
+        let ray = Ray::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 1.0));
+        let ts: Vec<f32> = field.intersections(&ray).iter().map(|h| h.0).collect();
+        assert_eq!(ts.len(), 4, "{:?}", ts);
+        for (t, expected) in ts.iter().zip([8.0, 9.0, 11.0, 12.0]) {
+            assert!((t - expected).abs() < 1e-3, "{:?}", ts);
+        }
+    }
+
+    #[test]
+    fn shadow_ray_leaving_the_surface_does_not_self_hit() {
+        let field = DistanceField::new(sd_sphere(Vec3::new(0.0, 0.0, 0.0), 1.0), Material::default());
+        let segment = Segment::new(Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, 5.0, 0.0));
+        assert!(field.intersects_segment(&segment).is_none());
+    }
+}
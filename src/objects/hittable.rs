@@ -1,4 +1,5 @@
 // filepath: src/objects/hittable.rs
+use crate::aabb::Aabb;
 use crate::material::Material;
 use crate::ray::{Ray, Segment};
 use crate::vec3::Vec3;
@@ -6,6 +7,15 @@ use crate::vec3::Vec3;
 pub(crate) trait Hittable {
     fn intersects_ray(&self, ray: &Ray) -> Option<(f32, Vec3, Vec3)>; // (t, point, normal)
     fn intersects_segment(&self, segment: &Segment) -> Option<(f32, Vec3, Vec3)>;
+    // Every surface crossing in front of the ray origin, sorted by t, normals facing outward.
+    // Needed by CSG to know where the ray enters and leaves a solid. An unbounded solid that
+    // holds the origin and is never left reports an entry at t = -inf instead of nothing.
+    fn intersections(&self, ray: &Ray) -> Vec<(f32, Vec3, Vec3)>;
+    fn uv(&self, point: &Vec3) -> (f32, f32); // surface parameterisation at a hit point, in [0, 1]
+    // World space bounds for culling, None if the object is unbounded
+    fn bounding_box(&self) -> Option<Aabb>;
     fn material(&self) -> &Material;
+    // Material at a hit point, for objects made of parts that differ
+    fn material_at(&self, _point: &Vec3) -> &Material { self.material() }
 }
 
@@ -1,11 +1,12 @@
 // filepath: src/objects/infinite_plane.rs
+use crate::aabb::Aabb;
 use crate::consts::EPS;
 use crate::ray::Ray;
 use crate::vec3::Vec3;
 use crate::material::Material;
 use crate::objects::hittable::Hittable;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub(crate) struct InfinitePlane {
     position: Vec3,
     normal: Vec3,
@@ -18,6 +19,15 @@ impl InfinitePlane {
     }
 
     fn material(&self) -> &Material { &self.material }
+
+    pub(crate) fn position(&self) -> Vec3 { self.position }
+    pub(crate) fn normal(&self) -> Vec3 { self.normal }
+    pub(crate) fn set_position(&mut self, position: Vec3) { self.position = position; }
+
+    // Distance of a point above (positive) or below (negative) the plane
+    pub(crate) fn signed_distance(&self, point: Vec3) -> f32 {
+        (point - self.position).dot(&self.normal)
+    }
 }
 
 impl Hittable for InfinitePlane {
@@ -31,7 +41,7 @@ impl Hittable for InfinitePlane {
     }
     fn intersects_segment(&self, segment: &crate::ray::Segment) -> Option<(f32, Vec3, Vec3)> {
         let dir = (segment.end - segment.start).normalized();
-        let ray = Ray::new(segment.start, dir);
+        let ray = Ray::at_time(segment.start, dir, segment.time);
         if let Some((t, point, normal)) = self.intersects_ray(&ray) {
             let seg_len = (segment.end - segment.start).length();
             if t <= seg_len + EPS {
@@ -40,6 +50,22 @@ impl Hittable for InfinitePlane {
         }
         None
     }
+    // The solid side of the plane is the half-space behind the normal. A ray starting in there
+    // that never crosses out is inside all the way, which CSG needs to hear about.
+    fn intersections(&self, ray: &Ray) -> Vec<(f32, Vec3, Vec3)> {
+        match self.intersects_ray(ray) {
+            Some(hit) => vec![hit],
+            None if self.signed_distance(ray.origin) < 0.0 => vec![(f32::NEG_INFINITY, ray.origin, -ray.direction)],
+            None => Vec::new(),
+        }
+    }
+    // Planar mapping that repeats every world unit
+    fn uv(&self, point: &Vec3) -> (f32, f32) {
+        let (tangent, bitangent) = self.normal.orthonormal_basis();
+        let local = *point - self.position;
+        (local.dot(&tangent).rem_euclid(1.0), local.dot(&bitangent).rem_euclid(1.0))
+    }
+    fn bounding_box(&self) -> Option<Aabb> { None }
     fn material(&self) -> &Material { &self.material }
 }
 
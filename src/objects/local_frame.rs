@@ -0,0 +1,73 @@
+// filepath: src/objects/local_frame.rs
+use crate::aabb::Aabb;
+use crate::ray::Ray;
+use crate::vec3::Vec3;
+
+// Orthonormal frame for primitives defined around an axis. Local +Y is the axis, so shapes can
+// intersect in canonical coordinates and map normals back to world space.
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct LocalFrame {
+    origin: Vec3,
+    u: Vec3,
+    axis: Vec3,
+    w: Vec3,
+}
+
+impl LocalFrame {
+    pub(crate) fn new(origin: Vec3, axis: Vec3) -> Self {
+        let axis = axis.normalized();
+        // (w, u, axis) is right handed, so (u, axis, w) is as well
+        let (w, u) = axis.orthonormal_basis();
+        LocalFrame { origin, u, axis, w }
+    }
+
+    pub(crate) fn point_to_local(&self, p: Vec3) -> Vec3 {
+        self.dir_to_local(p - self.origin)
+    }
+
+    pub(crate) fn dir_to_local(&self, d: Vec3) -> Vec3 {
+        Vec3::new(d.dot(&self.u), d.dot(&self.axis), d.dot(&self.w))
+    }
+
+    pub(crate) fn dir_to_world(&self, d: Vec3) -> Vec3 {
+        self.u * d.x() + self.axis * d.y() + self.w * d.z()
+    }
+
+    // World space bounds of a local space box
+    pub(crate) fn bounds(&self, local_min: Vec3, local_max: Vec3) -> Aabb {
+        let corners: Vec<Vec3> = (0..8)
+            .map(|i| {
+                let x = if i & 1 == 0 { local_min.x() } else { local_max.x() };
+                let y = if i & 2 == 0 { local_min.y() } else { local_max.y() };
+                let z = if i & 4 == 0 { local_min.z() } else { local_max.z() };
+                self.origin + self.dir_to_world(Vec3::new(x, y, z))
+            })
+            .collect();
+        Aabb::from_points(&corners).expect("eight corners")
+    }
+}
+
+// Picks the closest line hit in front of the ray origin from (t, local normal) candidates
+pub(crate) fn nearest(hits: &[(f32, Vec3)], min_t: f32) -> Option<(f32, Vec3)> {
+    hits.iter()
+        .filter(|(t, _)| *t > min_t)
+        .fold(None, |best: Option<(f32, Vec3)>, &(t, n)| match best {
+            Some((bt, _)) if bt <= t => best,
+            _ => Some((t, n)),
+        })
+}
+
+// All line hits in front of the ray origin as world space (t, point, normal), sorted by t
+pub(crate) fn world_hits(frame: &LocalFrame, ray: &Ray, hits: &[(f32, Vec3)], min_t: f32) -> Vec<(f32, Vec3, Vec3)> {
+    let mut out: Vec<(f32, Vec3, Vec3)> = hits.iter()
+        .filter(|(t, _)| *t > min_t)
+        .map(|&(t, n)| (t, ray.origin + ray.direction * t, frame.dir_to_world(n)))
+        .collect();
+    out.sort_by(|a, b| a.0.total_cmp(&b.0));
+    out
+}
+
+// Azimuth around local +Y mapped to [0, 1]
+pub(crate) fn azimuth_u(local: Vec3) -> f32 {
+    0.5 + local.z().atan2(local.x()) / (2.0 * std::f32::consts::PI)
+}
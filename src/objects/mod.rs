@@ -3,4 +3,15 @@
 pub mod sphere;
 pub mod infinite_plane;
 pub mod hittable;
+pub mod cylinder;
+pub mod cone;
+pub mod capsule;
+pub mod torus;
+pub mod triangle_mesh;
+pub mod transformed;
+pub mod csg;
+pub mod distance_field;
+pub mod disc;
+pub mod moving;
+mod local_frame;
 
@@ -0,0 +1,101 @@
+// filepath: src/objects/moving.rs
+use crate::aabb::Aabb;
+use crate::consts::EPS;
+use crate::ray::{Ray, Segment};
+use crate::vec3::Vec3;
+use crate::material::Material;
+use crate::objects::hittable::Hittable;
+
+// A Hittable travelling at constant velocity while the shutter is open. At ray time t the
+// object sits at its own position plus velocity * t, so rays spread over the shutter interval
+// see it smeared along its path.
+pub(crate) struct Moving {
+    inner: Box<dyn Hittable>,
+    velocity: Vec3,
+    shutter: (f32, f32), // only used to bound the swept volume
+}
+
+impl Moving {
+    pub(crate) fn new(inner: Box<dyn Hittable>, velocity: Vec3, shutter: (f32, f32)) -> Self {
+        Moving { inner, velocity, shutter }
+    }
+
+    // How far the object has moved from its own position at `time`
+    pub(crate) fn offset_at(&self, time: f32) -> Vec3 {
+        self.velocity * time
+    }
+
+    // Same hits as moving the object, without touching it
+    fn ray_to_object(&self, ray: &Ray) -> Ray {
+        Ray::at_time(ray.origin - self.offset_at(ray.time), ray.direction, ray.time)
+    }
+}
+
+impl Hittable for Moving {
+    fn intersects_ray(&self, ray: &Ray) -> Option<(f32, Vec3, Vec3)> {
+        let (t, p, n) = self.inner.intersects_ray(&self.ray_to_object(ray))?;
+        Some((t, p + self.offset_at(ray.time), n))
+    }
+
+    fn intersects_segment(&self, segment: &Segment) -> Option<(f32, Vec3, Vec3)> {
+        let dir = (segment.end - segment.start).normalized();
+        let ray = Ray::at_time(segment.start, dir, segment.time);
+        if let Some((t, point, normal)) = self.intersects_ray(&ray) {
+            let seg_len = (segment.end - segment.start).length();
+            if t <= seg_len + EPS {
+                return Some((t, point, normal));
+            }
+        }
+        None
+    }
+
+    fn intersections(&self, ray: &Ray) -> Vec<(f32, Vec3, Vec3)> {
+        let offset = self.offset_at(ray.time);
+        self.inner.intersections(&self.ray_to_object(ray))
+            .into_iter()
+            .map(|(t, p, n)| (t, p + offset, n))
+            .collect()
+    }
+
+    // The hit point's time isn't known here, so texture coordinates are taken at time zero
+    fn uv(&self, point: &Vec3) -> (f32, f32) {
+        self.inner.uv(point)
+    }
+
+    // Everywhere the object goes while the shutter is open
+    fn bounding_box(&self) -> Option<Aabb> {
+        let b = self.inner.bounding_box()?;
+        let (open, close) = (self.offset_at(self.shutter.0), self.offset_at(self.shutter.1));
+        Some(Aabb::new(b.min + open, b.max + open).union(&Aabb::new(b.min + close, b.max + close)))
+    }
+
+    fn material(&self) -> &Material { self.inner.material() }
+
+    // Like uv, taken at time zero
+    fn material_at(&self, point: &Vec3) -> &Material { self.inner.material_at(point) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::objects::sphere::Sphere;
+
+    #[test]
+    fn hits_follow_the_ray_time() {
+        let sphere = Sphere::new(1.0, Vec3::new(0.0, 0.0, 10.0), Material::default());
+        let moving = Moving::new(Box::new(sphere), Vec3::new(4.0, 0.0, 0.0), (0.0, 1.0));
+        let ray = |time| Ray::at_time(Vec3::new(2.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 1.0), time);
+        assert!(moving.intersects_ray(&ray(0.0)).is_none());
+        let (t, p, n) = moving.intersects_ray(&ray(0.5)).expect("centred at half time");
+        assert!((t - 9.0).abs() < 1e-4);
+        assert!((p - Vec3::new(2.0, 0.0, 9.0)).length() < 1e-4);
+        assert!((n - Vec3::new(0.0, 0.0, -1.0)).length() < 1e-4);
+        // Shadow segments see it at their own time too
+        let segment = |time| Segment::at_time(Vec3::new(2.0, 0.0, 0.0), Vec3::new(2.0, 0.0, 20.0), time);
+        assert!(moving.intersects_segment(&segment(0.5)).is_some());
+        assert!(moving.intersects_segment(&segment(1.0)).is_none());
+        // Bounds cover the whole sweep
+        let bounds = moving.bounding_box().unwrap();
+        assert!((bounds.min.x() + 1.0).abs() < 1e-5 && (bounds.max.x() - 5.0).abs() < 1e-5);
+    }
+}
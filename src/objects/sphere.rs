@@ -1,12 +1,13 @@
 // filepath: src/objects/sphere.rs
 
+use crate::aabb::Aabb;
 use crate::consts::EPS;
 use crate::ray::{Ray, Segment};
 use crate::vec3::Vec3;
 use crate::material::Material;
 use crate::objects::hittable::Hittable;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub(crate) struct Sphere {
     radius: f32,
     position: Vec3,
@@ -19,6 +20,10 @@ impl Sphere {
     }
 
     fn material(&self) -> &Material { &self.material }
+
+    pub(crate) fn radius(&self) -> f32 { self.radius }
+    pub(crate) fn position(&self) -> Vec3 { self.position }
+    pub(crate) fn set_position(&mut self, position: Vec3) { self.position = position; }
 }
 
 impl Hittable for Sphere {
@@ -44,7 +49,7 @@ impl Hittable for Sphere {
 
     fn intersects_segment(&self, segment: &Segment) -> Option<(f32, Vec3, Vec3)> {
         let dir = (segment.end - segment.start).normalized();
-        let ray = Ray::new(segment.start, dir);
+        let ray = Ray::at_time(segment.start, dir, segment.time);
         if let Some((t, point, normal)) = self.intersects_ray(&ray) {
             let seg_len = (segment.end - segment.start).length();
             if t <= seg_len + EPS {
@@ -54,6 +59,36 @@ impl Hittable for Sphere {
         None
     }
 
+    fn intersections(&self, ray: &Ray) -> Vec<(f32, Vec3, Vec3)> {
+        let oc = ray.origin - self.position;
+        let a = ray.direction.dot(&ray.direction);
+        let half_b = oc.dot(&ray.direction);
+        let c = oc.dot(&oc) - self.radius * self.radius;
+        let discriminant = half_b * half_b - a * c;
+        if discriminant < -EPS { return Vec::new(); }
+        let sqrtd = discriminant.max(0.0).sqrt();
+        [(-half_b - sqrtd) / a, (-half_b + sqrtd) / a]
+            .into_iter()
+            .filter(|t| *t > EPS)
+            .map(|t| {
+                let point = ray.origin + ray.direction * t;
+                (t, point, (point - self.position).normalized())
+            })
+            .collect()
+    }
+
+    // Longitude / latitude mapping, v = 0 at the bottom pole
+    fn uv(&self, point: &Vec3) -> (f32, f32) {
+        let d = (*point - self.position).normalized();
+        let u = 0.5 + d.z().atan2(d.x()) / (2.0 * std::f32::consts::PI);
+        let v = 0.5 + d.y().clamp(-1.0, 1.0).asin() / std::f32::consts::PI;
+        (u, v)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(Aabb::new(self.position - self.radius, self.position + self.radius))
+    }
+
     fn material(&self) -> &Material { &self.material }
 }
 
@@ -0,0 +1,156 @@
+// filepath: src/objects/torus.rs
+#![allow(dead_code, reason = "the torus is exercised by its tests, the demo has none")]
+use crate::aabb::Aabb;
+use crate::consts::EPS;
+use crate::ray::{Ray, Segment};
+use crate::roots::solve_quartic;
+use crate::vec3::Vec3;
+use crate::material::Material;
+use crate::objects::hittable::Hittable;
+use crate::objects::local_frame::{azimuth_u, nearest, world_hits, LocalFrame};
+
+// Torus centred on `position`, lying in the plane perpendicular to `axis`.
+// `major_radius` is the distance from the centre to the middle of the tube, `minor_radius` the tube radius.
+#[derive(Debug, Clone)]
+pub(crate) struct Torus {
+    frame: LocalFrame,
+    major_radius: f32,
+    minor_radius: f32,
+    material: Material,
+}
+
+impl Torus {
+    pub(crate) fn new(position: Vec3, axis: Vec3, major_radius: f32, minor_radius: f32, material: Material) -> Self {
+        Torus { frame: LocalFrame::new(position, axis), major_radius, minor_radius, material }
+    }
+
+    // Outward normal at a local point on the surface: away from the nearest point of the tube's centre circle
+    fn local_normal(&self, p: Vec3) -> Vec3 {
+        let ring = Vec3::new(p.x(), 0.0, p.z()).normalized() * self.major_radius;
+        (p - ring).normalized()
+    }
+
+    // Every crossing of the ray's line with the surface as (t, local normal)
+    pub(crate) fn local_hits(&self, o: Vec3, d: Vec3) -> Vec<(f32, Vec3)> {
+        // (|p|^2 + R^2 - r^2)^2 = 4 R^2 (x^2 + z^2) with p = o + t d, expanded in f64
+        let (ox, oy, oz) = (o.x() as f64, o.y() as f64, o.z() as f64);
+        let (dx, dy, dz) = (d.x() as f64, d.y() as f64, d.z() as f64);
+        let big_r2 = (self.major_radius as f64).powi(2);
+        let small_r2 = (self.minor_radius as f64).powi(2);
+
+        let dd = dx * dx + dy * dy + dz * dz;
+        let od = ox * dx + oy * dy + oz * dz;
+        let k = ox * ox + oy * oy + oz * oz + big_r2 - small_r2;
+        let four_r2 = 4.0 * big_r2;
+
+        let a = dd * dd;
+        let b = 4.0 * dd * od;
+        let c = 4.0 * od * od + 2.0 * dd * k - four_r2 * (dx * dx + dz * dz);
+        let dcoef = 4.0 * od * k - 2.0 * four_r2 * (ox * dx + oz * dz);
+        let e = k * k - four_r2 * (ox * ox + oz * oz);
+
+        solve_quartic(a, b, c, dcoef, e)
+            .into_iter()
+            .map(|t| {
+                let t = t as f32;
+                (t, self.local_normal(o + d * t))
+            })
+            .collect()
+    }
+}
+
+impl Hittable for Torus {
+    fn intersects_ray(&self, ray: &Ray) -> Option<(f32, Vec3, Vec3)> {
+        let o = self.frame.point_to_local(ray.origin);
+        let d = self.frame.dir_to_local(ray.direction);
+        let (t, n) = nearest(&self.local_hits(o, d), EPS)?;
+        let point = ray.origin + ray.direction * t;
+        Some((t, point, self.frame.dir_to_world(n)))
+    }
+
+    fn intersects_segment(&self, segment: &Segment) -> Option<(f32, Vec3, Vec3)> {
+        let dir = (segment.end - segment.start).normalized();
+        let ray = Ray::at_time(segment.start, dir, segment.time);
+        if let Some((t, point, normal)) = self.intersects_ray(&ray) {
+            let seg_len = (segment.end - segment.start).length();
+            if t <= seg_len + EPS {
+                return Some((t, point, normal));
+            }
+        }
+        None
+    }
+
+    fn intersections(&self, ray: &Ray) -> Vec<(f32, Vec3, Vec3)> {
+        let o = self.frame.point_to_local(ray.origin);
+        let d = self.frame.dir_to_local(ray.direction);
+        world_hits(&self.frame, ray, &self.local_hits(o, d), EPS)
+    }
+
+    // u goes around the main ring, v around the tube
+    fn uv(&self, point: &Vec3) -> (f32, f32) {
+        let p = self.frame.point_to_local(*point);
+        let radial = (p.x() * p.x() + p.z() * p.z()).sqrt() - self.major_radius;
+        (azimuth_u(p), 0.5 + p.y().atan2(radial) / (2.0 * std::f32::consts::PI))
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let outer = self.major_radius + self.minor_radius;
+        let r = self.minor_radius;
+        Some(self.frame.bounds(Vec3::new(-outer, -r, -outer), Vec3::new(outer, r, outer)))
+    }
+
+    fn material(&self) -> &Material { &self.material }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unit_torus() -> Torus {
+        Torus::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0), 2.0, 0.5, Material::default())
+    }
+
+    #[test]
+    fn hits_outer_wall() {
+        let ray = Ray::new(Vec3::new(-5.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        let (t, p, n) = unit_torus().intersects_ray(&ray).expect("hit");
+        assert!((t - 2.5).abs() < 1e-4);
+        assert!((p - Vec3::new(-2.5, 0.0, 0.0)).length() < 1e-4);
+        assert!((n - Vec3::new(-1.0, 0.0, 0.0)).length() < 1e-4);
+    }
+
+    #[test]
+    fn passes_through_the_hole() {
+        let ray = Ray::new(Vec3::new(0.0, 5.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        assert!(unit_torus().intersects_ray(&ray).is_none());
+    }
+
+    #[test]
+    fn grazing_ray_touches_top_of_tube() {
+        // Tangent to the top of the tube on both sides of the hole: double roots at x = -2 and x = 2
+        let ray = Ray::new(Vec3::new(-5.0, 0.5, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        let (t, _p, n) = unit_torus().intersects_ray(&ray).expect("grazing hit");
+        assert!((t - 3.0).abs() < 1e-2);
+        assert!((n - Vec3::new(0.0, 1.0, 0.0)).length() < 1e-2);
+    }
+
+    #[test]
+    fn ray_from_inside_tube_exits() {
+        let ray = Ray::new(Vec3::new(2.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        let (t, _p, n) = unit_torus().intersects_ray(&ray).expect("exit hit");
+        assert!((t - 0.5).abs() < 1e-4);
+        assert!((n - Vec3::new(1.0, 0.0, 0.0)).length() < 1e-4);
+    }
+
+    #[test]
+    fn tilted_torus_hit_lies_on_surface() {
+        let torus = Torus::new(Vec3::new(1.0, 2.0, 10.0), Vec3::new(1.0, 1.0, 0.0), 2.0, 0.5, Material::default());
+        let ray = Ray::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.1, 0.2, 1.0).normalized());
+        let (_t, p, n) = torus.intersects_ray(&ray).expect("hit");
+        let local = torus.frame.point_to_local(p);
+        let ring = (local.x() * local.x() + local.z() * local.z()).sqrt() - 2.0;
+        let tube_dist = (ring * ring + local.y() * local.y()).sqrt();
+        assert!((tube_dist - 0.5).abs() < 1e-3);
+        assert!((n.length() - 1.0).abs() < 1e-4);
+    }
+}
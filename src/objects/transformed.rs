@@ -0,0 +1,169 @@
+// filepath: src/objects/transformed.rs
+#![allow(dead_code, reason = "instancing is for scenes with repeated geometry, which the demo lacks")]
+use std::sync::Arc;
+use crate::aabb::Aabb;
+use crate::consts::EPS;
+use crate::mat4::Mat4;
+use crate::ray::{Ray, Segment};
+use crate::vec3::Vec3;
+use crate::material::Material;
+use crate::objects::hittable::Hittable;
+
+// Places a Hittable in the world with an arbitrary affine transform (translate, rotate, scale).
+// Rays are taken into object space and normals brought back with the inverse transpose.
+#[derive(Debug, Clone)]
+pub(crate) struct Transformed<T: Hittable> {
+    inner: T,
+    object_to_world: Mat4,
+    world_to_object: Mat4,
+}
+
+impl<T: Hittable> Transformed<T> {
+    pub(crate) fn new(inner: T, object_to_world: Mat4) -> Self {
+        let world_to_object = object_to_world.inverse().expect("object transform must be invertible");
+        Transformed { inner, object_to_world, world_to_object }
+    }
+
+    pub(crate) fn inner(&self) -> &T { &self.inner }
+    pub(crate) fn transform(&self) -> &Mat4 { &self.object_to_world }
+
+    pub(crate) fn set_transform(&mut self, object_to_world: Mat4) {
+        self.world_to_object = object_to_world.inverse().expect("object transform must be invertible");
+        self.object_to_world = object_to_world;
+    }
+
+    // The object space direction is deliberately left unnormalized so `t` means the same in both spaces
+    fn ray_to_object(&self, ray: &Ray) -> Ray {
+        Ray::at_time(
+            self.world_to_object.transform_point(ray.origin),
+            self.world_to_object.transform_vector(ray.direction),
+            ray.time,
+        )
+    }
+
+    fn normal_to_world(&self, n: Vec3) -> Vec3 {
+        self.world_to_object.transpose().transform_vector(n).normalized()
+    }
+}
+
+impl<T: Hittable> Hittable for Transformed<T> {
+    fn intersects_ray(&self, ray: &Ray) -> Option<(f32, Vec3, Vec3)> {
+        let (t, _p, n) = self.inner.intersects_ray(&self.ray_to_object(ray))?;
+        let point = ray.origin + ray.direction * t;
+        Some((t, point, self.normal_to_world(n)))
+    }
+
+    fn intersects_segment(&self, segment: &Segment) -> Option<(f32, Vec3, Vec3)> {
+        let dir = (segment.end - segment.start).normalized();
+        let ray = Ray::at_time(segment.start, dir, segment.time);
+        if let Some((t, point, normal)) = self.intersects_ray(&ray) {
+            let seg_len = (segment.end - segment.start).length();
+            if t <= seg_len + EPS {
+                return Some((t, point, normal));
+            }
+        }
+        None
+    }
+
+    fn intersections(&self, ray: &Ray) -> Vec<(f32, Vec3, Vec3)> {
+        self.inner.intersections(&self.ray_to_object(ray))
+            .into_iter()
+            .map(|(t, _p, n)| (t, ray.origin + ray.direction * t, self.normal_to_world(n)))
+            .collect()
+    }
+
+    fn uv(&self, point: &Vec3) -> (f32, f32) {
+        self.inner.uv(&self.world_to_object.transform_point(*point))
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.inner.bounding_box().map(|b| b.transformed(&self.object_to_world))
+    }
+
+    fn material(&self) -> &Material { self.inner.material() }
+
+    fn material_at(&self, point: &Vec3) -> &Material {
+        self.inner.material_at(&self.world_to_object.transform_point(*point))
+    }
+}
+
+// Shared geometry: many instances can point at the same (possibly large) object
+impl<T: Hittable + ?Sized> Hittable for Arc<T> {
+    fn intersects_ray(&self, ray: &Ray) -> Option<(f32, Vec3, Vec3)> { (**self).intersects_ray(ray) }
+    fn intersects_segment(&self, segment: &Segment) -> Option<(f32, Vec3, Vec3)> { (**self).intersects_segment(segment) }
+    fn intersections(&self, ray: &Ray) -> Vec<(f32, Vec3, Vec3)> { (**self).intersections(ray) }
+    fn uv(&self, point: &Vec3) -> (f32, f32) { (**self).uv(point) }
+    fn bounding_box(&self) -> Option<Aabb> { (**self).bounding_box() }
+    fn material(&self) -> &Material { (**self).material() }
+    fn material_at(&self, point: &Vec3) -> &Material { (**self).material_at(point) }
+}
+
+// One placement of shared geometry. Cloning an Instance only copies the transforms.
+pub(crate) type Instance = Transformed<Arc<dyn Hittable>>;
+
+impl Instance {
+    pub(crate) fn instance_of(geometry: &Arc<dyn Hittable>, object_to_world: Mat4) -> Self {
+        Transformed::new(Arc::clone(geometry), object_to_world)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::objects::sphere::Sphere;
+    use crate::objects::triangle_mesh::TriangleMesh;
+    use crate::quat::Quat;
+
+    #[test]
+    fn scaled_sphere_becomes_ellipsoid() {
+        let sphere = Sphere::new(1.0, Vec3::new(0.0, 0.0, 0.0), Material::default());
+        let m = Mat4::translation(Vec3::new(0.0, 0.0, 10.0)) * Mat4::scale(Vec3::new(1.0, 1.0, 3.0));
+        let ellipsoid = Transformed::new(sphere, m);
+
+        let ray = Ray::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 1.0));
+        let (t, p, n) = ellipsoid.intersects_ray(&ray).expect("hit");
+        assert!((t - 7.0).abs() < 1e-4);
+        assert!((p - Vec3::new(0.0, 0.0, 7.0)).length() < 1e-4);
+        assert!((n - Vec3::new(0.0, 0.0, -1.0)).length() < 1e-4);
+    }
+
+    #[test]
+    fn normals_use_inverse_transpose() {
+        // A 45 degree slope on a sphere squashed along y keeps a normal perpendicular to the surface
+        let sphere = Sphere::new(1.0, Vec3::new(0.0, 0.0, 0.0), Material::default());
+        let squashed = Transformed::new(sphere, Mat4::scale(Vec3::new(2.0, 1.0, 1.0)));
+        let ray = Ray::new(Vec3::new(2.0_f32.sqrt(), 5.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        let (_t, p, n) = squashed.intersects_ray(&ray).expect("hit");
+        // Ellipse x^2/4 + y^2 = 1 has gradient (x/2, 2y)
+        let expected = Vec3::new(p.x() / 2.0, 2.0 * p.y(), 0.0).normalized();
+        assert!((n - expected).length() < 1e-4);
+    }
+
+    #[test]
+    fn instances_share_geometry() {
+        let quad = TriangleMesh::new(
+            vec![
+                Vec3::new(-1.0, -1.0, 0.0),
+                Vec3::new(1.0, -1.0, 0.0),
+                Vec3::new(1.0, 1.0, 0.0),
+                Vec3::new(-1.0, 1.0, 0.0),
+            ],
+            vec![[0, 1, 2], [0, 2, 3]],
+            Material::default(),
+        );
+        let geometry: Arc<dyn Hittable> = Arc::new(quad);
+        let instances: Vec<Instance> = (0..3)
+            .map(|i| {
+                let rot = Quat::from_axis_angle(Vec3::new(0.0, 1.0, 0.0), 0.3 * i as f32);
+                Instance::instance_of(&geometry, Mat4::from_trs(Vec3::new(i as f32 * 5.0, 0.0, 10.0), rot, Vec3::new(1.0, 1.0, 1.0)))
+            })
+            .collect();
+        assert_eq!(Arc::strong_count(&geometry), 4);
+
+        for (i, instance) in instances.iter().enumerate() {
+            let ray = Ray::new(Vec3::new(i as f32 * 5.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 1.0));
+            let (t, _p, _n) = instance.intersects_ray(&ray).expect("each instance is hit");
+            assert!((t - 10.0).abs() < 1e-4);
+        }
+    }
+}
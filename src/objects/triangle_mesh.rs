@@ -0,0 +1,183 @@
+// filepath: src/objects/triangle_mesh.rs
+#![allow(dead_code, reason = "meshes come from scene code or cloth; the demo loads none")]
+use crate::aabb::Aabb;
+use crate::consts::EPS;
+use crate::ray::{Ray, Segment};
+use crate::vec3::Vec3;
+use crate::material::Material;
+use crate::objects::hittable::Hittable;
+
+// Indexed triangle mesh. Normals are interpolated from per-vertex normals when present,
+// otherwise each face is shaded flat.
+#[derive(Debug, Clone)]
+pub(crate) struct TriangleMesh {
+    positions: Vec<Vec3>,
+    triangles: Vec<[usize; 3]>,
+    normals: Option<Vec<Vec3>>,
+    uvs: Option<Vec<(f32, f32)>>,
+    bounds: Option<Aabb>,
+    material: Material,
+}
+
+impl TriangleMesh {
+    pub(crate) fn new(positions: Vec<Vec3>, triangles: Vec<[usize; 3]>, material: Material) -> Self {
+        let bounds = Aabb::from_points(&positions);
+        TriangleMesh { positions, triangles, normals: None, uvs: None, bounds, material }
+    }
+
+    pub(crate) fn set_vertex_normals(&mut self, normals: Vec<Vec3>) {
+        assert_eq!(normals.len(), self.positions.len(), "one normal per vertex");
+        self.normals = Some(normals.into_iter().map(|n| n.normalized()).collect());
+    }
+
+    pub(crate) fn set_uvs(&mut self, uvs: Vec<(f32, f32)>) {
+        assert_eq!(uvs.len(), self.positions.len(), "one uv per vertex");
+        self.uvs = Some(uvs);
+    }
+
+    // Area-weighted average of the adjacent face normals
+    pub(crate) fn compute_smooth_normals(&mut self) {
+        let mut normals = vec![Vec3::new(0.0, 0.0, 0.0); self.positions.len()];
+        for tri in &self.triangles {
+            let [a, b, c] = tri.map(|i| self.positions[i]);
+            let face = (b - a).cross(&(c - a));
+            for &i in tri {
+                normals[i] = normals[i] + face;
+            }
+        }
+        self.set_vertex_normals(normals);
+    }
+
+    // Moves the vertices of a deforming mesh, keeping its topology. Smooth normals are
+    // recomputed if the mesh had vertex normals.
+    pub(crate) fn set_positions(&mut self, positions: Vec<Vec3>) {
+        assert_eq!(positions.len(), self.positions.len(), "deforming can't change the vertex count");
+        self.bounds = Aabb::from_points(&positions);
+        self.positions = positions;
+        if self.normals.is_some() {
+            self.compute_smooth_normals();
+        }
+    }
+
+    pub(crate) fn positions(&self) -> &[Vec3] { &self.positions }
+    pub(crate) fn triangles(&self) -> &[[usize; 3]] { &self.triangles }
+    pub(crate) fn bounds(&self) -> Option<Aabb> { self.bounds }
+
+    // Moller-Trumbore, returns (t, barycentric u, barycentric v)
+    fn intersect_triangle(&self, ray: &Ray, tri: &[usize; 3]) -> Option<(f32, f32, f32)> {
+        let [a, b, c] = tri.map(|i| self.positions[i]);
+        let e1 = b - a;
+        let e2 = c - a;
+        let pvec = ray.direction.cross(&e2);
+        let det = e1.dot(&pvec);
+        if det.abs() < 1e-8 { return None; } // Ray is parallel to the triangle
+        let inv_det = 1.0 / det;
+        let tvec = ray.origin - a;
+        let u = tvec.dot(&pvec) * inv_det;
+        if !(0.0..=1.0).contains(&u) { return None; }
+        let qvec = tvec.cross(&e1);
+        let v = ray.direction.dot(&qvec) * inv_det;
+        if v < 0.0 || u + v > 1.0 { return None; }
+        let t = e2.dot(&qvec) * inv_det;
+        if t <= EPS { return None; }
+        Some((t, u, v))
+    }
+
+    fn shading_normal(&self, tri: &[usize; 3], u: f32, v: f32) -> Vec3 {
+        match &self.normals {
+            Some(normals) => {
+                let [na, nb, nc] = tri.map(|i| normals[i]);
+                (na * (1.0 - u - v) + nb * u + nc * v).normalized()
+            }
+            None => {
+                let [a, b, c] = tri.map(|i| self.positions[i]);
+                (b - a).cross(&(c - a)).normalized()
+            }
+        }
+    }
+
+    // Barycentric coordinates of a point lying on the mesh, with the triangle it lies on
+    fn locate(&self, point: &Vec3) -> Option<(&[usize; 3], f32, f32)> {
+        let mut best: Option<(&[usize; 3], f32, f32, f32)> = None;
+        for tri in &self.triangles {
+            let [a, b, c] = tri.map(|i| self.positions[i]);
+            let e1 = b - a;
+            let e2 = c - a;
+            let n = e1.cross(&e2);
+            let area2 = n.dot(&n);
+            if area2 == 0.0 { continue; }
+            let dist = (*point - a).dot(&n).abs() / area2.sqrt();
+            let w = *point - a;
+            let u = w.cross(&e2).dot(&n) / area2;
+            let v = e1.cross(&w).dot(&n) / area2;
+            let inside = u >= -EPS && v >= -EPS && u + v <= 1.0 + EPS;
+            if inside && best.is_none_or(|(_, _, _, d)| dist < d) {
+                best = Some((tri, u, v, dist));
+            }
+        }
+        best.map(|(tri, u, v, _)| (tri, u, v))
+    }
+}
+
+impl Hittable for TriangleMesh {
+    fn intersects_ray(&self, ray: &Ray) -> Option<(f32, Vec3, Vec3)> {
+        if let Some(bounds) = &self.bounds
+            && !bounds.hit(ray, f32::INFINITY) {
+            return None;
+        }
+        let mut closest: Option<(f32, &[usize; 3], f32, f32)> = None;
+        for tri in &self.triangles {
+            if let Some((t, u, v)) = self.intersect_triangle(ray, tri)
+                && closest.is_none_or(|(ct, _, _, _)| t < ct) {
+                closest = Some((t, tri, u, v));
+            }
+        }
+        let (t, tri, u, v) = closest?;
+        let point = ray.origin + ray.direction * t;
+        Some((t, point, self.shading_normal(tri, u, v)))
+    }
+
+    fn intersects_segment(&self, segment: &Segment) -> Option<(f32, Vec3, Vec3)> {
+        let dir = (segment.end - segment.start).normalized();
+        let ray = Ray::at_time(segment.start, dir, segment.time);
+        if let Some((t, point, normal)) = self.intersects_ray(&ray) {
+            let seg_len = (segment.end - segment.start).length();
+            if t <= seg_len + EPS {
+                return Some((t, point, normal));
+            }
+        }
+        None
+    }
+
+    fn intersections(&self, ray: &Ray) -> Vec<(f32, Vec3, Vec3)> {
+        if let Some(bounds) = &self.bounds
+            && !bounds.hit(ray, f32::INFINITY) {
+            return Vec::new();
+        }
+        let mut hits: Vec<(f32, Vec3, Vec3)> = self.triangles.iter()
+            .filter_map(|tri| {
+                let (t, u, v) = self.intersect_triangle(ray, tri)?;
+                Some((t, ray.origin + ray.direction * t, self.shading_normal(tri, u, v)))
+            })
+            .collect();
+        hits.sort_by(|a, b| a.0.total_cmp(&b.0));
+        hits
+    }
+
+    // Interpolated vertex uvs, or the barycentric coordinates when the mesh has none
+    fn uv(&self, point: &Vec3) -> (f32, f32) {
+        let Some((tri, u, v)) = self.locate(point) else { return (0.0, 0.0) };
+        match &self.uvs {
+            Some(uvs) => {
+                let [a, b, c] = tri.map(|i| uvs[i]);
+                let w = 1.0 - u - v;
+                (a.0 * w + b.0 * u + c.0 * v, a.1 * w + b.1 * u + c.1 * v)
+            }
+            None => (u, v),
+        }
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> { self.bounds }
+
+    fn material(&self) -> &Material { &self.material }
+}
@@ -0,0 +1,276 @@
+use std::f32::consts::PI;
+use crate::consts::EPS;
+use crate::environment::Rgb;
+use crate::light::{arriving_color, AmbientLight, VisibleLight};
+use crate::phong::attenuation;
+use crate::rng::Rng;
+use crate::vec3::Vec3;
+
+// Metallic-roughness microfacet material. Specular reflection uses the GGX (Trowbridge-Reitz)
+// normal distribution with Smith masking-shadowing and Schlick's Fresnel; whatever the specular
+// lobe doesn't reflect is left for a Lambertian diffuse lobe, so no light is created.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) struct Pbr {
+    base_color: Rgb,
+    metallic: f32,  // 0 dielectric, 1 metal
+    roughness: f32, // perceptual, squared to get the GGX alpha
+    specular: f32,  // dielectric reflectance at normal incidence, 0.5 is the common 4%
+    emission: Rgb,  // light given off, added on top of anything reflected
+}
+
+// Smooth mirrors make GGX a spike that floats can't hold
+const MIN_ROUGHNESS: f32 = 0.03;
+
+fn mix(a: Rgb, b: Rgb, t: f32) -> Rgb {
+    [0, 1, 2].map(|i| a[i] + (b[i] - a[i]) * t)
+}
+
+impl Pbr {
+    pub(crate) fn new(base_color: Rgb) -> Self {
+        let base_color = base_color.map(|c| c.clamp(0.0, 1.0));
+        Pbr { base_color, metallic: 0.0, roughness: 0.5, specular: 0.5, emission: [0.0; 3] }
+    }
+
+    pub(crate) fn base_color(&self) -> Rgb { self.base_color }
+    pub(crate) fn roughness(&self) -> f32 { self.roughness }
+    pub(crate) fn emission(&self) -> Rgb { self.emission }
+
+    #[allow(dead_code, reason = "the demo has no Pbr materials, so nothing is made metal yet")]
+    pub(crate) fn set_metallic(&mut self, metallic: f32) {
+        self.metallic = metallic.clamp(0.0, 1.0);
+    }
+
+    pub(crate) fn set_roughness(&mut self, roughness: f32) {
+        self.roughness = roughness.clamp(MIN_ROUGHNESS, 1.0);
+    }
+
+    pub(crate) fn set_specular(&mut self, specular: f32) {
+        self.specular = specular.clamp(0.0, 1.0);
+    }
+
+    #[allow(dead_code, reason = "nothing in the demo scene glows")]
+    pub(crate) fn set_emission(&mut self, emission: Rgb) {
+        self.emission = emission.map(|c| c.max(0.0));
+    }
+
+    fn alpha(&self) -> f32 {
+        let r = self.roughness.max(MIN_ROUGHNESS);
+        r * r
+    }
+
+    // Reflectance at normal incidence: a few percent for dielectrics, the base colour for metals
+    pub(crate) fn f0(&self) -> Rgb {
+        mix([0.08 * self.specular; 3], self.base_color, self.metallic)
+    }
+
+    // Colour of the diffuse lobe, metals have none
+    pub(crate) fn diffuse_color(&self) -> Rgb {
+        self.base_color.map(|c| c * (1.0 - self.metallic))
+    }
+
+    fn distribution(&self, n_dot_h: f32) -> f32 {
+        let a2 = self.alpha() * self.alpha();
+        let d = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+        a2 / (PI * d * d)
+    }
+
+    // Separable Smith masking-shadowing for GGX
+    fn masking(&self, n_dot_v: f32, n_dot_l: f32) -> f32 {
+        let a2 = self.alpha() * self.alpha();
+        let g1 = |c: f32| 2.0 * c / (c + (a2 + (1.0 - a2) * c * c).sqrt());
+        g1(n_dot_v) * g1(n_dot_l)
+    }
+
+    fn fresnel(&self, v_dot_h: f32) -> Rgb {
+        let f0 = self.f0();
+        let w = (1.0 - v_dot_h).clamp(0.0, 1.0).powi(5);
+        f0.map(|f| f + (1.0 - f) * w)
+    }
+
+    // BRDF times the cosine at the light, for unit vectors towards the viewer and the light
+    pub(crate) fn evaluate(&self, normal: Vec3, view: Vec3, light: Vec3) -> Rgb {
+        let n_dot_l = normal.dot(&light);
+        let n_dot_v = normal.dot(&view);
+        if n_dot_l <= 0.0 || n_dot_v <= 0.0 { return [0.0; 3]; }
+        let h = (view + light).normalized();
+        let fresnel = self.fresnel(view.dot(&h));
+        let specular = self.distribution(normal.dot(&h).max(0.0)) * self.masking(n_dot_v, n_dot_l) / (4.0 * n_dot_v * n_dot_l);
+        // Diffuse gets what the surface doesn't reflect towards the viewer, taking it per half
+        // vector instead lets smooth dielectrics at grazing angles return more than they receive
+        let transmitted = self.fresnel(n_dot_v).map(|f| 1.0 - f);
+        let diffuse = self.diffuse_color();
+        [0, 1, 2].map(|i| (transmitted[i] * diffuse[i] / PI + fresnel[i] * specular) * n_dot_l)
+    }
+
+    // Chance of sampling the specular lobe rather than the diffuse one
+    fn specular_probability(&self) -> f32 {
+        0.5 + 0.5 * self.metallic
+    }
+
+    // Probability density of sample() returning `light`
+    pub(crate) fn pdf(&self, normal: Vec3, view: Vec3, light: Vec3) -> f32 {
+        let n_dot_l = normal.dot(&light);
+        if n_dot_l <= 0.0 { return 0.0; }
+        let h = (view + light).normalized();
+        let n_dot_h = normal.dot(&h).max(0.0);
+        let specular = self.distribution(n_dot_h) * n_dot_h / (4.0 * view.dot(&h).abs().max(1e-6));
+        let diffuse = n_dot_l / PI;
+        let p = self.specular_probability();
+        p * specular + (1.0 - p) * diffuse
+    }
+
+    // Picks a direction to continue a path in, from one lobe or the other, and returns it with
+    // the BRDF * cos / pdf throughput. None if the pick went below the surface.
+    pub(crate) fn sample(&self, normal: Vec3, view: Vec3, rng: &mut Rng) -> Option<(Vec3, Rgb)> {
+        let (t, b) = normal.orthonormal_basis();
+        let light = if rng.next_f32() < self.specular_probability() {
+            // GGX distributed half vector, reflected about
+            let a2 = self.alpha() * self.alpha();
+            let u = rng.next_f32();
+            let phi = 2.0 * PI * rng.next_f32();
+            let cos_theta = ((1.0 - u) / (1.0 + (a2 - 1.0) * u)).sqrt();
+            let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+            let h = t * (sin_theta * phi.cos()) + b * (sin_theta * phi.sin()) + normal * cos_theta;
+            h * (2.0 * view.dot(&h)) - view
+        } else {
+            // Cosine weighted
+            let (x, y) = rng.in_unit_disc();
+            let z = (1.0 - x * x - y * y).max(0.0).sqrt();
+            t * x + b * y + normal * z
+        };
+        let light = light.normalized();
+        let pdf = self.pdf(normal, view, light);
+        if pdf <= 0.0 { return None; }
+        Some((light, self.evaluate(normal, view, light).map(|c| c / pdf)))
+    }
+
+    // Stand-in for a legacy Phong material where only PBR makes sense, such as the path tracer:
+    // its diffuse colour as a fully rough, non-reflective dielectric
+    pub(crate) fn from_diffuse(diffuse: Rgb) -> Self {
+        let mut pbr = Pbr::new(diffuse);
+        pbr.set_roughness(1.0);
+        pbr.set_specular(0.0);
+        pbr
+    }
+}
+
+// Light arriving straight from point lights. A light's intensity is taken as the radiance a
+// surface facing it would see, so a white rough dielectric comes out as bright as under Phong.
+pub(crate) fn direct_light(normal: Vec3, view_dir: Vec3, point: Vec3, lights: &[VisibleLight], pbr: &Pbr) -> Rgb {
+    let mut rgb = [0.0; 3];
+    for visible in lights {
+        let light = visible.0;
+        let to_light = *light.position() - point;
+        let dist = to_light.length().max(EPS);
+        let f = pbr.evaluate(normal, view_dir, to_light / dist);
+        let scale = PI * light.intensity() * attenuation(dist);
+        for (c, (f, l)) in rgb.iter_mut().zip(f.into_iter().zip(arriving_color(visible))) {
+            *c += f * l * scale;
+        }
+    }
+    rgb
+}
+
+// PBR counterpart of shade_multi_light: emission, ambient on the base colour and direct light
+pub(crate) fn shade_pbr(normal: Vec3, view_dir: Vec3, point: Vec3, lights: &[VisibleLight], pbr: &Pbr, ambient_light: &AmbientLight) -> Rgb {
+    let ambient = [ambient_light.color.r, ambient_light.color.g, ambient_light.color.b];
+    let direct = direct_light(normal.normalized(), view_dir.normalized(), point, lights, pbr);
+    [0, 1, 2].map(|i| pbr.emission[i] + ambient[i] * ambient_light.intensity * pbr.base_color[i] + direct[i])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Light reflected towards `view` from uniform white surroundings, estimated with sample()
+    fn albedo(pbr: &Pbr, normal: Vec3, view: Vec3, n: usize) -> Rgb {
+        let mut rng = Rng::new(11);
+        let mut total = [0.0; 3];
+        for _ in 0..n {
+            if let Some((_l, weight)) = pbr.sample(normal, view, &mut rng) {
+                for (t, w) in total.iter_mut().zip(weight) {
+                    *t += w / n as f32;
+                }
+            }
+        }
+        total
+    }
+
+    #[test]
+    fn never_reflects_more_than_it_receives() {
+        let normal = Vec3::new(0.0, 0.0, 1.0);
+        for metallic in [0.0, 1.0] {
+            for roughness in [0.05, 0.3, 0.7, 1.0] {
+                let mut pbr = Pbr::new([1.0, 1.0, 1.0]);
+                pbr.set_metallic(metallic);
+                pbr.set_roughness(roughness);
+                for view in [Vec3::new(0.0, 0.0, 1.0), Vec3::new(0.7, 0.0, 0.7).normalized(), Vec3::new(0.95, 0.0, 0.3).normalized()] {
+                    let a = albedo(&pbr, normal, view, 20000);
+                    assert!(a[0] <= 1.02, "metallic {} roughness {} view {:?}: {}", metallic, roughness, view, a[0]);
+                    // Single scattering GGX loses what bounces between microfacets, down to
+                    // 1 - ln 2 for a fully rough metal seen head on
+                    assert!(a[0] > 0.29, "metallic {} roughness {} view {:?}: {}", metallic, roughness, view, a[0]);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn sampling_matches_its_pdf() {
+        // Averaging evaluate / pdf over directions from sample() is the same as integrating
+        // evaluate over the hemisphere directly
+        let mut pbr = Pbr::new([0.9, 0.5, 0.2]);
+        pbr.set_roughness(0.4);
+        pbr.set_metallic(0.3);
+        let normal = Vec3::new(0.0, 0.0, 1.0);
+        let view = Vec3::new(0.5, 0.0, 0.8).normalized();
+        let sampled = albedo(&pbr, normal, view, 50000);
+        let steps = 400;
+        let mut integrated = [0.0; 3];
+        for i in 0..steps {
+            for j in 0..steps {
+                let cos_theta = (i as f32 + 0.5) / steps as f32;
+                let phi = 2.0 * PI * (j as f32 + 0.5) / steps as f32;
+                let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
+                let light = Vec3::new(sin_theta * phi.cos(), sin_theta * phi.sin(), cos_theta);
+                let f = pbr.evaluate(normal, view, light);
+                for (t, v) in integrated.iter_mut().zip(f) {
+                    *t += v * 2.0 * PI / (steps * steps) as f32;
+                }
+            }
+        }
+        for c in 0..3 {
+            assert!((sampled[c] - integrated[c]).abs() < 0.02, "{:?} vs {:?}", sampled, integrated);
+        }
+    }
+
+    #[test]
+    fn rough_dielectric_is_mostly_diffuse_and_metal_takes_its_colour() {
+        let normal = Vec3::new(0.0, 0.0, 1.0);
+        let view = normal;
+        let light = Vec3::new(0.0, 0.6, 0.8);
+        let mut plastic = Pbr::new([1.0, 0.0, 0.0]);
+        plastic.set_roughness(1.0);
+        let f = plastic.evaluate(normal, view, light);
+        // Red diffuse, with only a few percent of white specular in the other channels
+        assert!(f[0] > 0.2 && f[1] < 0.03 && f[1] > 0.0);
+
+        let mut gold = Pbr::new([1.0, 0.8, 0.3]);
+        gold.set_metallic(1.0);
+        assert_eq!(gold.diffuse_color(), [0.0, 0.0, 0.0]);
+        let mirror = Vec3::new(0.0, 0.0, 1.0);
+        let g = gold.evaluate(normal, view, mirror);
+        assert!(g[0] > g[1] && g[1] > g[2]);
+        // Below the surface nothing gets through
+        assert_eq!(gold.evaluate(normal, view, Vec3::new(0.0, 0.0, -1.0)), [0.0; 3]);
+    }
+
+    #[test]
+    fn base_colour_is_clamped_like_everything_else() {
+        let hot = Pbr::new([1.2, 0.5, -0.1]);
+        assert_eq!(hot.base_color(), [1.0, 0.5, 0.0]);
+        // The Phong fallback colours stay valid too
+        let material = crate::material::Material::from_pbr(hot);
+        assert_eq!(material.diffuse.r, 1.0);
+    }
+}
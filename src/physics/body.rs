@@ -0,0 +1,221 @@
+// filepath: src/physics/body.rs
+use crate::mat3::Mat3;
+use crate::objects::hittable::Hittable;
+use crate::objects::infinite_plane::InfinitePlane;
+use crate::objects::sphere::Sphere;
+use crate::quat::Quat;
+use crate::vec3::Vec3;
+
+// Collision / render geometry carried by a body. The body's position is written back into the
+// primitive after every step, so rendering the shape shows the simulated state.
+#[derive(Debug, Clone)]
+pub(crate) enum Shape {
+    Sphere(Sphere),
+    Plane(InfinitePlane),
+}
+
+impl Shape {
+    pub(crate) fn hittable(&self) -> &dyn Hittable {
+        match self {
+            Shape::Sphere(s) => s,
+            Shape::Plane(p) => p,
+        }
+    }
+
+    pub(crate) fn to_hittable(&self) -> Box<dyn Hittable> {
+        match self {
+            Shape::Sphere(s) => Box::new(s.clone()),
+            Shape::Plane(p) => Box::new(p.clone()),
+        }
+    }
+
+    fn position(&self) -> Vec3 {
+        match self {
+            Shape::Sphere(s) => s.position(),
+            Shape::Plane(p) => p.position(),
+        }
+    }
+
+    fn set_position(&mut self, position: Vec3) {
+        match self {
+            Shape::Sphere(s) => s.set_position(position),
+            Shape::Plane(p) => p.set_position(position),
+        }
+    }
+
+    // Inertia tensor about the centre of mass, in body space
+    fn inertia(&self, mass: f32) -> Mat3 {
+        match self {
+            Shape::Sphere(s) => {
+                let i = 0.4 * mass * s.radius() * s.radius(); // solid sphere: 2/5 m r^2
+                Mat3::diagonal(Vec3::new(i, i, i))
+            }
+            Shape::Plane(_) => Mat3::zero(), // planes are always static
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct RigidBody {
+    pub position: Vec3,
+    pub orientation: Quat,
+    pub velocity: Vec3,
+    pub angular_velocity: Vec3,
+    inverse_mass: f32,
+    inertia_local: Mat3,
+    inverse_inertia_local: Mat3,
+    force: Vec3,
+    torque: Vec3,
+    restitution: f32, // bounciness, 0 = no bounce, 1 = perfectly elastic
+    friction: f32,    // Coulomb friction coefficient
+    bullet: bool,     // swept against other bodies every step, see World::integrate_bullet
+    sleeping: bool,   // skipped by integration and contact generation until woken
+    rest_time: f32,   // how long the body has been nearly still, see island::update_sleep
+    shape: Shape,
+}
+
+impl RigidBody {
+    // Starts at the shape's position. A non-positive or infinite mass makes the body static.
+    pub(crate) fn new(shape: Shape, mass: f32) -> Self {
+        let dynamic = mass > 0.0 && mass.is_finite() && !matches!(shape, Shape::Plane(_));
+        let (inverse_mass, inertia_local, inverse_inertia_local) = if dynamic {
+            let inertia = shape.inertia(mass);
+            let inverse = inertia.inverse().expect("a dynamic body's inertia must be invertible");
+            (1.0 / mass, inertia, inverse)
+        } else {
+            (0.0, Mat3::zero(), Mat3::zero())
+        };
+        RigidBody {
+            position: shape.position(),
+            orientation: Quat::identity(),
+            velocity: Vec3::new(0.0, 0.0, 0.0),
+            angular_velocity: Vec3::new(0.0, 0.0, 0.0),
+            inverse_mass,
+            inertia_local,
+            inverse_inertia_local,
+            force: Vec3::new(0.0, 0.0, 0.0),
+            torque: Vec3::new(0.0, 0.0, 0.0),
+            restitution: 0.5,
+            friction: 0.5,
+            bullet: false,
+            sleeping: false,
+            rest_time: 0.0,
+            shape,
+        }
+    }
+
+    pub(crate) fn new_static(shape: Shape) -> Self {
+        RigidBody::new(shape, 0.0)
+    }
+
+    pub(crate) fn is_static(&self) -> bool { self.inverse_mass == 0.0 }
+    pub(crate) fn is_sleeping(&self) -> bool { self.sleeping }
+    // Moving or able to move this step
+    pub(crate) fn is_active(&self) -> bool { !self.is_static() && !self.sleeping }
+
+    pub(crate) fn wake(&mut self) {
+        if self.sleeping {
+            self.sleeping = false;
+            self.rest_time = 0.0;
+        }
+    }
+
+    // Stops the body dead until something wakes it
+    pub(crate) fn sleep(&mut self) {
+        if self.is_static() { return; }
+        self.sleeping = true;
+        self.velocity = Vec3::new(0.0, 0.0, 0.0);
+        self.angular_velocity = Vec3::new(0.0, 0.0, 0.0);
+    }
+
+    pub(crate) fn rest_time(&self) -> f32 { self.rest_time }
+
+    pub(crate) fn set_rest_time(&mut self, rest_time: f32) {
+        self.rest_time = rest_time;
+    }
+    pub(crate) fn inverse_mass(&self) -> f32 { self.inverse_mass }
+
+    pub(crate) fn restitution(&self) -> f32 { self.restitution }
+    pub(crate) fn friction(&self) -> f32 { self.friction }
+
+    pub(crate) fn set_restitution(&mut self, restitution: f32) {
+        self.restitution = restitution.clamp(0.0, 1.0);
+    }
+
+    #[allow(dead_code, reason = "the demo ball rolls with the default friction")]
+    pub(crate) fn set_friction(&mut self, friction: f32) {
+        self.friction = friction.max(0.0);
+    }
+
+    pub(crate) fn is_bullet(&self) -> bool { self.bullet }
+
+    // Continuous collision for small fast bodies that would otherwise tunnel through thin obstacles
+    #[allow(dead_code, reason = "the demo ball is big and slow enough for discrete steps")]
+    pub(crate) fn set_bullet(&mut self, bullet: bool) {
+        self.bullet = bullet;
+    }
+
+    pub(crate) fn shape(&self) -> &Shape { &self.shape }
+    pub(crate) fn force(&self) -> Vec3 { self.force }
+    pub(crate) fn torque(&self) -> Vec3 { self.torque }
+
+    // I^-1 in world space for a given orientation: R I^-1 R^T
+    pub(crate) fn inverse_inertia_world_at(&self, orientation: Quat) -> Mat3 {
+        let r = Mat3::rotation(orientation);
+        r * self.inverse_inertia_local * r.transpose()
+    }
+
+    pub(crate) fn inverse_inertia_world(&self) -> Mat3 {
+        self.inverse_inertia_world_at(self.orientation)
+    }
+
+    // I in world space, rotated like the inverse rather than inverted from it. Zero for static bodies.
+    pub(crate) fn inertia_world_at(&self, orientation: Quat) -> Mat3 {
+        let r = Mat3::rotation(orientation);
+        r * self.inertia_local * r.transpose()
+    }
+
+    // Forces and torques accumulate until the end of the next step. Applying any wakes the body.
+    #[allow(dead_code, reason = "for game code pushing bodies around; the demo only sets a starting velocity")]
+    pub(crate) fn apply_force(&mut self, force: Vec3) {
+        self.wake();
+        self.force = self.force + force;
+    }
+
+    #[allow(dead_code, reason = "off-centre pushes, e.g. wind on a sail; nothing in the demo needs one")]
+    pub(crate) fn apply_force_at(&mut self, force: Vec3, point: Vec3) {
+        self.wake();
+        self.force = self.force + force;
+        self.torque = self.torque + (point - self.position).cross(&force);
+    }
+
+    #[allow(dead_code, reason = "covered by the spin-up tests; the demo ball only spins from friction")]
+    pub(crate) fn apply_torque(&mut self, torque: Vec3) {
+        self.wake();
+        self.torque = self.torque + torque;
+    }
+
+    // Instantaneous change of momentum applied at a world space point
+    #[allow(dead_code, reason = "for one-off kicks such as hits or explosions, which the demo never deals out")]
+    pub(crate) fn apply_impulse(&mut self, impulse: Vec3, point: Vec3) {
+        if self.is_static() { return; }
+        self.wake();
+        self.velocity = self.velocity + impulse * self.inverse_mass;
+        self.angular_velocity = self.angular_velocity + self.inverse_inertia_world() * (point - self.position).cross(&impulse);
+    }
+
+    // Velocity of the material point of the body currently at `point`
+    pub(crate) fn velocity_at(&self, point: Vec3) -> Vec3 {
+        self.velocity + self.angular_velocity.cross(&(point - self.position))
+    }
+
+    pub(crate) fn clear_forces(&mut self) {
+        self.force = Vec3::new(0.0, 0.0, 0.0);
+        self.torque = Vec3::new(0.0, 0.0, 0.0);
+    }
+
+    // Pushes the simulated position into the render primitive
+    pub(crate) fn sync_shape(&mut self) {
+        self.shape.set_position(self.position);
+    }
+}
@@ -0,0 +1,234 @@
+// filepath: src/physics/broad_phase.rs
+use std::collections::HashMap;
+use crate::aabb::Aabb;
+
+// Cheap culling of body pairs before the exact narrow phase. Boxes come from
+// Hittable::bounding_box, the same bounds the renderer culls rays with.
+// `None` marks an unbounded shape (an infinite plane) which is paired with everything.
+// Implementations return each candidate pair once as (lower index, higher index), sorted,
+// so the solver sees contacts in a deterministic order.
+pub(crate) trait BroadPhase {
+    fn pairs(&mut self, boxes: &[Option<Aabb>]) -> Vec<(usize, usize)>;
+}
+
+// Tests everything against everything, the O(n^2) reference
+#[derive(Debug, Default)]
+#[allow(dead_code, reason = "kept as the reference the faster broad phases are tested against")]
+pub(crate) struct BruteForce;
+
+impl BroadPhase for BruteForce {
+    fn pairs(&mut self, boxes: &[Option<Aabb>]) -> Vec<(usize, usize)> {
+        let mut pairs = Vec::new();
+        for a in 0..boxes.len() {
+            for b in (a + 1)..boxes.len() {
+                if overlap(&boxes[a], &boxes[b]) {
+                    pairs.push((a, b));
+                }
+            }
+        }
+        pairs
+    }
+}
+
+// Sorts boxes along X and only tests boxes whose X intervals overlap. The order is kept between
+// calls, so with coherent motion the insertion sort is close to linear.
+#[derive(Debug, Default)]
+pub(crate) struct SweepAndPrune {
+    order: Vec<usize>,
+}
+
+impl BroadPhase for SweepAndPrune {
+    fn pairs(&mut self, boxes: &[Option<Aabb>]) -> Vec<(usize, usize)> {
+        let bounded: Vec<usize> = (0..boxes.len()).filter(|&i| boxes[i].is_some()).collect();
+        if self.order.len() != bounded.len() || self.order.iter().any(|&i| i >= boxes.len() || boxes[i].is_none()) {
+            self.order = bounded;
+        }
+        let min_x = |i: usize| boxes[i].map_or(f32::NEG_INFINITY, |b| b.min.x());
+        insertion_sort_by_key(&mut self.order, min_x);
+
+        let mut pairs = Vec::new();
+        let mut active: Vec<usize> = Vec::new();
+        for &i in &self.order {
+            let bi = boxes[i].expect("bounded");
+            active.retain(|&j| boxes[j].expect("bounded").max.x() >= bi.min.x());
+            for &j in &active {
+                if boxes[j].expect("bounded").overlaps(&bi) {
+                    pairs.push((i.min(j), i.max(j)));
+                }
+            }
+            active.push(i);
+        }
+        add_unbounded_pairs(boxes, &mut pairs);
+        pairs.sort_unstable();
+        pairs
+    }
+}
+
+// Uniform grid hashed by cell coordinates. Works best when the cell size is about the size of
+// a typical body.
+#[derive(Debug)]
+pub(crate) struct SpatialHash {
+    cell_size: f32,
+    cells: HashMap<(i32, i32, i32), Vec<usize>>,
+}
+
+impl SpatialHash {
+    #[allow(dead_code, reason = "sweep and prune suits the demo; the hash is for crowds of similar sized bodies")]
+    pub(crate) fn new(cell_size: f32) -> Self {
+        assert!(cell_size > 0.0, "cell size must be positive");
+        SpatialHash { cell_size, cells: HashMap::new() }
+    }
+
+    fn cell_range(&self, b: &Aabb) -> ((i32, i32, i32), (i32, i32, i32)) {
+        let cell = |v: f32| (v / self.cell_size).floor() as i32;
+        (
+            (cell(b.min.x()), cell(b.min.y()), cell(b.min.z())),
+            (cell(b.max.x()), cell(b.max.y()), cell(b.max.z())),
+        )
+    }
+}
+
+impl BroadPhase for SpatialHash {
+    fn pairs(&mut self, boxes: &[Option<Aabb>]) -> Vec<(usize, usize)> {
+        // Reuse the allocated buckets between steps
+        for bucket in self.cells.values_mut() {
+            bucket.clear();
+        }
+        for (i, b) in boxes.iter().enumerate() {
+            let Some(b) = b else { continue };
+            let (lo, hi) = self.cell_range(b);
+            for x in lo.0..=hi.0 {
+                for y in lo.1..=hi.1 {
+                    for z in lo.2..=hi.2 {
+                        self.cells.entry((x, y, z)).or_default().push(i);
+                    }
+                }
+            }
+        }
+
+        let mut pairs = Vec::new();
+        for bucket in self.cells.values() {
+            for (k, &a) in bucket.iter().enumerate() {
+                for &b in &bucket[k + 1..] {
+                    if overlap(&boxes[a], &boxes[b]) {
+                        pairs.push((a.min(b), a.max(b)));
+                    }
+                }
+            }
+        }
+        add_unbounded_pairs(boxes, &mut pairs);
+        // Bodies sharing several cells are found more than once
+        pairs.sort_unstable();
+        pairs.dedup();
+        pairs
+    }
+}
+
+fn overlap(a: &Option<Aabb>, b: &Option<Aabb>) -> bool {
+    match (a, b) {
+        (Some(a), Some(b)) => a.overlaps(b),
+        _ => true,
+    }
+}
+
+fn add_unbounded_pairs(boxes: &[Option<Aabb>], pairs: &mut Vec<(usize, usize)>) {
+    for (u, b) in boxes.iter().enumerate() {
+        if b.is_some() { continue; }
+        for (other, other_box) in boxes.iter().enumerate() {
+            // Two unbounded boxes are added once, from the lower index
+            if other == u || (other_box.is_none() && other < u) { continue; }
+            pairs.push((u.min(other), u.max(other)));
+        }
+    }
+}
+
+fn insertion_sort_by_key(items: &mut [usize], key: impl Fn(usize) -> f32) {
+    for i in 1..items.len() {
+        let mut j = i;
+        while j > 0 && key(items[j - 1]) > key(items[j]) {
+            items.swap(j - 1, j);
+            j -= 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+    use crate::material::Material;
+    use crate::objects::infinite_plane::InfinitePlane;
+    use crate::objects::sphere::Sphere;
+    use crate::physics::body::{RigidBody, Shape};
+    use crate::physics::world::World;
+    use crate::vec3::Vec3;
+
+    // Deterministic scatter of boxes, with a couple of unbounded entries mixed in
+    fn scattered_boxes(count: usize) -> Vec<Option<Aabb>> {
+        let mut seed: u32 = 12345;
+        let mut next = move || {
+            seed = seed.wrapping_mul(1664525).wrapping_add(1013904223);
+            (seed >> 8) as f32 / (1u32 << 24) as f32
+        };
+        (0..count)
+            .map(|i| {
+                if i % 97 == 3 { return None; }
+                let c = Vec3::new(next() * 20.0, next() * 20.0, next() * 20.0);
+                let half = 0.2 + next() * 0.8;
+                Some(Aabb::new(c - half, c + half))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn sweep_and_prune_and_spatial_hash_match_brute_force() {
+        let boxes = scattered_boxes(400);
+        let expected = BruteForce.pairs(&boxes);
+        assert!(!expected.is_empty());
+        let mut sap = SweepAndPrune::default();
+        assert_eq!(sap.pairs(&boxes), expected);
+        // A second pass reuses the previous sort order
+        assert_eq!(sap.pairs(&boxes), expected);
+        assert_eq!(SpatialHash::new(1.0).pairs(&boxes), expected);
+        assert_eq!(SpatialHash::new(7.5).pairs(&boxes), expected);
+    }
+
+    // Thousands of spheres raining onto the ground and piling up. Sleeping is off so the pile keeps
+    // every contact alive to the end. Run with
+    //   cargo test --release -- --ignored thousands_of_bodies
+    #[test]
+    #[ignore]
+    fn thousands_of_bodies_step_in_real_time() {
+        let mut world = World::new(Vec3::new(0.0, -9.81, 0.0));
+        world.set_timestep(1.0 / 60.0);
+        world.set_sleeping(false);
+        world.add_body(RigidBody::new_static(Shape::Plane(InfinitePlane::new(
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            Material::default(),
+        ))));
+        let side = 16;
+        for x in 0..side {
+            for y in 0..10 {
+                for z in 0..side {
+                    let position = Vec3::new(x as f32 * 1.1, 0.6 + y as f32 * 1.1, z as f32 * 1.1);
+                    world.add_body(RigidBody::new(Shape::Sphere(Sphere::new(0.5, position, Material::default())), 1.0));
+                }
+            }
+        }
+        let bodies = world.bodies().len();
+
+        let steps = 120;
+        let start = Instant::now();
+        for _ in 0..steps {
+            world.step();
+        }
+        let per_step = start.elapsed().as_secs_f32() / steps as f32;
+        // Every column has landed, so each sphere rests on the ground or the one below
+        assert_eq!(bodies, 1 + side * side * 10);
+        assert!(world.contacts().len() >= bodies - 1, "only {} contacts", world.contacts().len());
+        if !cfg!(debug_assertions) {
+            assert!(per_step < 1.0 / 60.0, "too slow for real time: {} s per step", per_step);
+        }
+    }
+}
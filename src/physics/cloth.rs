@@ -0,0 +1,311 @@
+// filepath: src/physics/cloth.rs
+#![allow(dead_code, reason = "cloth runs alongside a scene when added; the demo has none")]
+use crate::material::Material;
+use crate::objects::triangle_mesh::TriangleMesh;
+use crate::physics::body::Shape;
+use crate::vec3::Vec3;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum SpringKind {
+    Structural, // grid neighbours, resist stretching
+    Shear,      // diagonals, resist skewing
+    Bend,       // two apart, resist folding
+}
+
+#[derive(Debug, Copy, Clone)]
+struct Spring {
+    a: usize,
+    b: usize,
+    rest_length: f32,
+    kind: SpringKind,
+}
+
+// Position based cloth on a rectangular grid of particles. Verlet integration followed by
+// iterative projection of the springs as distance constraints, which stays stable at any
+// stiffness unlike explicit mass-spring forces. The simulated state is mirrored into a
+// TriangleMesh that can be rendered directly.
+pub(crate) struct Cloth {
+    positions: Vec<Vec3>,
+    previous: Vec<Vec3>,
+    inverse_mass: Vec<f32>, // 0 for pinned particles
+    pinned_inverse_mass: Vec<f32>, // what each pinned particle had before, restored by unpin
+    springs: Vec<Spring>,
+    columns: usize,
+    rows: usize,
+    gravity: Vec3,
+    damping: f32,   // fraction of velocity lost per step
+    iterations: usize,
+    stiffness: [f32; 3], // per SpringKind, 1 = rigid
+    thickness: f32, // kept between the cloth and colliders
+    friction: f32,  // fraction of sliding velocity lost per step while touching a collider
+    mesh: TriangleMesh,
+}
+
+impl Cloth {
+    // `columns` x `rows` particles spanning the parallelogram from `corner` along `width` and
+    // `height`, sharing `mass` evenly
+    pub(crate) fn grid(corner: Vec3, width: Vec3, height: Vec3, columns: usize, rows: usize, mass: f32, material: Material) -> Self {
+        assert!(columns >= 2 && rows >= 2, "cloth needs at least 2 x 2 particles");
+        let count = columns * rows;
+        let mut positions = Vec::with_capacity(count);
+        let mut uvs = Vec::with_capacity(count);
+        for j in 0..rows {
+            for i in 0..columns {
+                let (u, v) = (i as f32 / (columns - 1) as f32, j as f32 / (rows - 1) as f32);
+                positions.push(corner + width * u + height * v);
+                uvs.push((u, v));
+            }
+        }
+
+        let index = |i: usize, j: usize| j * columns + i;
+        let mut links = Vec::new();
+        for j in 0..rows {
+            for i in 0..columns {
+                if i + 1 < columns { links.push((index(i, j), index(i + 1, j), SpringKind::Structural)); }
+                if j + 1 < rows { links.push((index(i, j), index(i, j + 1), SpringKind::Structural)); }
+                if i + 1 < columns && j + 1 < rows {
+                    links.push((index(i, j), index(i + 1, j + 1), SpringKind::Shear));
+                    links.push((index(i + 1, j), index(i, j + 1), SpringKind::Shear));
+                }
+                if i + 2 < columns { links.push((index(i, j), index(i + 2, j), SpringKind::Bend)); }
+                if j + 2 < rows { links.push((index(i, j), index(i, j + 2), SpringKind::Bend)); }
+            }
+        }
+        let springs = links.into_iter()
+            .map(|(a, b, kind)| Spring { a, b, rest_length: (positions[b] - positions[a]).length(), kind })
+            .collect();
+
+        let mut triangles = Vec::with_capacity(2 * (columns - 1) * (rows - 1));
+        for j in 0..rows - 1 {
+            for i in 0..columns - 1 {
+                triangles.push([index(i, j), index(i + 1, j), index(i + 1, j + 1)]);
+                triangles.push([index(i, j), index(i + 1, j + 1), index(i, j + 1)]);
+            }
+        }
+        let mut mesh = TriangleMesh::new(positions.clone(), triangles, material);
+        mesh.set_uvs(uvs);
+        mesh.compute_smooth_normals();
+
+        Cloth {
+            previous: positions.clone(),
+            positions,
+            inverse_mass: vec![count as f32 / mass; count],
+            pinned_inverse_mass: vec![0.0; count],
+            springs,
+            columns,
+            rows,
+            gravity: Vec3::new(0.0, -9.81, 0.0),
+            damping: 0.01,
+            iterations: 15,
+            stiffness: [1.0, 0.5, 0.1],
+            thickness: 0.02,
+            friction: 0.5,
+            mesh,
+        }
+    }
+
+    pub(crate) fn index(&self, column: usize, row: usize) -> usize {
+        assert!(column < self.columns && row < self.rows, "particle outside the grid");
+        row * self.columns + column
+    }
+
+    // Pinned particles stay where they are put and aren't moved by springs or collisions
+    pub(crate) fn pin(&mut self, index: usize) {
+        if self.is_pinned(index) { return; }
+        self.pinned_inverse_mass[index] = self.inverse_mass[index];
+        self.inverse_mass[index] = 0.0;
+    }
+
+    // Lets a pinned particle go again with the mass it had before
+    pub(crate) fn unpin(&mut self, index: usize) {
+        if !self.is_pinned(index) { return; }
+        self.inverse_mass[index] = self.pinned_inverse_mass[index];
+    }
+
+    // Moves a particle without giving it velocity, for dragging pinned corners around
+    pub(crate) fn set_position(&mut self, index: usize, position: Vec3) {
+        self.positions[index] = position;
+        self.previous[index] = position;
+    }
+
+    pub(crate) fn set_gravity(&mut self, gravity: Vec3) {
+        self.gravity = gravity;
+    }
+
+    pub(crate) fn set_damping(&mut self, damping: f32) {
+        self.damping = damping.clamp(0.0, 1.0);
+    }
+
+    pub(crate) fn set_iterations(&mut self, iterations: usize) {
+        self.iterations = iterations.max(1);
+    }
+
+    pub(crate) fn set_stiffness(&mut self, kind: SpringKind, stiffness: f32) {
+        self.stiffness[kind as usize] = stiffness.clamp(0.0, 1.0);
+    }
+
+    pub(crate) fn set_thickness(&mut self, thickness: f32) {
+        self.thickness = thickness.max(0.0);
+    }
+
+    pub(crate) fn set_friction(&mut self, friction: f32) {
+        self.friction = friction.clamp(0.0, 1.0);
+    }
+
+    pub(crate) fn positions(&self) -> &[Vec3] { &self.positions }
+    pub(crate) fn is_pinned(&self, index: usize) -> bool { self.inverse_mass[index] == 0.0 }
+
+    // Current shape, ready to clone into the scene for Camera::render_scene
+    pub(crate) fn mesh(&self) -> &TriangleMesh { &self.mesh }
+
+    // Largest relative stretch of any structural spring
+    pub(crate) fn max_stretch(&self) -> f32 {
+        self.springs.iter()
+            .filter(|s| s.kind == SpringKind::Structural)
+            .map(|s| ((self.positions[s.b] - self.positions[s.a]).length() - s.rest_length) / s.rest_length)
+            .fold(0.0, f32::max)
+    }
+
+    pub(crate) fn step(&mut self, dt: f32, colliders: &[Shape]) {
+        // Verlet: the previous position carries the velocity
+        for i in 0..self.positions.len() {
+            if self.inverse_mass[i] == 0.0 { continue; }
+            let current = self.positions[i];
+            let velocity = (current - self.previous[i]) * (1.0 - self.damping);
+            self.previous[i] = current;
+            self.positions[i] = current + velocity + self.gravity * (dt * dt);
+        }
+
+        // Scale stiffness so the overall response doesn't depend on the iteration count
+        let per_iteration = self.stiffness.map(|k| 1.0 - (1.0 - k).powf(1.0 / self.iterations as f32));
+        let mut contact_normals: Vec<Option<Vec3>> = vec![None; self.positions.len()];
+        for _ in 0..self.iterations {
+            for spring in &self.springs {
+                let (wa, wb) = (self.inverse_mass[spring.a], self.inverse_mass[spring.b]);
+                if wa + wb == 0.0 { continue; }
+                let delta = self.positions[spring.b] - self.positions[spring.a];
+                let length = delta.length();
+                if length < 1e-9 { continue; }
+                let correction = delta * ((length - spring.rest_length) / (length * (wa + wb)) * per_iteration[spring.kind as usize]);
+                self.positions[spring.a] = self.positions[spring.a] + correction * wa;
+                self.positions[spring.b] = self.positions[spring.b] - correction * wb;
+            }
+            let particles = self.positions.iter_mut().zip(&self.inverse_mass).zip(&mut contact_normals);
+            for ((position, &inverse_mass), contact) in particles {
+                if inverse_mass == 0.0 { continue; }
+                for collider in colliders {
+                    if let Some((pushed, normal)) = push_out(*position, collider, self.thickness) {
+                        *position = pushed;
+                        *contact = Some(normal);
+                    }
+                }
+            }
+        }
+
+        // Friction once per step, dropping the velocity into the surface and part of the sliding
+        for (i, normal) in contact_normals.into_iter().enumerate() {
+            let Some(n) = normal else { continue };
+            let velocity = self.positions[i] - self.previous[i];
+            let sliding = velocity - n * velocity.dot(&n);
+            self.previous[i] = self.positions[i] - sliding * (1.0 - self.friction);
+        }
+
+        self.mesh.set_positions(self.positions.clone());
+    }
+}
+
+// Nearest point at least `thickness` outside the shape and the surface normal there,
+// None if the point is already clear
+fn push_out(p: Vec3, shape: &Shape, thickness: f32) -> Option<(Vec3, Vec3)> {
+    match shape {
+        Shape::Sphere(s) => {
+            let offset = p - s.position();
+            let distance = offset.length();
+            let min = s.radius() + thickness;
+            if distance >= min || distance < 1e-9 { return None; }
+            let normal = offset / distance;
+            Some((s.position() + normal * min, normal))
+        }
+        Shape::Plane(plane) => {
+            let distance = plane.signed_distance(p);
+            if distance >= thickness { return None; }
+            Some((p + plane.normal() * (thickness - distance), plane.normal()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::objects::hittable::Hittable;
+    use crate::objects::infinite_plane::InfinitePlane;
+    use crate::objects::sphere::Sphere;
+    use crate::ray::Ray;
+
+    // 2 x 2 horizontal sheet centred over the origin at height y
+    fn sheet(y: f32, n: usize) -> Cloth {
+        Cloth::grid(Vec3::new(-1.0, y, -1.0), Vec3::new(2.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 2.0), n, n, 1.0, Material::default())
+    }
+
+    #[test]
+    fn hangs_from_pinned_corners() {
+        let mut cloth = sheet(0.0, 11);
+        let (left, right) = (cloth.index(0, 0), cloth.index(10, 0));
+        cloth.pin(left);
+        cloth.pin(right);
+        let pinned = (cloth.positions()[left], cloth.positions()[right]);
+        for _ in 0..240 {
+            cloth.step(1.0 / 120.0, &[]);
+        }
+        assert_eq!((cloth.positions()[left], cloth.positions()[right]), pinned);
+        // The free edge swung down below the pins
+        let free_edge = cloth.positions()[cloth.index(5, 10)];
+        assert!(free_edge.y() < -1.5, "{:?}", free_edge);
+        assert!(cloth.max_stretch() < 0.1, "stretched {}", cloth.max_stretch());
+
+        // Let go of one corner, pinning twice doesn't lose its mass
+        cloth.pin(left);
+        cloth.unpin(left);
+        assert!(!cloth.is_pinned(left));
+        assert_eq!(cloth.inverse_mass[left], cloth.inverse_mass[cloth.index(5, 10)]);
+        for _ in 0..60 {
+            cloth.step(1.0 / 120.0, &[]);
+        }
+        assert!(cloth.positions()[left].y() < pinned.0.y() - 0.1);
+    }
+
+    #[test]
+    fn drapes_over_a_sphere_and_rests_on_the_ground() {
+        let mut cloth = sheet(1.5, 15);
+        let ball = Sphere::new(0.6, Vec3::new(0.0, 0.6, 0.0), Material::default());
+        let ground = InfinitePlane::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0), Material::default());
+        let colliders = [Shape::Sphere(ball.clone()), Shape::Plane(ground)];
+        for _ in 0..360 {
+            cloth.step(1.0 / 120.0, &colliders);
+        }
+        for p in cloth.positions() {
+            assert!((*p - ball.position()).length() > ball.radius() - 1e-3, "inside the ball at {:?}", p);
+            assert!(p.y() > -1e-3, "below the ground at {:?}", p);
+        }
+        // The middle sits on top of the ball, the corners hang lower
+        let top = cloth.positions()[cloth.index(7, 7)];
+        assert!((top.y() - (1.2 + 0.02)).abs() < 0.02, "{:?}", top);
+        assert!(cloth.positions()[cloth.index(0, 0)].y() < 0.8);
+    }
+
+    #[test]
+    fn mesh_follows_the_simulation() {
+        let mut cloth = sheet(0.0, 5);
+        assert_eq!(cloth.mesh().triangles().len(), 2 * 4 * 4);
+        for _ in 0..60 {
+            cloth.step(1.0 / 60.0, &[]);
+        }
+        // Free fall for a second: the sheet is about 4.9 lower and still flat
+        let ray = Ray::new(Vec3::new(0.3, 10.0, 0.2), Vec3::new(0.0, -1.0, 0.0));
+        let (t, _p, n) = cloth.mesh().intersects_ray(&ray).expect("hit");
+        let expected = 10.0 - cloth.positions()[0].y();
+        assert!((t - expected).abs() < 1e-3, "t {} expected {}", t, expected);
+        assert!(n.y().abs() > 0.99);
+        assert!(cloth.positions()[0].y() < -4.0);
+    }
+}
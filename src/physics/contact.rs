@@ -0,0 +1,126 @@
+// filepath: src/physics/contact.rs
+use crate::objects::infinite_plane::InfinitePlane;
+use crate::objects::sphere::Sphere;
+use crate::physics::body::{RigidBody, Shape};
+use crate::vec3::Vec3;
+
+// Allowed overlap before positional correction kicks in, avoids jitter on resting contacts
+const PENETRATION_SLOP: f32 = 0.005;
+// Fraction of the remaining overlap removed per step
+const CORRECTION_PERCENT: f32 = 0.8;
+
+// Where two shapes touch. `normal` points from the first shape towards the second.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) struct ContactGeometry {
+    pub point: Vec3,
+    pub normal: Vec3,
+    pub penetration: f32,
+}
+
+impl ContactGeometry {
+    fn flipped(self) -> Self {
+        ContactGeometry { normal: -self.normal, ..self }
+    }
+}
+
+// A touching pair of bodies, by index into the world's body list
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) struct Contact {
+    pub a: usize,
+    pub b: usize,
+    pub geometry: ContactGeometry,
+}
+
+pub(crate) fn sphere_sphere(a: &Sphere, b: &Sphere) -> Option<ContactGeometry> {
+    let delta = b.position() - a.position();
+    let dist = delta.length();
+    let radii = a.radius() + b.radius();
+    if dist >= radii { return None; }
+    // Concentric spheres have no preferred direction, push apart along +Y
+    let normal = if dist > 0.0 { delta / dist } else { Vec3::new(0.0, 1.0, 0.0) };
+    let penetration = radii - dist;
+    Some(ContactGeometry {
+        point: a.position() + normal * (a.radius() - 0.5 * penetration),
+        normal,
+        penetration,
+    })
+}
+
+pub(crate) fn sphere_plane(sphere: &Sphere, plane: &InfinitePlane) -> Option<ContactGeometry> {
+    let dist = plane.signed_distance(sphere.position());
+    if dist >= sphere.radius() { return None; }
+    Some(ContactGeometry {
+        point: sphere.position() - plane.normal() * dist,
+        normal: -plane.normal(),
+        penetration: sphere.radius() - dist,
+    })
+}
+
+// Narrow phase dispatch on the shape pair
+pub(crate) fn collide(a: &Shape, b: &Shape) -> Option<ContactGeometry> {
+    match (a, b) {
+        (Shape::Sphere(sa), Shape::Sphere(sb)) => sphere_sphere(sa, sb),
+        (Shape::Sphere(s), Shape::Plane(p)) => sphere_plane(s, p),
+        (Shape::Plane(p), Shape::Sphere(s)) => sphere_plane(s, p).map(ContactGeometry::flipped),
+        (Shape::Plane(_), Shape::Plane(_)) => None,
+    }
+}
+
+// Pushes overlapping bodies apart in proportion to their inverse masses
+pub(crate) fn correct_position(a: &mut RigidBody, b: &mut RigidBody, contact: &ContactGeometry) {
+    let total = a.inverse_mass() + b.inverse_mass();
+    if total == 0.0 { return; }
+    let depth = (contact.penetration - PENETRATION_SLOP).max(0.0);
+    let correction = contact.normal * (depth * CORRECTION_PERCENT / total);
+    a.position = a.position - correction * a.inverse_mass();
+    b.position = b.position + correction * b.inverse_mass();
+}
+
+// Mutable access to two distinct bodies of a slice
+pub(crate) fn pair_mut(bodies: &mut [RigidBody], a: usize, b: usize) -> (&mut RigidBody, &mut RigidBody) {
+    assert_ne!(a, b, "a body can't collide with itself");
+    if a < b {
+        let (lo, hi) = bodies.split_at_mut(b);
+        (&mut lo[a], &mut hi[0])
+    } else {
+        let (lo, hi) = bodies.split_at_mut(a);
+        (&mut hi[0], &mut lo[b])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Material;
+
+    #[test]
+    fn overlapping_spheres_touch_midway() {
+        let a = Sphere::new(1.0, Vec3::new(0.0, 0.0, 0.0), Material::default());
+        let b = Sphere::new(1.0, Vec3::new(1.5, 0.0, 0.0), Material::default());
+        let c = sphere_sphere(&a, &b).expect("contact");
+        assert!((c.normal - Vec3::new(1.0, 0.0, 0.0)).length() < 1e-6);
+        assert!((c.penetration - 0.5).abs() < 1e-6);
+        assert!((c.point - Vec3::new(0.75, 0.0, 0.0)).length() < 1e-6);
+    }
+
+    #[test]
+    fn sphere_resting_in_plane() {
+        let s = Sphere::new(1.0, Vec3::new(3.0, 0.8, 0.0), Material::default());
+        let p = InfinitePlane::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0), Material::default());
+        let c = sphere_plane(&s, &p).expect("contact");
+        assert!((c.normal - Vec3::new(0.0, -1.0, 0.0)).length() < 1e-6);
+        assert!((c.penetration - 0.2).abs() < 1e-6);
+        assert!((c.point - Vec3::new(3.0, 0.0, 0.0)).length() < 1e-6);
+
+        // Plane first reverses the normal
+        let flipped = collide(&Shape::Plane(p), &Shape::Sphere(s)).expect("contact");
+        assert!((flipped.normal - Vec3::new(0.0, 1.0, 0.0)).length() < 1e-6);
+    }
+
+    #[test]
+    fn separated_shapes_have_no_contact() {
+        let a = Sphere::new(1.0, Vec3::new(0.0, 0.0, 0.0), Material::default());
+        let b = Sphere::new(1.0, Vec3::new(2.5, 0.0, 0.0), Material::default());
+        assert!(sphere_sphere(&a, &b).is_none());
+    }
+}
@@ -0,0 +1,436 @@
+// filepath: src/physics/fluid.rs
+#![allow(dead_code, reason = "the SPH fluid is driven by scene code; the demo has no fluid")]
+use std::collections::HashMap;
+use std::f32::consts::PI;
+use crate::aabb::Aabb;
+use crate::material::Material;
+use crate::objects::distance_field::DistanceField;
+use crate::objects::hittable::Hittable;
+use crate::objects::infinite_plane::InfinitePlane;
+use crate::objects::sphere::Sphere;
+use crate::sdf::smooth_min;
+use crate::vec3::Vec3;
+
+// Smoothing kernels from Müller et al. 2003, all zero beyond the smoothing radius h
+
+// Density kernel
+fn poly6(r2: f32, h: f32) -> f32 {
+    let h2 = h * h;
+    if r2 >= h2 { return 0.0; }
+    315.0 / (64.0 * PI * h.powi(9)) * (h2 - r2).powi(3)
+}
+
+// Gradient of the pressure kernel, doesn't vanish near r = 0 so close particles still repel
+fn spiky_gradient(r: Vec3, h: f32) -> Vec3 {
+    let length = r.length();
+    if length >= h || length < 1e-9 { return Vec3::new(0.0, 0.0, 0.0); }
+    r * (-45.0 / (PI * h.powi(6)) * (h - length).powi(2) / length)
+}
+
+// Laplacian of the viscosity kernel
+fn viscosity_laplacian(r: f32, h: f32) -> f32 {
+    if r >= h { return 0.0; }
+    45.0 / (PI * h.powi(6)) * (h - r)
+}
+
+// What keeps the fluid in place
+#[derive(Debug, Clone)]
+pub(crate) enum Boundary {
+    Plane(InfinitePlane), // fluid stays in front of the normal
+    Container(Aabb),      // fluid stays inside
+    Obstacle(Aabb),       // fluid stays outside
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum FluidRendering {
+    Spheres, // one sphere per particle
+    Surface, // blended implicit surface around the particles
+}
+
+// Uniform grid of particle indices with cells the size of the smoothing radius, so every
+// neighbour of a particle is in the 27 cells around it
+struct NeighbourGrid {
+    cell_size: f32,
+    cells: HashMap<(i32, i32, i32), Vec<usize>>,
+}
+
+impl NeighbourGrid {
+    fn new(positions: &[Vec3], cell_size: f32) -> Self {
+        let mut grid = NeighbourGrid { cell_size, cells: HashMap::new() };
+        for (i, p) in positions.iter().enumerate() {
+            grid.cells.entry(grid.cell(*p)).or_default().push(i);
+        }
+        grid
+    }
+
+    fn cell(&self, p: Vec3) -> (i32, i32, i32) {
+        let c = |v: f32| (v / self.cell_size).floor() as i32;
+        (c(p.x()), c(p.y()), c(p.z()))
+    }
+
+    // Candidates near p in a fixed order, so sums over neighbours are reproducible
+    fn near(&self, p: Vec3) -> impl Iterator<Item = usize> + '_ {
+        let (x, y, z) = self.cell(p);
+        (-1..=1).flat_map(move |dx| (-1..=1).flat_map(move |dy| (-1..=1).map(move |dz| (x + dx, y + dy, z + dz))))
+            .filter_map(|key| self.cells.get(&key))
+            .flatten()
+            .copied()
+    }
+}
+
+// Smoothed particle hydrodynamics fluid. Densities come from nearby particle masses, a stiff
+// equation of state turns density above rest into pressure, and pressure plus viscosity forces
+// move the particles with symplectic Euler in fixed substeps.
+pub(crate) struct Fluid {
+    positions: Vec<Vec3>,
+    velocities: Vec<Vec3>,
+    densities: Vec<f32>,
+    pressures: Vec<f32>,
+    particle_mass: f32,
+    particle_radius: f32, // for boundaries and rendering
+    smoothing_radius: f32,
+    rest_density: f32,
+    stiffness: f32,
+    viscosity: f32,
+    gravity: Vec3,
+    substep: f32,
+    boundary_restitution: f32,
+    boundaries: Vec<Boundary>,
+}
+
+impl Fluid {
+    // Block of particles on a lattice filling [min, max]. The particle mass is chosen so the
+    // lattice is exactly at rest density, and the smoothing radius is twice the spacing.
+    pub(crate) fn block(min: Vec3, max: Vec3, spacing: f32, rest_density: f32) -> Self {
+        let h = 2.0 * spacing;
+        let mut fluid = Fluid {
+            positions: Vec::new(),
+            velocities: Vec::new(),
+            densities: Vec::new(),
+            pressures: Vec::new(),
+            particle_mass: rest_density / lattice_weight(spacing, h),
+            particle_radius: 0.5 * spacing,
+            smoothing_radius: h,
+            rest_density,
+            stiffness: 200.0,
+            viscosity: 1.0,
+            gravity: Vec3::new(0.0, -9.81, 0.0),
+            substep: 1.0 / 600.0,
+            boundary_restitution: 0.3,
+            boundaries: Vec::new(),
+        };
+        let count = |lo: f32, hi: f32| ((hi - lo) / spacing).floor() as usize + 1;
+        let (nx, ny, nz) = (count(min.x(), max.x()), count(min.y(), max.y()), count(min.z(), max.z()));
+        for i in 0..nx {
+            for j in 0..ny {
+                for k in 0..nz {
+                    let offset = Vec3::new(i as f32, j as f32, k as f32) * spacing;
+                    fluid.add_particle(min + offset, Vec3::new(0.0, 0.0, 0.0));
+                }
+            }
+        }
+        fluid
+    }
+
+    pub(crate) fn add_particle(&mut self, position: Vec3, velocity: Vec3) {
+        self.positions.push(position);
+        self.velocities.push(velocity);
+        self.densities.push(self.rest_density);
+        self.pressures.push(0.0);
+    }
+
+    pub(crate) fn add_boundary(&mut self, boundary: Boundary) {
+        self.boundaries.push(boundary);
+    }
+
+    // Pressure per unit of density above rest. Stiffer is less compressible but needs smaller substeps.
+    pub(crate) fn set_stiffness(&mut self, stiffness: f32) {
+        self.stiffness = stiffness.max(0.0);
+    }
+
+    pub(crate) fn set_viscosity(&mut self, viscosity: f32) {
+        self.viscosity = viscosity.max(0.0);
+    }
+
+    pub(crate) fn set_gravity(&mut self, gravity: Vec3) {
+        self.gravity = gravity;
+    }
+
+    pub(crate) fn set_substep(&mut self, substep: f32) {
+        assert!(substep > 0.0, "substep must be positive");
+        self.substep = substep;
+    }
+
+    pub(crate) fn positions(&self) -> &[Vec3] { &self.positions }
+    pub(crate) fn velocities(&self) -> &[Vec3] { &self.velocities }
+    pub(crate) fn densities(&self) -> &[f32] { &self.densities }
+    pub(crate) fn particle_radius(&self) -> f32 { self.particle_radius }
+    pub(crate) fn rest_density(&self) -> f32 { self.rest_density }
+
+    // Advances by dt in as many fixed substeps as it takes
+    pub(crate) fn step(&mut self, dt: f32) {
+        let substeps = (dt / self.substep).ceil().max(1.0) as usize;
+        let h = dt / substeps as f32;
+        for _ in 0..substeps {
+            self.substep_by(h);
+        }
+    }
+
+    fn substep_by(&mut self, dt: f32) {
+        let h = self.smoothing_radius;
+        let grid = NeighbourGrid::new(&self.positions, h);
+        self.compute_densities(&grid);
+
+        let mut accelerations = Vec::with_capacity(self.positions.len());
+        for i in 0..self.positions.len() {
+            let (pi, vi) = (self.positions[i], self.velocities[i]);
+            let mut pressure_force = Vec3::new(0.0, 0.0, 0.0);
+            let mut viscosity_force = Vec3::new(0.0, 0.0, 0.0);
+            for j in grid.near(pi) {
+                if j == i { continue; }
+                let r = pi - self.positions[j];
+                if r.dot(&r) >= h * h { continue; }
+                let shared_pressure = (self.pressures[i] + self.pressures[j]) / (2.0 * self.densities[j]);
+                pressure_force = pressure_force - spiky_gradient(r, h) * (self.particle_mass * shared_pressure);
+                viscosity_force = viscosity_force
+                    + (self.velocities[j] - vi) * (self.viscosity * self.particle_mass / self.densities[j] * viscosity_laplacian(r.length(), h));
+            }
+            accelerations.push((pressure_force + viscosity_force) / self.densities[i] + self.gravity);
+        }
+
+        for (i, acceleration) in accelerations.into_iter().enumerate() {
+            self.velocities[i] = self.velocities[i] + acceleration * dt;
+            self.positions[i] = self.positions[i] + self.velocities[i] * dt;
+            self.apply_boundaries(i);
+        }
+    }
+
+    fn compute_densities(&mut self, grid: &NeighbourGrid) {
+        let h = self.smoothing_radius;
+        for i in 0..self.positions.len() {
+            let pi = self.positions[i];
+            let density: f32 = grid.near(pi)
+                .map(|j| {
+                    let r = pi - self.positions[j];
+                    self.particle_mass * poly6(r.dot(&r), h)
+                })
+                .sum();
+            self.densities[i] = density;
+            // Only resist compression, a free surface shouldn't pull particles together
+            self.pressures[i] = (self.stiffness * (density - self.rest_density)).max(0.0);
+        }
+    }
+
+    // Projects a particle back out of every boundary, bouncing its velocity off the wall
+    fn apply_boundaries(&mut self, i: usize) {
+        let r = self.particle_radius;
+        let e = self.boundary_restitution;
+        let (mut p, mut v) = (self.positions[i], self.velocities[i]);
+        for boundary in &self.boundaries {
+            match boundary {
+                Boundary::Plane(plane) => {
+                    let distance = plane.signed_distance(p);
+                    if distance < r {
+                        let n = plane.normal();
+                        p = p + n * (r - distance);
+                        let vn = v.dot(&n);
+                        if vn < 0.0 { v = v - n * ((1.0 + e) * vn); }
+                    }
+                }
+                Boundary::Container(bounds) => {
+                    let mut c = [p.x(), p.y(), p.z()];
+                    let mut w = [v.x(), v.y(), v.z()];
+                    let lo = [bounds.min.x() + r, bounds.min.y() + r, bounds.min.z() + r];
+                    let hi = [bounds.max.x() - r, bounds.max.y() - r, bounds.max.z() - r];
+                    for axis in 0..3 {
+                        if c[axis] < lo[axis] {
+                            c[axis] = lo[axis];
+                            if w[axis] < 0.0 { w[axis] *= -e; }
+                        } else if c[axis] > hi[axis] {
+                            c[axis] = hi[axis];
+                            if w[axis] > 0.0 { w[axis] *= -e; }
+                        }
+                    }
+                    p = Vec3::new(c[0], c[1], c[2]);
+                    v = Vec3::new(w[0], w[1], w[2]);
+                }
+                Boundary::Obstacle(bounds) => {
+                    let lo = bounds.min - r;
+                    let hi = bounds.max + r;
+                    let inside = p.x() > lo.x() && p.x() < hi.x() && p.y() > lo.y() && p.y() < hi.y() && p.z() > lo.z() && p.z() < hi.z();
+                    if inside {
+                        // Leave through the nearest face
+                        let exits = [
+                            (p.x() - lo.x(), Vec3::new(-1.0, 0.0, 0.0)),
+                            (hi.x() - p.x(), Vec3::new(1.0, 0.0, 0.0)),
+                            (p.y() - lo.y(), Vec3::new(0.0, -1.0, 0.0)),
+                            (hi.y() - p.y(), Vec3::new(0.0, 1.0, 0.0)),
+                            (p.z() - lo.z(), Vec3::new(0.0, 0.0, -1.0)),
+                            (hi.z() - p.z(), Vec3::new(0.0, 0.0, 1.0)),
+                        ];
+                        let (depth, n) = exits.into_iter().min_by(|a, b| a.0.total_cmp(&b.0)).expect("six faces");
+                        p = p + n * depth;
+                        let vn = v.dot(&n);
+                        if vn < 0.0 { v = v - n * ((1.0 + e) * vn); }
+                    }
+                }
+            }
+        }
+        self.positions[i] = p;
+        self.velocities[i] = v;
+    }
+
+    // Geometry for Camera::render_scene
+    pub(crate) fn hittables(&self, rendering: FluidRendering, material: Material) -> Vec<Box<dyn Hittable>> {
+        match rendering {
+            FluidRendering::Spheres => self.positions.iter()
+                .map(|p| Box::new(Sphere::new(self.particle_radius, *p, material)) as Box<dyn Hittable>)
+                .collect(),
+            FluidRendering::Surface => match self.surface(material) {
+                Some(surface) => vec![Box::new(surface) as Box<dyn Hittable>],
+                None => Vec::new(),
+            },
+        }
+    }
+
+    // Smooth union of a sphere per particle, sphere traced. Only particles in the cells around
+    // a sample point are blended, anything further away is at least a cell away, which keeps
+    // the field a safe distance bound.
+    pub(crate) fn surface(&self, material: Material) -> Option<DistanceField> {
+        let radius = self.particle_radius * 2.0;
+        let blend = self.particle_radius * 2.0;
+        let cell_size = radius + blend;
+        let bounds = Aabb::from_points(&self.positions)?;
+        let grid = NeighbourGrid::new(&self.positions, cell_size);
+        let positions = self.positions.clone();
+        let field = move |p: Vec3| {
+            let far = cell_size - radius;
+            grid.near(p)
+                .map(|j| (p - positions[j]).length() - radius)
+                .fold(far, |d, s| smooth_min(d, s, blend))
+                .min(far)
+        };
+        let mut surface = DistanceField::new(field, material);
+        surface.set_bounds(Aabb::new(bounds.min - cell_size, bounds.max + cell_size));
+        surface.set_step_scale(0.8);
+        surface.set_hit_epsilon(1e-3);
+        Some(surface)
+    }
+}
+
+// Kernel sum at a particle inside an infinite lattice, the density per unit particle mass
+fn lattice_weight(spacing: f32, h: f32) -> f32 {
+    let reach = (h / spacing).ceil() as i32;
+    let mut sum = 0.0;
+    for i in -reach..=reach {
+        for j in -reach..=reach {
+            for k in -reach..=reach {
+                let r = Vec3::new(i as f32, j as f32, k as f32) * spacing;
+                sum += poly6(r.dot(&r), h);
+            }
+        }
+    }
+    sum
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ray::Ray;
+
+    #[test]
+    fn density_kernel_integrates_to_one() {
+        let h = 0.5;
+        let step = h / 20.0;
+        let mut total = 0.0;
+        for i in -20..=20 {
+            for j in -20..=20 {
+                for k in -20..=20 {
+                    let r = Vec3::new(i as f32, j as f32, k as f32) * step;
+                    total += poly6(r.dot(&r), h) * step.powi(3);
+                }
+            }
+        }
+        assert!((total - 1.0).abs() < 0.01, "{}", total);
+    }
+
+    #[test]
+    fn grid_finds_the_same_neighbours_as_brute_force() {
+        let fluid = Fluid::block(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.3, 0.2, 0.25), 0.05, 1000.0);
+        let h = fluid.smoothing_radius;
+        let grid = NeighbourGrid::new(&fluid.positions, h);
+        for (i, pi) in fluid.positions.iter().enumerate() {
+            let mut near: Vec<usize> = grid.near(*pi).filter(|&j| (*pi - fluid.positions[j]).length() < h).collect();
+            near.sort_unstable();
+            let brute: Vec<usize> = (0..fluid.positions.len()).filter(|&j| (*pi - fluid.positions[j]).length() < h).collect();
+            assert_eq!(near, brute, "particle {}", i);
+        }
+    }
+
+    #[test]
+    fn block_interior_starts_at_rest_density() {
+        let mut fluid = Fluid::block(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.4, 0.4, 0.4), 0.05, 1000.0);
+        let grid = NeighbourGrid::new(&fluid.positions, fluid.smoothing_radius);
+        fluid.compute_densities(&grid);
+        // The particle in the middle has a full neighbourhood
+        let centre = fluid.positions.iter().position(|p| (*p - Vec3::new(0.2, 0.2, 0.2)).length() < 1e-4).unwrap();
+        assert!((fluid.densities()[centre] - 1000.0).abs() < 1.0, "{}", fluid.densities()[centre]);
+    }
+
+    #[test]
+    fn dam_break_spreads_across_the_tank() {
+        let tank = Aabb::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.8, 0.6, 0.2));
+        let mut fluid = Fluid::block(Vec3::new(0.03, 0.03, 0.03), Vec3::new(0.23, 0.33, 0.17), 0.05, 1000.0);
+        fluid.add_boundary(Boundary::Container(tank));
+        let start_height = fluid.positions().iter().map(|p| p.y()).fold(0.0, f32::max);
+        for _ in 0..60 {
+            fluid.step(1.0 / 60.0);
+        }
+        let r = fluid.particle_radius();
+        for (p, v) in fluid.positions().iter().zip(fluid.velocities()) {
+            assert!(p.x() >= r - 1e-5 && p.x() <= 0.8 - r + 1e-5 && p.y() >= r - 1e-5 && p.z() >= r - 1e-5 && p.z() <= 0.2 - r + 1e-5, "escaped to {:?}", p);
+            assert!(v.length() < 5.0, "blew up: {:?}", v);
+        }
+        // The column collapsed and ran along the floor
+        let height = fluid.positions().iter().map(|p| p.y()).fold(0.0, f32::max);
+        let reach = fluid.positions().iter().map(|p| p.x()).fold(0.0, f32::max);
+        assert!(height < start_height, "height {} from {}", height, start_height);
+        assert!(reach > 0.5, "reach {}", reach);
+    }
+
+    #[test]
+    fn obstacle_and_plane_keep_particles_out() {
+        let mut fluid = Fluid::block(Vec3::new(0.0, 0.5, 0.0), Vec3::new(0.1, 0.6, 0.1), 0.05, 1000.0);
+        let obstacle = Aabb::new(Vec3::new(-0.2, 0.2, -0.2), Vec3::new(0.3, 0.3, 0.3));
+        fluid.add_boundary(Boundary::Obstacle(obstacle));
+        fluid.add_boundary(Boundary::Plane(InfinitePlane::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0), Material::default())));
+        for _ in 0..60 {
+            fluid.step(1.0 / 60.0);
+            for p in fluid.positions() {
+                assert!(p.y() >= fluid.particle_radius() - 1e-5);
+                let inside = p.x() > obstacle.min.x() && p.x() < obstacle.max.x()
+                    && p.y() > obstacle.min.y() && p.y() < obstacle.max.y()
+                    && p.z() > obstacle.min.z() && p.z() < obstacle.max.z();
+                assert!(!inside, "inside the obstacle at {:?}", p);
+            }
+        }
+    }
+
+    #[test]
+    fn renders_as_spheres_or_a_surface() {
+        let mut fluid = Fluid::block(Vec3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, 5.0), 0.1, 1000.0);
+        fluid.add_particle(Vec3::new(0.1, 0.0, 5.0), Vec3::new(0.0, 0.0, 0.0));
+        assert_eq!(fluid.hittables(FluidRendering::Spheres, Material::default()).len(), 2);
+
+        let surface = fluid.hittables(FluidRendering::Surface, Material::default());
+        assert_eq!(surface.len(), 1);
+        // Straight at the first particle: the blended surface bulges a little past its sphere
+        let ray = Ray::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 1.0));
+        let (t, _p, n) = surface[0].intersects_ray(&ray).expect("hit");
+        assert!(t < 5.0 - 0.1 + 1e-3 && t > 5.0 - 0.2, "t {}", t);
+        assert!(n.z() < -0.9);
+        // Between the two particles the surface fills the gap
+        let ray = Ray::new(Vec3::new(0.05, 0.0, 0.0), Vec3::new(0.0, 0.0, 1.0));
+        assert!(surface[0].intersects_ray(&ray).is_some());
+    }
+}
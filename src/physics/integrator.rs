@@ -0,0 +1,98 @@
+// filepath: src/physics/integrator.rs
+use crate::physics::body::RigidBody;
+use crate::quat::Quat;
+use crate::vec3::Vec3;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[allow(dead_code, reason = "Rk4 is opt-in through World::set_integrator, the demo steps with Euler")]
+pub(crate) enum Integrator {
+    // Velocity first, then position with the new velocity. Cheap and stable for stiff contacts.
+    SemiImplicitEuler,
+    // Classic 4th order Runge-Kutta, accurate for smooth force fields
+    Rk4,
+}
+
+#[derive(Debug, Copy, Clone)]
+struct State {
+    position: Vec3,
+    orientation: Quat,
+    velocity: Vec3,
+    angular_velocity: Vec3,
+}
+
+#[derive(Debug, Copy, Clone)]
+struct Derivative {
+    velocity: Vec3,
+    spin: Quat,
+    acceleration: Vec3,
+    angular_acceleration: Vec3,
+}
+
+// Advances one body by dt under gravity plus its accumulated force and torque,
+// which are held constant over the step
+pub(crate) fn integrate(body: &mut RigidBody, gravity: Vec3, dt: f32, integrator: Integrator) {
+    if body.is_static() { return; }
+    let state = State {
+        position: body.position,
+        orientation: body.orientation,
+        velocity: body.velocity,
+        angular_velocity: body.angular_velocity,
+    };
+    let next = match integrator {
+        Integrator::SemiImplicitEuler => semi_implicit_euler(body, state, gravity, dt),
+        Integrator::Rk4 => rk4(body, state, gravity, dt),
+    };
+    body.position = next.position;
+    body.orientation = next.orientation.normalized();
+    body.velocity = next.velocity;
+    body.angular_velocity = next.angular_velocity;
+}
+
+fn evaluate(body: &RigidBody, state: &State, gravity: Vec3) -> Derivative {
+    let acceleration = gravity + body.force() * body.inverse_mass();
+    // Euler's equations in world space: I w' = tau - w x (I w)
+    let inertia = body.inertia_world_at(state.orientation);
+    let w = state.angular_velocity;
+    let gyroscopic = w.cross(&(inertia * w));
+    let angular_acceleration = body.inverse_inertia_world_at(state.orientation) * (body.torque() - gyroscopic);
+    Derivative {
+        velocity: state.velocity,
+        spin: state.orientation.derivative(w),
+        acceleration,
+        angular_acceleration,
+    }
+}
+
+fn semi_implicit_euler(body: &RigidBody, state: State, gravity: Vec3, dt: f32) -> State {
+    let d = evaluate(body, &state, gravity);
+    let velocity = state.velocity + d.acceleration * dt;
+    let angular_velocity = state.angular_velocity + d.angular_acceleration * dt;
+    State {
+        position: state.position + velocity * dt,
+        orientation: state.orientation.integrated(angular_velocity, dt),
+        velocity,
+        angular_velocity,
+    }
+}
+
+fn rk4(body: &RigidBody, state: State, gravity: Vec3, dt: f32) -> State {
+    let step = |d: &Derivative, h: f32| State {
+        position: state.position + d.velocity * h,
+        orientation: (state.orientation + d.spin * h).normalized(),
+        velocity: state.velocity + d.acceleration * h,
+        angular_velocity: state.angular_velocity + d.angular_acceleration * h,
+    };
+    let k1 = evaluate(body, &state, gravity);
+    let k2 = evaluate(body, &step(&k1, 0.5 * dt), gravity);
+    let k3 = evaluate(body, &step(&k2, 0.5 * dt), gravity);
+    let k4 = evaluate(body, &step(&k3, dt), gravity);
+
+    let sixth = dt / 6.0;
+    State {
+        position: state.position + (k1.velocity + (k2.velocity + k3.velocity) * 2.0 + k4.velocity) * sixth,
+        orientation: state.orientation + (k1.spin + (k2.spin + k3.spin) * 2.0 + k4.spin) * sixth,
+        velocity: state.velocity + (k1.acceleration + (k2.acceleration + k3.acceleration) * 2.0 + k4.acceleration) * sixth,
+        angular_velocity: state.angular_velocity
+            + (k1.angular_acceleration + (k2.angular_acceleration + k3.angular_acceleration) * 2.0 + k4.angular_acceleration) * sixth,
+    }
+}
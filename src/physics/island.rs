@@ -0,0 +1,99 @@
+// filepath: src/physics/island.rs
+use crate::physics::body::RigidBody;
+
+// Speeds below which a body counts as resting
+const LINEAR_SLEEP_SPEED: f32 = 0.05;
+const ANGULAR_SLEEP_SPEED: f32 = 0.05;
+// How long every body of an island has to rest before the island goes to sleep
+const TIME_TO_SLEEP: f32 = 0.5;
+
+// Groups of dynamic bodies linked by contacts or joints, each sorted by body index and ordered by
+// their first body. Static bodies don't link islands, otherwise everything resting on the ground
+// would end up in one island. Bodies with no links form islands of their own.
+pub(crate) fn islands(bodies: &[RigidBody], links: impl IntoIterator<Item = (usize, usize)>) -> Vec<Vec<usize>> {
+    let mut parent: Vec<usize> = (0..bodies.len()).collect();
+    for (a, b) in links {
+        if bodies[a].is_static() || bodies[b].is_static() { continue; }
+        let (ra, rb) = (root(&mut parent, a), root(&mut parent, b));
+        // Lower index becomes the root, keeps the output independent of link order
+        if ra != rb { parent[ra.max(rb)] = ra.min(rb); }
+    }
+
+    let mut groups: Vec<Vec<usize>> = Vec::new();
+    let mut group_of_root = vec![usize::MAX; bodies.len()];
+    for (i, body) in bodies.iter().enumerate() {
+        if body.is_static() { continue; }
+        let r = root(&mut parent, i);
+        if group_of_root[r] == usize::MAX {
+            group_of_root[r] = groups.len();
+            groups.push(Vec::new());
+        }
+        groups[group_of_root[r]].push(i);
+    }
+    groups
+}
+
+fn root(parent: &mut [usize], mut i: usize) -> usize {
+    while parent[i] != i {
+        parent[i] = parent[parent[i]]; // path halving
+        i = parent[i];
+    }
+    i
+}
+
+// Advances every awake body's rest timer, then puts islands to sleep once all of their bodies have
+// rested long enough. An island with any restless body wakes its sleeping members instead, which is
+// how a moving body knocking into a sleeping pile wakes the pile.
+pub(crate) fn update_sleep(bodies: &mut [RigidBody], islands: &[Vec<usize>], dt: f32) {
+    for body in bodies.iter_mut() {
+        if body.is_static() || body.is_sleeping() { continue; }
+        let resting = body.velocity.length() < LINEAR_SLEEP_SPEED && body.angular_velocity.length() < ANGULAR_SLEEP_SPEED;
+        body.set_rest_time(if resting { body.rest_time() + dt } else { 0.0 });
+    }
+    for island in islands {
+        let ready = island.iter().all(|&i| bodies[i].is_sleeping() || bodies[i].rest_time() >= TIME_TO_SLEEP);
+        for &i in island {
+            if ready { bodies[i].sleep(); } else { bodies[i].wake(); }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Material;
+    use crate::objects::sphere::Sphere;
+    use crate::physics::body::Shape;
+    use crate::vec3::Vec3;
+
+    fn ball(mass: f32) -> RigidBody {
+        RigidBody::new(Shape::Sphere(Sphere::new(0.5, Vec3::new(0.0, 0.0, 0.0), Material::default())), mass)
+    }
+
+    #[test]
+    fn static_bodies_do_not_join_islands() {
+        // 0 and 3 are static ground, 1-2 touch each other, 4 rests alone on the ground
+        let bodies = vec![ball(0.0), ball(1.0), ball(1.0), ball(0.0), ball(1.0), ball(1.0)];
+        let groups = islands(&bodies, [(0, 1), (2, 1), (0, 4), (3, 4), (3, 2)]);
+        assert_eq!(groups, vec![vec![1, 2], vec![4], vec![5]]);
+    }
+
+    #[test]
+    fn restless_body_keeps_its_island_awake() {
+        let mut bodies = vec![ball(1.0), ball(1.0)];
+        bodies[0].sleep();
+        bodies[1].velocity = Vec3::new(1.0, 0.0, 0.0);
+        let groups = islands(&bodies, [(0, 1)]);
+        update_sleep(&mut bodies, &groups, 0.1);
+        assert!(!bodies[0].is_sleeping());
+
+        // Once both rest for long enough they sleep together
+        bodies[1].velocity = Vec3::new(0.0, 0.0, 0.0);
+        for _ in 0..4 {
+            update_sleep(&mut bodies, &groups, 0.1);
+            assert!(!bodies[1].is_sleeping());
+        }
+        update_sleep(&mut bodies, &groups, 0.2);
+        assert!(bodies[0].is_sleeping() && bodies[1].is_sleeping());
+    }
+}
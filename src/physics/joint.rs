@@ -0,0 +1,286 @@
+// filepath: src/physics/joint.rs
+use crate::physics::body::RigidBody;
+use crate::physics::solver::{Jacobian, Row};
+use crate::quat::Quat;
+use crate::vec3::Vec3;
+
+// Fraction of the positional / angular drift fed back into the velocity target each step (Baumgarte)
+const BAUMGARTE: f32 = 0.2;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[allow(dead_code, reason = "picked by whoever calls World::add_joint; the demo has nothing jointed")]
+pub(crate) enum JointKind {
+    BallSocket,           // shared pivot, free rotation
+    Hinge { axis: Vec3 }, // shared pivot, rotation about the world space axis only
+    Slider { axis: Vec3 }, // translation along the world space axis only, no relative rotation
+    Fixed,                // welded, no relative motion at all
+    Distance,             // anchors keep the separation they had when the joint was made
+}
+
+// Constraint between two bodies of a world, by index into its body list. Anchors and axes are
+// stored in each body's frame so they move with the bodies.
+#[derive(Debug, Clone)]
+pub(crate) struct Joint {
+    kind: JointKind,
+    a: usize,
+    b: usize,
+    local_anchor_a: Vec3,
+    local_anchor_b: Vec3,
+    local_axis_a: Vec3,
+    local_axis_b: Vec3,
+    rest_orientation: Quat, // orientation of b relative to a at creation
+    rest_length: f32,
+    impulses: Vec<f32>, // per row, from the last step, for warm starting
+}
+
+impl Joint {
+    // Anchors are world space points, fixed to body a and body b respectively at their current poses
+    pub(crate) fn new(kind: JointKind, bodies: &[RigidBody], a: usize, b: usize, anchor_a: Vec3, anchor_b: Vec3) -> Self {
+        assert_ne!(a, b, "a joint needs two different bodies");
+        let (body_a, body_b) = (&bodies[a], &bodies[b]);
+        let to_a = body_a.orientation.conjugate();
+        let to_b = body_b.orientation.conjugate();
+        let axis = match kind {
+            JointKind::Hinge { axis } | JointKind::Slider { axis } => axis.normalized(),
+            _ => Vec3::new(0.0, 0.0, 0.0),
+        };
+        Joint {
+            kind,
+            a,
+            b,
+            local_anchor_a: to_a.rotate(anchor_a - body_a.position),
+            local_anchor_b: to_b.rotate(anchor_b - body_b.position),
+            local_axis_a: to_a.rotate(axis),
+            local_axis_b: to_b.rotate(axis),
+            rest_orientation: to_a * body_b.orientation,
+            rest_length: (anchor_b - anchor_a).length(),
+            impulses: Vec::new(),
+        }
+    }
+
+    pub(crate) fn bodies(&self) -> (usize, usize) { (self.a, self.b) }
+    pub(crate) fn impulses(&self) -> &[f32] { &self.impulses }
+
+    pub(crate) fn set_impulses(&mut self, impulses: Vec<f32>) {
+        self.impulses = impulses;
+    }
+
+    // World space anchors on body a and body b, equal while the joint holds
+    #[cfg(test)]
+    pub(crate) fn anchors(&self, bodies: &[RigidBody]) -> (Vec3, Vec3) {
+        let (a, b) = (&bodies[self.a], &bodies[self.b]);
+        (a.position + a.orientation.rotate(self.local_anchor_a), b.position + b.orientation.rotate(self.local_anchor_b))
+    }
+
+    // Solver rows for the current poses
+    pub(crate) fn rows(&self, bodies: &[RigidBody], dt: f32) -> Vec<Row> {
+        let (body_a, body_b) = (&bodies[self.a], &bodies[self.b]);
+        let ra = body_a.orientation.rotate(self.local_anchor_a);
+        let rb = body_b.orientation.rotate(self.local_anchor_b);
+        let separation = (body_b.position + rb) - (body_a.position + ra);
+        let feedback = BAUMGARTE / dt;
+        let row = |jacobian: Jacobian, error: f32| Row::new(self.a, self.b, bodies, jacobian, feedback * error);
+        let world_axes = [Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, 0.0, 1.0)];
+
+        let mut rows = Vec::new();
+        match self.kind {
+            JointKind::BallSocket => {
+                for e in world_axes {
+                    rows.push(row(Jacobian::linear(ra, rb, e), separation.dot(&e)));
+                }
+            }
+            JointKind::Hinge { .. } => {
+                for e in world_axes {
+                    rows.push(row(Jacobian::linear(ra, rb, e), separation.dot(&e)));
+                }
+                // Keep the two copies of the axis aligned, leaving spin about it free
+                let axis_a = body_a.orientation.rotate(self.local_axis_a);
+                let axis_b = body_b.orientation.rotate(self.local_axis_b);
+                let misalignment = axis_a.cross(&axis_b);
+                let (t1, t2) = axis_a.orthonormal_basis();
+                for t in [t1, t2] {
+                    rows.push(row(Jacobian::angular(t), misalignment.dot(&t)));
+                }
+            }
+            JointKind::Slider { .. } => {
+                let error = self.orientation_error(body_a, body_b);
+                for e in world_axes {
+                    rows.push(row(Jacobian::angular(e), error.dot(&e)));
+                }
+                // Body a's lever reaches to b's anchor, wherever it has slid to
+                let axis = body_a.orientation.rotate(self.local_axis_a);
+                let (t1, t2) = axis.orthonormal_basis();
+                for t in [t1, t2] {
+                    rows.push(row(Jacobian::linear(ra + separation, rb, t), separation.dot(&t)));
+                }
+            }
+            JointKind::Fixed => {
+                for e in world_axes {
+                    rows.push(row(Jacobian::linear(ra, rb, e), separation.dot(&e)));
+                }
+                let error = self.orientation_error(body_a, body_b);
+                for e in world_axes {
+                    rows.push(row(Jacobian::angular(e), error.dot(&e)));
+                }
+            }
+            JointKind::Distance => {
+                let length = separation.length();
+                if length > 1e-6 {
+                    let n = separation / length;
+                    rows.push(row(Jacobian::linear(ra, rb, n), length - self.rest_length));
+                }
+            }
+        }
+        rows
+    }
+
+    // Small rotation vector taking b's rest orientation (relative to a) to its current one, in world space
+    fn orientation_error(&self, body_a: &RigidBody, body_b: &RigidBody) -> Vec3 {
+        let target = body_a.orientation * self.rest_orientation;
+        let mut delta = body_b.orientation * target.conjugate();
+        if delta.w < 0.0 {
+            delta = delta * -1.0; // shortest way round
+        }
+        Vec3::new(delta.x, delta.y, delta.z) * 2.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Material;
+    use crate::objects::sphere::Sphere;
+    use crate::physics::body::Shape;
+    use crate::physics::world::{BodyHandle, World};
+
+    fn ball(world: &mut World, position: Vec3, mass: f32) -> BodyHandle {
+        world.add_body(RigidBody::new(Shape::Sphere(Sphere::new(0.1, position, Material::default())), mass))
+    }
+
+    fn joint_error(world: &World) -> f32 {
+        world.joints().iter()
+            .map(|j| { let (pa, pb) = j.anchors(world.bodies()); (pb - pa).length() })
+            .fold(0.0, f32::max)
+    }
+
+    // Pivot at the origin with `links` balls hanging along +X, 0.5 apart
+    fn chain(links: usize) -> World {
+        let mut world = World::new(Vec3::new(0.0, -9.81, 0.0));
+        let mut previous = ball(&mut world, Vec3::new(0.0, 0.0, 0.0), 0.0);
+        for i in 1..=links {
+            let link = ball(&mut world, Vec3::new(0.5 * i as f32, 0.0, 0.0), 1.0);
+            let pivot = Vec3::new(0.5 * i as f32 - 0.25, 0.0, 0.0);
+            world.add_joint(previous, link, JointKind::BallSocket, pivot, pivot);
+            previous = link;
+        }
+        world
+    }
+
+    #[test]
+    fn distance_pendulum_keeps_its_length_and_period() {
+        let mut world = World::new(Vec3::new(0.0, -9.81, 0.0));
+        let pivot = ball(&mut world, Vec3::new(0.0, 0.0, 0.0), 0.0);
+        let length = 2.0;
+        let angle: f32 = 0.1;
+        let start = Vec3::new(length * angle.sin(), -length * angle.cos(), 0.0);
+        let bob = ball(&mut world, start, 1.0);
+        world.add_joint(pivot, bob, JointKind::Distance, Vec3::new(0.0, 0.0, 0.0), start);
+
+        // Half a period between the first two crossings of the vertical
+        let mut crossings = Vec::new();
+        let mut last_x = start.x();
+        while crossings.len() < 2 {
+            world.step();
+            let p = world.body(bob).position;
+            assert!((p.length() - length).abs() < 0.02, "length drifted to {}", p.length());
+            if last_x.signum() != p.x().signum() { crossings.push(world.time()); }
+            last_x = p.x();
+        }
+        let period = 2.0 * (crossings[1] - crossings[0]);
+        let expected = 2.0 * std::f32::consts::PI * (length / 9.81).sqrt();
+        assert!((period - expected).abs() < 0.03 * expected, "period {} expected {}", period, expected);
+    }
+
+    #[test]
+    fn swinging_chain_stays_connected() {
+        let mut world = chain(6);
+        let mut lowest: f32 = 0.0;
+        for _ in 0..240 {
+            world.step();
+            assert!(joint_error(&world) < 0.05, "chain stretched by {}", joint_error(&world));
+            lowest = lowest.min(world.bodies()[6].position.y());
+        }
+        // The free end swung well below the pivot
+        assert!(lowest < -2.0, "lowest {}", lowest);
+    }
+
+    #[test]
+    fn warm_starting_stiffens_a_chain_at_low_iterations() {
+        let stretch = |warm: bool| {
+            let mut world = chain(10);
+            world.set_solver_iterations(2);
+            world.set_warm_starting(warm);
+            for _ in 0..240 { world.step(); }
+            joint_error(&world)
+        };
+        let (warm, cold) = (stretch(true), stretch(false));
+        assert!(warm < cold, "warm {} cold {}", warm, cold);
+    }
+
+    #[test]
+    fn hinge_only_turns_about_its_axis() {
+        let mut world = World::new(Vec3::new(0.0, 0.0, 0.0));
+        let frame = ball(&mut world, Vec3::new(0.0, 0.0, 0.0), 0.0);
+        let door = ball(&mut world, Vec3::new(1.0, 0.0, 0.0), 1.0);
+        let axis = Vec3::new(0.0, 1.0, 0.0);
+        world.add_joint(frame, door, JointKind::Hinge { axis }, Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 0.0));
+        // Swinging about the hinge, plus a knock about the other axes that the hinge has to absorb
+        world.body_mut(door).velocity = Vec3::new(0.0, 0.0, -1.0);
+        world.body_mut(door).angular_velocity = Vec3::new(2.0, 1.0, 0.5);
+        for _ in 0..60 {
+            world.step();
+        }
+        let w = world.body(door).angular_velocity;
+        let off_axis = (w - axis * w.dot(&axis)).length();
+        assert!(off_axis < 1e-2, "wobbling at {}", off_axis);
+        assert!(w.dot(&axis) > 0.5, "swing about the hinge should survive");
+        // Still one unit from the hinge, in the horizontal plane
+        let p = world.body(door).position;
+        assert!((p.length() - 1.0).abs() < 0.02 && p.y().abs() < 0.02, "{:?}", p);
+    }
+
+    #[test]
+    fn slider_moves_along_its_axis_only() {
+        let mut world = World::new(Vec3::new(0.0, -9.81, 0.0));
+        let rail = ball(&mut world, Vec3::new(0.0, 0.0, 0.0), 0.0);
+        let carriage = ball(&mut world, Vec3::new(0.0, 0.0, 0.0), 1.0);
+        world.add_joint(rail, carriage, JointKind::Slider { axis: Vec3::new(1.0, 0.0, 0.0) }, Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 0.0));
+        world.body_mut(carriage).velocity = Vec3::new(1.0, 0.0, 0.0);
+        for _ in 0..120 {
+            world.step();
+        }
+        let body = world.body(carriage);
+        assert!(body.position.y().abs() < 0.02, "sagged to {}", body.position.y());
+        assert!((body.position.x() - world.time()).abs() < 0.05, "x {}", body.position.x());
+        assert!(body.angular_velocity.length() < 1e-3);
+    }
+
+    #[test]
+    fn fixed_joint_welds_two_bodies() {
+        let mut world = World::new(Vec3::new(0.0, 0.0, 0.0));
+        let a = ball(&mut world, Vec3::new(0.0, 0.0, 0.0), 1.0);
+        let b = ball(&mut world, Vec3::new(1.0, 0.0, 0.0), 1.0);
+        let weld = Vec3::new(0.5, 0.0, 0.0);
+        world.add_joint(a, b, JointKind::Fixed, weld, weld);
+        // Knock b sideways: the pair should tumble as one piece
+        world.body_mut(b).velocity = Vec3::new(0.0, 1.0, 0.0);
+        for _ in 0..120 {
+            world.step();
+        }
+        let separation = (world.body(b).position - world.body(a).position).length();
+        assert!((separation - 1.0).abs() < 0.02, "separation {}", separation);
+        let (wa, wb) = (world.body(a).angular_velocity, world.body(b).angular_velocity);
+        assert!((wa - wb).length() < 1e-2);
+        assert!(wa.z() > 0.1, "the knock should spin the pair");
+    }
+}
@@ -0,0 +1,15 @@
+// filepath: src/physics/mod.rs
+
+pub mod body;
+pub mod broad_phase;
+pub mod ccd;
+pub mod cloth;
+pub mod contact;
+pub mod fluid;
+pub mod integrator;
+pub mod island;
+pub mod joint;
+pub mod particles;
+pub mod recording;
+pub mod solver;
+pub mod world;
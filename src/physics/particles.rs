@@ -0,0 +1,339 @@
+// filepath: src/physics/particles.rs
+#![allow(dead_code, reason = "particle effects are opt-in; the demo simulates rigid bodies only")]
+use crate::material::Material;
+use crate::objects::disc::Disc;
+use crate::objects::hittable::Hittable;
+use crate::objects::sphere::Sphere;
+use crate::ray::Segment;
+use crate::rng::Rng;
+use crate::vec3::Vec3;
+
+// Gap left between a particle and the surface it bounced off, so the next step's segment starts clear of it
+const SURFACE_OFFSET: f32 = 1e-3;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) struct Particle {
+    pub position: Vec3,
+    pub velocity: Vec3,
+    pub age: f32,
+    pub lifetime: f32,
+    pub radius: f32,
+}
+
+impl Particle {
+    // 0 when born, 1 when it dies
+    pub(crate) fn life_fraction(&self) -> f32 {
+        (self.age / self.lifetime).clamp(0.0, 1.0)
+    }
+}
+
+// How an emitter picks each new particle's velocity
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) enum VelocityDistribution {
+    Fixed(Vec3),
+    // Uniform direction within half_angle radians of direction, speed uniform in [min, max)
+    Cone { direction: Vec3, half_angle: f32, speed: (f32, f32) },
+    // Uniform direction over the whole sphere
+    Sphere { speed: (f32, f32) },
+}
+
+impl VelocityDistribution {
+    fn sample(&self, rng: &mut Rng) -> Vec3 {
+        match *self {
+            VelocityDistribution::Fixed(v) => v,
+            VelocityDistribution::Cone { direction, half_angle, speed } => rng.in_cone(direction, half_angle) * rng.range(speed.0, speed.1),
+            VelocityDistribution::Sphere { speed } => rng.unit_vector() * rng.range(speed.0, speed.1),
+        }
+    }
+}
+
+// Spawns particles at a steady rate from a point, or from within a ball around it
+#[derive(Debug, Clone)]
+pub(crate) struct Emitter {
+    position: Vec3,
+    spawn_radius: f32,
+    rate: f32,              // particles per second
+    lifetime: (f32, f32),   // seconds, uniform in [min, max)
+    velocity: VelocityDistribution,
+    particle_radius: f32,
+    pending: f32,           // fractional particles carried between steps
+}
+
+impl Emitter {
+    pub(crate) fn new(position: Vec3, rate: f32, lifetime: (f32, f32), velocity: VelocityDistribution) -> Self {
+        Emitter { position, spawn_radius: 0.0, rate, lifetime, velocity, particle_radius: 0.05, pending: 0.0 }
+    }
+
+    pub(crate) fn set_position(&mut self, position: Vec3) {
+        self.position = position;
+    }
+
+    pub(crate) fn set_rate(&mut self, rate: f32) {
+        self.rate = rate.max(0.0);
+    }
+
+    pub(crate) fn set_spawn_radius(&mut self, spawn_radius: f32) {
+        self.spawn_radius = spawn_radius.max(0.0);
+    }
+
+    pub(crate) fn set_particle_radius(&mut self, particle_radius: f32) {
+        self.particle_radius = particle_radius;
+    }
+
+    pub(crate) fn position(&self) -> Vec3 { self.position }
+    pub(crate) fn rate(&self) -> f32 { self.rate }
+
+    fn emit(&mut self, dt: f32, rng: &mut Rng, out: &mut Vec<Particle>) {
+        self.pending += self.rate * dt;
+        while self.pending >= 1.0 {
+            self.pending -= 1.0;
+            let offset = if self.spawn_radius > 0.0 {
+                rng.unit_vector() * (self.spawn_radius * rng.next_f32().cbrt())
+            } else {
+                Vec3::new(0.0, 0.0, 0.0)
+            };
+            out.push(Particle {
+                position: self.position + offset,
+                velocity: self.velocity.sample(rng),
+                age: 0.0,
+                lifetime: rng.range(self.lifetime.0, self.lifetime.1),
+                radius: self.particle_radius,
+            });
+        }
+    }
+}
+
+// Accelerations acting on every particle
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) enum Force {
+    Gravity(Vec3),
+    // Linear drag, slows particles towards rest at rate `coefficient` per second
+    Drag(f32),
+    // Pulls particle velocity towards the wind velocity
+    Wind { velocity: Vec3, coefficient: f32 },
+    // Swirl around a line through centre along axis, strongest near the line
+    Vortex { centre: Vec3, axis: Vec3, strength: f32 },
+}
+
+impl Force {
+    fn acceleration(&self, p: &Particle) -> Vec3 {
+        match *self {
+            Force::Gravity(g) => g,
+            Force::Drag(k) => p.velocity * -k,
+            Force::Wind { velocity, coefficient } => (velocity - p.velocity) * coefficient,
+            Force::Vortex { centre, axis, strength } => {
+                let axis = axis.normalized();
+                let r = p.position - centre;
+                let radial = r - axis * r.dot(&axis);
+                let distance = radial.length().max(0.1);
+                axis.cross(&radial).normalized() * (strength / distance)
+            }
+        }
+    }
+}
+
+// Particles from any number of emitters, moved by forces and bounced off scene geometry
+pub(crate) struct ParticleSystem {
+    emitters: Vec<Emitter>,
+    forces: Vec<Force>,
+    particles: Vec<Particle>,
+    restitution: f32, // normal speed kept after a bounce
+    friction: f32,    // fraction of tangential speed lost in a bounce
+    rng: Rng,
+}
+
+impl ParticleSystem {
+    pub(crate) fn new(seed: u64) -> Self {
+        ParticleSystem {
+            emitters: Vec::new(),
+            forces: Vec::new(),
+            particles: Vec::new(),
+            restitution: 0.5,
+            friction: 0.1,
+            rng: Rng::new(seed),
+        }
+    }
+
+    pub(crate) fn add_emitter(&mut self, emitter: Emitter) -> usize {
+        self.emitters.push(emitter);
+        self.emitters.len() - 1
+    }
+
+    pub(crate) fn emitter_mut(&mut self, index: usize) -> &mut Emitter { &mut self.emitters[index] }
+
+    pub(crate) fn add_force(&mut self, force: Force) {
+        self.forces.push(force);
+    }
+
+    pub(crate) fn set_restitution(&mut self, restitution: f32) {
+        self.restitution = restitution.clamp(0.0, 1.0);
+    }
+
+    pub(crate) fn set_friction(&mut self, friction: f32) {
+        self.friction = friction.clamp(0.0, 1.0);
+    }
+
+    pub(crate) fn particles(&self) -> &[Particle] { &self.particles }
+
+    // Emits, ages, integrates and collides. Each particle's motion over the step, lengthened by
+    // its radius, is tested as a segment against the colliders, so fast particles can't pass
+    // through thin surfaces and slow ones stop with their edge, not their centre, on them.
+    pub(crate) fn step(&mut self, dt: f32, colliders: &[Box<dyn Hittable>]) {
+        for emitter in &mut self.emitters {
+            emitter.emit(dt, &mut self.rng, &mut self.particles);
+        }
+
+        for p in &mut self.particles {
+            p.age += dt;
+            let acceleration = self.forces.iter().fold(Vec3::new(0.0, 0.0, 0.0), |a, f| a + f.acceleration(p));
+            p.velocity = p.velocity + acceleration * dt;
+            let start = p.position;
+            let end = start + p.velocity * dt;
+            if start == end { continue; }
+
+            // The particle's leading edge reaches a radius beyond its centre
+            let segment = Segment::new(start, end + (end - start).normalized() * p.radius);
+            let hit = colliders.iter()
+                .filter_map(|c| c.intersects_segment(&segment))
+                .min_by(|a, b| a.0.total_cmp(&b.0));
+            match hit {
+                Some((_, point, normal)) => {
+                    // Work with the side of the surface the particle came from
+                    let n = if normal.dot(&p.velocity) > 0.0 { -normal } else { normal };
+                    let normal_speed = p.velocity.dot(&n);
+                    let tangential = p.velocity - n * normal_speed;
+                    p.velocity = tangential * (1.0 - self.friction) - n * (normal_speed * self.restitution);
+                    p.position = point + n * (p.radius + SURFACE_OFFSET);
+                }
+                None => p.position = end,
+            }
+        }
+
+        self.particles.retain(|p| p.age < p.lifetime);
+    }
+
+    // Geometry for Camera::render_scene, one small sphere per particle
+    pub(crate) fn spheres(&self, material: Material) -> Vec<Box<dyn Hittable>> {
+        self.particles.iter()
+            .map(|p| Box::new(Sphere::new(p.radius, p.position, material)) as Box<dyn Hittable>)
+            .collect()
+    }
+
+    // Flat discs turned towards the camera, cheaper to hit than spheres
+    pub(crate) fn billboards(&self, eye: Vec3, material: Material) -> Vec<Box<dyn Hittable>> {
+        self.particles.iter()
+            .map(|p| Box::new(Disc::billboard(p.position, eye, p.radius, material)) as Box<dyn Hittable>)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::objects::infinite_plane::InfinitePlane;
+    use crate::ray::Ray;
+
+    fn fountain(rate: f32) -> ParticleSystem {
+        let mut system = ParticleSystem::new(1);
+        system.add_emitter(Emitter::new(
+            Vec3::new(0.0, 1.0, 0.0),
+            rate,
+            (1.0, 1.0),
+            VelocityDistribution::Cone { direction: Vec3::new(0.0, 1.0, 0.0), half_angle: 0.3, speed: (2.0, 3.0) },
+        ));
+        system
+    }
+
+    #[test]
+    fn emits_at_the_rate_and_expires_old_particles() {
+        let mut system = fountain(100.0);
+        for _ in 0..50 {
+            system.step(0.01, &[]);
+        }
+        assert!((49..=50).contains(&system.particles().len()), "{}", system.particles().len());
+        for _ in 0..100 {
+            system.step(0.01, &[]);
+        }
+        // Lifetime is one second, so only about the last second's worth is left
+        assert!((99..=101).contains(&system.particles().len()), "{}", system.particles().len());
+        assert!(system.particles().iter().all(|p| p.age < 1.0));
+    }
+
+    #[test]
+    fn same_seed_same_particles() {
+        let run = || {
+            let mut system = fountain(60.0);
+            system.add_force(Force::Gravity(Vec3::new(0.0, -9.81, 0.0)));
+            for _ in 0..30 {
+                system.step(1.0 / 60.0, &[]);
+            }
+            system.particles().to_vec()
+        };
+        assert_eq!(run(), run());
+    }
+
+    #[test]
+    fn forces_shape_the_motion() {
+        let particle = Particle {
+            position: Vec3::new(1.0, 0.0, 0.0),
+            velocity: Vec3::new(2.0, 0.0, 0.0),
+            age: 0.0,
+            lifetime: 1.0,
+            radius: 0.1,
+        };
+        assert_eq!(Force::Drag(0.5).acceleration(&particle), Vec3::new(-1.0, 0.0, 0.0));
+        let wind = Force::Wind { velocity: Vec3::new(0.0, 0.0, 2.0), coefficient: 1.0 };
+        assert_eq!(wind.acceleration(&particle), Vec3::new(-2.0, 0.0, 2.0));
+        // Counter-clockwise about +Y: at +X the swirl points towards -Z
+        let vortex = Force::Vortex { centre: Vec3::new(0.0, 5.0, 0.0), axis: Vec3::new(0.0, 1.0, 0.0), strength: 3.0 };
+        assert!((vortex.acceleration(&particle) - Vec3::new(0.0, 0.0, -3.0)).length() < 1e-5);
+    }
+
+    #[test]
+    fn particles_bounce_off_the_ground() {
+        let mut system = ParticleSystem::new(3);
+        system.set_restitution(0.5);
+        system.add_force(Force::Gravity(Vec3::new(0.0, -9.81, 0.0)));
+        system.add_emitter(Emitter::new(Vec3::new(0.0, 1.0, 0.0), 50.0, (5.0, 5.0), VelocityDistribution::Fixed(Vec3::new(0.0, -20.0, 0.0))));
+        let ground: Vec<Box<dyn Hittable>> = vec![Box::new(InfinitePlane::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0), Material::default()))];
+        let mut bounced = false;
+        for _ in 0..120 {
+            system.step(1.0 / 60.0, &ground);
+            assert!(system.particles().iter().all(|p| p.position.y() > 0.5 * p.radius));
+            bounced |= system.particles().iter().any(|p| p.velocity.y() > 0.0);
+        }
+        assert!(bounced);
+    }
+
+    #[test]
+    fn resting_particle_sits_on_its_edge() {
+        let mut system = ParticleSystem::new(4);
+        system.set_restitution(0.0);
+        system.add_force(Force::Gravity(Vec3::new(0.0, -9.81, 0.0)));
+        let mut emitter = Emitter::new(Vec3::new(0.0, 1.0, 0.0), 1.0, (10.0, 10.0), VelocityDistribution::Fixed(Vec3::new(0.0, 0.0, 0.0)));
+        emitter.set_particle_radius(0.2);
+        system.add_emitter(emitter);
+        let ground: Vec<Box<dyn Hittable>> = vec![Box::new(InfinitePlane::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0), Material::default()))];
+        system.step(1.0, &ground);
+        system.emitter_mut(0).set_rate(0.0);
+        for _ in 0..120 {
+            system.step(1.0 / 60.0, &ground);
+        }
+        let y = system.particles()[0].position.y();
+        assert!((y - 0.2).abs() < 2.0 * SURFACE_OFFSET, "rests at {}", y);
+    }
+
+    #[test]
+    fn renders_as_spheres_or_billboards() {
+        let mut system = ParticleSystem::new(5);
+        system.add_emitter(Emitter::new(Vec3::new(0.0, 0.0, 10.0), 1.0, (10.0, 10.0), VelocityDistribution::Fixed(Vec3::new(0.0, 0.0, 0.0))));
+        system.step(1.0, &[]);
+        let eye = Vec3::new(0.0, 0.0, 0.0);
+        let ray = Ray::new(eye, Vec3::new(0.0, 0.0, 1.0));
+        let spheres = system.spheres(Material::default());
+        let billboards = system.billboards(eye, Material::default());
+        assert_eq!(spheres.len(), 1);
+        assert!((spheres[0].intersects_ray(&ray).unwrap().0 - 9.95).abs() < 1e-4);
+        assert!((billboards[0].intersects_ray(&ray).unwrap().0 - 10.0).abs() < 1e-4);
+    }
+}
@@ -0,0 +1,448 @@
+// filepath: src/physics/recording.rs
+#![allow(dead_code, reason = "recording and replay are tools for debugging runs, not used by the demo")]
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use crate::physics::world::World;
+use crate::quat::Quat;
+use crate::vec3::Vec3;
+
+// File layout, all little endian:
+//   magic "PHYR", version u32, body count u32, timestep f32, frame count u32
+//   per frame: time f32, then per body 13 f32 (position, orientation wxyz, velocity,
+//   angular velocity) and a flags byte
+// Floats are stored as their exact bits so a replay reproduces the run bit for bit.
+const MAGIC: &[u8; 4] = b"PHYR";
+const VERSION: u32 = 1;
+const SLEEPING: u8 = 1;
+
+// One body at the end of a step
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) struct BodyState {
+    pub position: Vec3,
+    pub orientation: Quat,
+    pub velocity: Vec3,
+    pub angular_velocity: Vec3,
+    pub sleeping: bool,
+}
+
+impl BodyState {
+    // Blend towards `other`, for playback between recorded steps
+    fn lerp(&self, other: &BodyState, t: f32) -> BodyState {
+        let mix = |a: Vec3, b: Vec3| a + (b - a) * t;
+        BodyState {
+            position: mix(self.position, other.position),
+            orientation: self.orientation.slerp(&other.orientation, t),
+            velocity: mix(self.velocity, other.velocity),
+            angular_velocity: mix(self.angular_velocity, other.angular_velocity),
+            sleeping: if t < 1.0 { self.sleeping } else { other.sleeping },
+        }
+    }
+
+    // Exact comparison, -0.0 and 0.0 differ and NaN equals itself
+    fn bits(&self) -> [u32; 13] {
+        let (p, q, v, w) = (self.position, self.orientation, self.velocity, self.angular_velocity);
+        [p.x(), p.y(), p.z(), q.w, q.x, q.y, q.z, v.x(), v.y(), v.z(), w.x(), w.y(), w.z()].map(f32::to_bits)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Frame {
+    pub time: f32,
+    pub bodies: Vec<BodyState>,
+}
+
+// Every body's state after every step of a world. Frame 0 is the state recording started from.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Recording {
+    timestep: f32,
+    body_count: usize,
+    frames: Vec<Frame>,
+}
+
+impl Recording {
+    pub(crate) fn new(world: &World) -> Self {
+        let mut recording = Recording { timestep: world.timestep(), body_count: world.bodies().len(), frames: Vec::new() };
+        recording.record(world);
+        recording
+    }
+
+    // Call after each World::step
+    pub(crate) fn record(&mut self, world: &World) {
+        assert_eq!(world.bodies().len(), self.body_count, "bodies were added or removed while recording");
+        let bodies = world.bodies().iter()
+            .map(|b| BodyState {
+                position: b.position,
+                orientation: b.orientation,
+                velocity: b.velocity,
+                angular_velocity: b.angular_velocity,
+                sleeping: b.is_sleeping(),
+            })
+            .collect();
+        self.frames.push(Frame { time: world.time(), bodies });
+    }
+
+    pub(crate) fn timestep(&self) -> f32 { self.timestep }
+    pub(crate) fn body_count(&self) -> usize { self.body_count }
+    pub(crate) fn frames(&self) -> &[Frame] { &self.frames }
+    pub(crate) fn frame(&self, index: usize) -> &Frame { &self.frames[index] }
+    pub(crate) fn len(&self) -> usize { self.frames.len() }
+    pub(crate) fn is_empty(&self) -> bool { self.frames.is_empty() }
+
+    // Length of the recording in simulated seconds
+    pub(crate) fn duration(&self) -> f32 {
+        self.frames.len().saturating_sub(1) as f32 * self.timestep
+    }
+
+    // First (frame, body) whose state differs in any bit, for tracking down where two runs split
+    pub(crate) fn first_divergence(&self, other: &Recording) -> Option<(usize, usize)> {
+        for (index, (a, b)) in self.frames.iter().zip(&other.frames).enumerate() {
+            if a.time.to_bits() != b.time.to_bits() || a.bodies.len() != b.bodies.len() {
+                return Some((index, 0));
+            }
+            for (body, (sa, sb)) in a.bodies.iter().zip(&b.bodies).enumerate() {
+                if sa.bits() != sb.bits() || sa.sleeping != sb.sleeping {
+                    return Some((index, body));
+                }
+            }
+        }
+        if self.frames.len() != other.frames.len() {
+            return Some((self.frames.len().min(other.frames.len()), 0));
+        }
+        None
+    }
+
+    pub(crate) fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_to(&mut writer)?;
+        writer.flush()
+    }
+
+    pub(crate) fn load(path: impl AsRef<Path>) -> io::Result<Recording> {
+        Recording::read_from(&mut BufReader::new(File::open(path)?))
+    }
+
+    pub(crate) fn write_to(&self, writer: &mut impl Write) -> io::Result<()> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&VERSION.to_le_bytes())?;
+        writer.write_all(&(self.body_count as u32).to_le_bytes())?;
+        writer.write_all(&self.timestep.to_le_bytes())?;
+        writer.write_all(&(self.frames.len() as u32).to_le_bytes())?;
+        for frame in &self.frames {
+            writer.write_all(&frame.time.to_le_bytes())?;
+            for body in &frame.bodies {
+                for bits in body.bits() {
+                    writer.write_all(&bits.to_le_bytes())?;
+                }
+                writer.write_all(&[if body.sleeping { SLEEPING } else { 0 }])?;
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn read_from(reader: &mut impl Read) -> io::Result<Recording> {
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "not a physics recording"));
+        }
+        let version = read_u32(reader)?;
+        if version != VERSION {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("unsupported recording version {}", version)));
+        }
+        let body_count = read_u32(reader)? as usize;
+        let timestep = read_f32(reader)?;
+        let frame_count = read_u32(reader)? as usize;
+        // Recording::new always stores the starting state, so a recording is never empty
+        if frame_count == 0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "recording has no frames"));
+        }
+        // The counts come from the file, so nothing is reserved up front: a corrupt header runs
+        // out of data and fails instead of asking for gigabytes
+        let truncated = |e: io::Error| if e.kind() == io::ErrorKind::UnexpectedEof {
+            io::Error::new(io::ErrorKind::InvalidData, "recording is shorter than its header says")
+        } else {
+            e
+        };
+        let mut frames = Vec::new();
+        for _ in 0..frame_count {
+            let time = read_f32(reader).map_err(truncated)?;
+            let mut bodies = Vec::new();
+            for _ in 0..body_count {
+                let mut f = [0.0f32; 13];
+                for value in &mut f {
+                    *value = read_f32(reader).map_err(truncated)?;
+                }
+                let mut flags = [0u8; 1];
+                reader.read_exact(&mut flags).map_err(truncated)?;
+                bodies.push(BodyState {
+                    position: Vec3::new(f[0], f[1], f[2]),
+                    orientation: Quat::new(f[3], f[4], f[5], f[6]),
+                    velocity: Vec3::new(f[7], f[8], f[9]),
+                    angular_velocity: Vec3::new(f[10], f[11], f[12]),
+                    sleeping: flags[0] & SLEEPING != 0,
+                });
+            }
+            frames.push(Frame { time, bodies });
+        }
+        Ok(Recording { timestep, body_count, frames })
+    }
+}
+
+fn read_u32(reader: &mut impl Read) -> io::Result<u32> {
+    let mut bytes = [0u8; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_f32(reader: &mut impl Read) -> io::Result<f32> {
+    read_u32(reader).map(f32::from_bits)
+}
+
+// Plays a recording back into a world. The playhead is in simulated seconds and moves at any
+// speed, backwards included; between recorded steps body states are interpolated.
+pub(crate) struct Replay {
+    recording: Recording,
+    playhead: f32,
+    speed: f32,
+    looping: bool,
+}
+
+impl Replay {
+    pub(crate) fn new(recording: Recording) -> Self {
+        assert!(!recording.is_empty(), "nothing to replay");
+        Replay { recording, playhead: 0.0, speed: 1.0, looping: false }
+    }
+
+    pub(crate) fn recording(&self) -> &Recording { &self.recording }
+    pub(crate) fn playhead(&self) -> f32 { self.playhead }
+    pub(crate) fn speed(&self) -> f32 { self.speed }
+
+    // 1 is real time, 0.25 slow motion, negative plays backwards
+    pub(crate) fn set_speed(&mut self, speed: f32) {
+        self.speed = speed;
+    }
+
+    pub(crate) fn set_looping(&mut self, looping: bool) {
+        self.looping = looping;
+    }
+
+    // Scrub to a time, clamped to the recording
+    pub(crate) fn seek(&mut self, time: f32) {
+        self.playhead = time.clamp(0.0, self.recording.duration());
+    }
+
+    pub(crate) fn seek_frame(&mut self, frame: usize) {
+        self.seek(frame.min(self.recording.len() - 1) as f32 * self.recording.timestep);
+    }
+
+    // Recorded frame at or just before the playhead
+    pub(crate) fn frame(&self) -> usize {
+        ((self.playhead / self.recording.timestep + 1e-4).floor() as usize).min(self.recording.len() - 1)
+    }
+
+    pub(crate) fn is_finished(&self) -> bool {
+        !self.looping && if self.speed >= 0.0 { self.playhead >= self.recording.duration() } else { self.playhead <= 0.0 }
+    }
+
+    // Moves the playhead by wall clock `elapsed` seconds scaled by the speed
+    pub(crate) fn advance(&mut self, elapsed: f32) {
+        let duration = self.recording.duration();
+        let playhead = self.playhead + elapsed * self.speed;
+        self.playhead = if self.looping && duration > 0.0 { playhead.rem_euclid(duration) } else { playhead.clamp(0.0, duration) };
+    }
+
+    // Share of the way from the current frame to the next, 0 exactly on a recorded step
+    fn blend(&self, frame: usize) -> f32 {
+        let t = self.playhead / self.recording.timestep - frame as f32;
+        if t <= 1e-4 || frame + 1 >= self.recording.len() { 0.0 } else { t }
+    }
+
+    // Body states at the playhead. Exactly on a recorded step this is the recorded state.
+    pub(crate) fn states(&self) -> Vec<BodyState> {
+        let frame = self.frame();
+        let states = &self.recording.frames[frame].bodies;
+        let t = self.blend(frame);
+        if t == 0.0 {
+            return states.clone();
+        }
+        let next = &self.recording.frames[frame + 1].bodies;
+        states.iter().zip(next).map(|(a, b)| a.lerp(b, t)).collect()
+    }
+
+    // World time at the playhead, as World::time read when the frame was recorded
+    pub(crate) fn time(&self) -> f32 {
+        let frame = self.frame();
+        self.recording.frames[frame].time + self.blend(frame) * self.recording.timestep
+    }
+
+    // Poses the world's bodies as they were at the playhead and sets its clock to match. This is
+    // for viewing only: rest timers and the solver's warm-start impulses aren't recorded, so
+    // stepping the world from a scrubbed pose doesn't follow the recorded run.
+    pub(crate) fn apply(&self, world: &mut World) {
+        assert_eq!(world.bodies().len(), self.recording.body_count, "recording is of a different world");
+        world.set_time(self.time());
+        for (body, state) in world.bodies_mut().iter_mut().zip(self.states()) {
+            if state.sleeping { body.sleep() } else { body.wake() }
+            body.position = state.position;
+            body.orientation = state.orientation;
+            body.velocity = state.velocity;
+            body.angular_velocity = state.angular_velocity;
+            body.sync_shape();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Material;
+    use crate::objects::infinite_plane::InfinitePlane;
+    use crate::objects::sphere::Sphere;
+    use crate::physics::body::{RigidBody, Shape};
+    use crate::rng::Rng;
+
+    // A pile of balls dropped into a pit, enough contacts and sleeping to exercise every stage
+    fn pit(seed: u64) -> World {
+        let mut world = World::new(Vec3::new(0.0, -9.81, 0.0));
+        let mut rng = Rng::new(seed);
+        let walls = [
+            (Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0)),
+            (Vec3::new(-2.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0)),
+            (Vec3::new(2.0, 0.0, 0.0), Vec3::new(-1.0, 0.0, 0.0)),
+            (Vec3::new(0.0, 0.0, -2.0), Vec3::new(0.0, 0.0, 1.0)),
+            (Vec3::new(0.0, 0.0, 2.0), Vec3::new(0.0, 0.0, -1.0)),
+        ];
+        for (position, normal) in walls {
+            world.add_body(RigidBody::new_static(Shape::Plane(InfinitePlane::new(position, normal, Material::default()))));
+        }
+        for i in 0..30 {
+            let position = Vec3::new(rng.range(-1.5, 1.5), 1.0 + i as f32 * 0.4, rng.range(-1.5, 1.5));
+            let mut body = RigidBody::new(Shape::Sphere(Sphere::new(0.3, position, Material::default())), 1.0);
+            body.velocity = Vec3::new(rng.range(-1.0, 1.0), 0.0, rng.range(-1.0, 1.0));
+            world.add_body(body);
+        }
+        world
+    }
+
+    fn record(mut world: World, steps: usize) -> Recording {
+        let mut recording = Recording::new(&world);
+        for _ in 0..steps {
+            world.step();
+            recording.record(&world);
+        }
+        recording
+    }
+
+    #[test]
+    fn same_inputs_record_identical_bits() {
+        let a = record(pit(1), 240);
+        let b = record(pit(1), 240);
+        assert_eq!(a.first_divergence(&b), None);
+        let mut bytes_a = Vec::new();
+        let mut bytes_b = Vec::new();
+        a.write_to(&mut bytes_a).unwrap();
+        b.write_to(&mut bytes_b).unwrap();
+        assert_eq!(bytes_a, bytes_b);
+        // A different seed splits off straight away
+        let c = record(pit(2), 240);
+        assert_eq!(a.first_divergence(&c), Some((0, 5)));
+    }
+
+    #[test]
+    fn round_trips_through_a_file() {
+        let recording = record(pit(3), 60);
+        let path = std::env::temp_dir().join(format!("recording_{}.phyr", std::process::id()));
+        recording.save(&path).unwrap();
+        let loaded = Recording::load(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(loaded.first_divergence(&recording), None);
+        assert_eq!(loaded.len(), 61);
+        // 5 header words, then a time and 13 floats plus flags per body per frame
+        let mut bytes = Vec::new();
+        recording.write_to(&mut bytes).unwrap();
+        assert_eq!(bytes.len(), 20 + 61 * (4 + 35 * 53));
+        assert!(Recording::read_from(&mut &b"nope"[..]).is_err());
+        assert!(Recording::read_from(&mut &bytes[..bytes.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn garbage_header_counts_fail_without_allocating() {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&VERSION.to_le_bytes());
+        bytes.extend_from_slice(&u32::MAX.to_le_bytes()); // bodies
+        bytes.extend_from_slice(&(1.0f32 / 60.0).to_le_bytes());
+        bytes.extend_from_slice(&u32::MAX.to_le_bytes()); // frames
+        bytes.extend_from_slice(&[0; 9]);
+        let error = Recording::read_from(&mut &bytes[..]).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        // A header with no frames at all, which Replay couldn't play
+        let mut empty = bytes[..16].to_vec();
+        empty.extend_from_slice(&0u32.to_le_bytes());
+        let error = Recording::read_from(&mut &empty[..]).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn replay_scrubs_and_plays_at_any_speed() {
+        let recording = record(pit(4), 120);
+        let dt = recording.timestep();
+        let mut world = pit(4);
+        let mut replay = Replay::new(recording.clone());
+
+        // Scrubbing lands exactly on the recorded state
+        replay.seek_frame(90);
+        replay.apply(&mut world);
+        assert_eq!(replay.frame(), 90);
+        for (body, state) in world.bodies().iter().zip(&recording.frame(90).bodies) {
+            assert_eq!(body.position, state.position);
+        }
+
+        // Half speed takes two wall clock steps per recorded step and passes through the middle
+        replay.seek_frame(10);
+        replay.set_speed(0.5);
+        replay.advance(dt);
+        assert_eq!(replay.frame(), 10);
+        let (a, b) = (recording.frame(10).bodies[5].position, recording.frame(11).bodies[5].position);
+        assert!((replay.states()[5].position - (a + b) * 0.5).length() < 1e-5);
+        replay.advance(dt);
+        assert_eq!(replay.frame(), 11);
+
+        // Backwards and fast, stopping at the start
+        replay.set_speed(-4.0);
+        replay.advance(dt);
+        assert_eq!(replay.frame(), 7);
+        replay.advance(1.0);
+        assert_eq!(replay.frame(), 0);
+        assert!(replay.is_finished());
+
+        // Looping wraps around instead of stopping
+        replay.set_speed(1.0);
+        replay.set_looping(true);
+        replay.seek(recording.duration() - 0.5 * dt);
+        replay.advance(dt);
+        assert_eq!(replay.frame(), 0);
+        assert!(!replay.is_finished());
+    }
+
+    #[test]
+    fn applying_a_scrub_is_for_viewing_only() {
+        let recording = record(pit(5), 240);
+        let mut world = pit(5);
+        let mut replay = Replay::new(recording.clone());
+        replay.seek_frame(190);
+        replay.apply(&mut world);
+        // The clock shows the recorded time, so anything keyed on it (a timeline) lines up
+        assert_eq!(world.time().to_bits(), recording.frame(190).time.to_bits());
+        replay.seek(replay.playhead() + 0.5 * recording.timestep());
+        replay.apply(&mut world);
+        assert!((world.time() - recording.frame(190).time - 0.5 * recording.timestep()).abs() < 1e-5);
+
+        // Deep in the pile, stepping on without the warm-start impulses doesn't retrace the run
+        replay.seek_frame(190);
+        replay.apply(&mut world);
+        world.step();
+        assert_ne!(Recording::new(&world).frame(0).bodies, recording.frame(191).bodies);
+    }
+}
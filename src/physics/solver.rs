@@ -0,0 +1,288 @@
+// filepath: src/physics/solver.rs
+use std::collections::HashMap;
+use crate::physics::body::RigidBody;
+use crate::physics::contact::Contact;
+use crate::physics::joint::Joint;
+use crate::vec3::Vec3;
+
+// Passes over all constraint rows per step, more converge stiffer chains and stacks
+const DEFAULT_ITERATIONS: usize = 10;
+// Closing speeds below this don't bounce, so resting bodies settle instead of vibrating
+const RESTITUTION_THRESHOLD: f32 = 0.5;
+
+// Velocity-space Jacobian of a one dimensional constraint, the constraint speed is
+// lin_a·va + ang_a·wa + lin_b·vb + ang_b·wb
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) struct Jacobian {
+    pub lin_a: Vec3,
+    pub ang_a: Vec3,
+    pub lin_b: Vec3,
+    pub ang_b: Vec3,
+}
+
+impl Jacobian {
+    // Relative velocity along `direction` of two points, given as offsets from each body's centre
+    pub(crate) fn linear(ra: Vec3, rb: Vec3, direction: Vec3) -> Self {
+        Jacobian {
+            lin_a: -direction,
+            ang_a: -ra.cross(&direction),
+            lin_b: direction,
+            ang_b: rb.cross(&direction),
+        }
+    }
+
+    // Relative angular velocity about `axis`
+    pub(crate) fn angular(axis: Vec3) -> Self {
+        let zero = Vec3::new(0.0, 0.0, 0.0);
+        Jacobian { lin_a: zero, ang_a: -axis, lin_b: zero, ang_b: axis }
+    }
+}
+
+// One scalar constraint between two bodies. The solver drives its speed towards -bias while
+// keeping the accumulated impulse within [lower, upper].
+#[derive(Debug, Clone)]
+pub(crate) struct Row {
+    a: usize,
+    b: usize,
+    jacobian: Jacobian,
+    // Velocity change per unit impulse, M^-1 J^T split per body
+    dv_a: Vec3,
+    dw_a: Vec3,
+    dv_b: Vec3,
+    dw_b: Vec3,
+    inverse_k: f32, // 1 / (J M^-1 J^T), zero if neither body can move
+    bias: f32,
+    lower: f32,
+    upper: f32,
+    friction: Option<(usize, f32)>, // (normal row, mu), bounds follow that row's impulse
+    impulse: f32,
+}
+
+impl Row {
+    pub(crate) fn new(a: usize, b: usize, bodies: &[RigidBody], jacobian: Jacobian, bias: f32) -> Self {
+        let (body_a, body_b) = (&bodies[a], &bodies[b]);
+        let dv_a = jacobian.lin_a * body_a.inverse_mass();
+        let dw_a = body_a.inverse_inertia_world() * jacobian.ang_a;
+        let dv_b = jacobian.lin_b * body_b.inverse_mass();
+        let dw_b = body_b.inverse_inertia_world() * jacobian.ang_b;
+        let k = jacobian.lin_a.dot(&dv_a) + jacobian.ang_a.dot(&dw_a) + jacobian.lin_b.dot(&dv_b) + jacobian.ang_b.dot(&dw_b);
+        Row {
+            a,
+            b,
+            jacobian,
+            dv_a,
+            dw_a,
+            dv_b,
+            dw_b,
+            inverse_k: if k > 1e-12 { 1.0 / k } else { 0.0 },
+            bias,
+            lower: f32::NEG_INFINITY,
+            upper: f32::INFINITY,
+            friction: None,
+            impulse: 0.0,
+        }
+    }
+
+    pub(crate) fn with_bounds(mut self, lower: f32, upper: f32) -> Self {
+        self.lower = lower;
+        self.upper = upper;
+        self
+    }
+
+    fn with_friction(mut self, normal_row: usize, mu: f32) -> Self {
+        self.friction = Some((normal_row, mu));
+        self
+    }
+
+    pub(crate) fn impulse(&self) -> f32 { self.impulse }
+
+    fn speed(&self, bodies: &[RigidBody]) -> f32 {
+        let (a, b) = (&bodies[self.a], &bodies[self.b]);
+        let j = &self.jacobian;
+        j.lin_a.dot(&a.velocity) + j.ang_a.dot(&a.angular_velocity) + j.lin_b.dot(&b.velocity) + j.ang_b.dot(&b.angular_velocity)
+    }
+
+    fn apply(&self, bodies: &mut [RigidBody], impulse: f32) {
+        let a = &mut bodies[self.a];
+        a.velocity = a.velocity + self.dv_a * impulse;
+        a.angular_velocity = a.angular_velocity + self.dw_a * impulse;
+        let b = &mut bodies[self.b];
+        b.velocity = b.velocity + self.dv_b * impulse;
+        b.angular_velocity = b.angular_velocity + self.dw_b * impulse;
+    }
+}
+
+// Projected Gauss-Seidel / sequential impulse solver over contacts and joints. Accumulated
+// impulses are kept between steps and applied up front (warm starting), so resting stacks and
+// chains start close to the answer instead of rebuilding it from zero every step.
+#[derive(Debug)]
+pub(crate) struct Solver {
+    iterations: usize,
+    warm_starting: bool,
+    // Last step's (normal, tangent, bitangent) impulses per touching pair
+    contact_impulses: HashMap<(usize, usize), [f32; 3]>,
+}
+
+impl Default for Solver {
+    fn default() -> Self {
+        Solver { iterations: DEFAULT_ITERATIONS, warm_starting: true, contact_impulses: HashMap::new() }
+    }
+}
+
+impl Solver {
+    pub(crate) fn set_iterations(&mut self, iterations: usize) {
+        self.iterations = iterations.max(1);
+    }
+
+    pub(crate) fn set_warm_starting(&mut self, warm_starting: bool) {
+        self.warm_starting = warm_starting;
+        if !warm_starting {
+            self.contact_impulses.clear();
+        }
+    }
+
+    // Accumulated normal impulse on a touching pair during the last step
+    #[cfg(test)]
+    pub(crate) fn contact_impulse(&self, a: usize, b: usize) -> Option<f32> {
+        self.contact_impulses.get(&(a.min(b), a.max(b))).map(|i| i[0])
+    }
+
+    pub(crate) fn solve(&mut self, bodies: &mut [RigidBody], contacts: &[Contact], joints: &mut [Joint], dt: f32) {
+        let mut rows = Vec::new();
+
+        let mut joint_rows = Vec::with_capacity(joints.len());
+        for joint in joints.iter() {
+            // Joints between sleeping or static bodies are skipped, keeping their impulses for when they wake
+            let (a, b) = joint.bodies();
+            if !bodies[a].is_active() && !bodies[b].is_active() {
+                joint_rows.push(None);
+                continue;
+            }
+            let start = rows.len();
+            rows.extend(joint.rows(bodies, dt));
+            if self.warm_starting && joint.impulses().len() == rows.len() - start {
+                for (row, &impulse) in rows[start..].iter_mut().zip(joint.impulses()) {
+                    row.impulse = impulse;
+                }
+            }
+            joint_rows.push(Some(start..rows.len()));
+        }
+
+        let contacts_start = rows.len();
+        for contact in contacts {
+            let (a, b) = (contact.a, contact.b);
+            let geometry = &contact.geometry;
+            let n = geometry.normal;
+            let ra = geometry.point - bodies[a].position;
+            let rb = geometry.point - bodies[b].position;
+
+            // Bounce target from the closing speed before any impulses this step
+            let closing = (bodies[b].velocity_at(geometry.point) - bodies[a].velocity_at(geometry.point)).dot(&n);
+            let restitution = if -closing < RESTITUTION_THRESHOLD { 0.0 } else { bodies[a].restitution().max(bodies[b].restitution()) };
+            let normal_row = rows.len();
+            rows.push(Row::new(a, b, bodies, Jacobian::linear(ra, rb, n), restitution * closing).with_bounds(0.0, f32::INFINITY));
+
+            // Box friction along a tangent basis that only depends on the normal, so cached impulses line up
+            let mu = (bodies[a].friction() * bodies[b].friction()).sqrt();
+            let (tangent, bitangent) = n.orthonormal_basis();
+            for t in [tangent, bitangent] {
+                rows.push(Row::new(a, b, bodies, Jacobian::linear(ra, rb, t), 0.0).with_friction(normal_row, mu));
+            }
+
+            if self.warm_starting && let Some(cached) = self.contact_impulses.get(&(a.min(b), a.max(b))) {
+                for (row, &impulse) in rows[normal_row..].iter_mut().zip(cached) {
+                    row.impulse = impulse;
+                }
+            }
+        }
+
+        for row in &rows {
+            if row.impulse != 0.0 {
+                row.apply(bodies, row.impulse);
+            }
+        }
+
+        for _ in 0..self.iterations {
+            for i in 0..rows.len() {
+                let (lower, upper) = match rows[i].friction {
+                    Some((normal_row, mu)) => {
+                        let limit = mu * rows[normal_row].impulse;
+                        (-limit, limit)
+                    }
+                    None => (rows[i].lower, rows[i].upper),
+                };
+                let row = &mut rows[i];
+                let delta = -row.inverse_k * (row.speed(bodies) + row.bias);
+                let previous = row.impulse;
+                row.impulse = (previous + delta).clamp(lower, upper);
+                row.apply(bodies, row.impulse - previous);
+            }
+        }
+
+        for (joint, range) in joints.iter_mut().zip(joint_rows) {
+            let Some(range) = range else { continue };
+            joint.set_impulses(rows[range].iter().map(Row::impulse).collect());
+        }
+        self.contact_impulses.clear();
+        for (contact, impulses) in contacts.iter().zip(rows[contacts_start..].chunks(3)) {
+            let key = (contact.a.min(contact.b), contact.a.max(contact.b));
+            self.contact_impulses.insert(key, [impulses[0].impulse, impulses[1].impulse, impulses[2].impulse]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Material;
+    use crate::objects::infinite_plane::InfinitePlane;
+    use crate::objects::sphere::Sphere;
+    use crate::physics::body::Shape;
+    use crate::physics::contact::ContactGeometry;
+
+    fn resting_pair() -> (Vec<RigidBody>, Vec<Contact>) {
+        let ground = RigidBody::new_static(Shape::Plane(InfinitePlane::new(
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            Material::default(),
+        )));
+        let mut ball = RigidBody::new(Shape::Sphere(Sphere::new(0.5, Vec3::new(0.0, 0.5, 0.0), Material::default())), 2.0);
+        ball.velocity = Vec3::new(0.0, -0.1, 0.0);
+        let contact = Contact {
+            a: 0,
+            b: 1,
+            geometry: ContactGeometry { point: Vec3::new(0.0, 0.0, 0.0), normal: Vec3::new(0.0, 1.0, 0.0), penetration: 0.0 },
+        };
+        (vec![ground, ball], vec![contact])
+    }
+
+    #[test]
+    fn contact_stops_the_approach_without_pulling() {
+        let (mut bodies, contacts) = resting_pair();
+        let mut solver = Solver::default();
+        solver.solve(&mut bodies, &contacts, &mut [], 0.01);
+        assert!(bodies[1].velocity.y().abs() < 1e-6);
+        // Impulse = m * dv
+        assert!((solver.contact_impulse(0, 1).unwrap() - 0.2).abs() < 1e-5);
+
+        // Already separating: the contact can only push, so nothing changes
+        bodies[1].velocity = Vec3::new(0.0, 1.0, 0.0);
+        solver.set_warm_starting(false);
+        solver.solve(&mut bodies, &contacts, &mut [], 0.01);
+        assert!((bodies[1].velocity.y() - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn warm_start_reapplies_the_last_impulse() {
+        let (mut bodies, contacts) = resting_pair();
+        let mut solver = Solver::default();
+        solver.solve(&mut bodies, &contacts, &mut [], 0.01);
+
+        // Same approach again: the cached impulse alone cancels it, so one iteration is exact
+        solver.set_iterations(1);
+        bodies[1].velocity = Vec3::new(0.0, -0.1, 0.0);
+        solver.solve(&mut bodies, &contacts, &mut [], 0.01);
+        assert!(bodies[1].velocity.y().abs() < 1e-6);
+        assert!((solver.contact_impulse(0, 1).unwrap() - 0.2).abs() < 1e-5);
+    }
+}
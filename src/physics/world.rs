@@ -0,0 +1,525 @@
+// filepath: src/physics/world.rs
+use std::collections::HashSet;
+use crate::aabb::Aabb;
+use crate::objects::hittable::Hittable;
+use crate::objects::moving::Moving;
+use crate::physics::body::{RigidBody, Shape};
+use crate::physics::broad_phase::{BroadPhase, SweepAndPrune};
+use crate::physics::ccd::sweep_sphere;
+use crate::physics::contact::{collide, correct_position, pair_mut, Contact};
+use crate::physics::integrator::{integrate, Integrator};
+use crate::physics::island::{islands, update_sleep};
+use crate::physics::joint::{Joint, JointKind};
+use crate::physics::solver::Solver;
+use crate::vec3::Vec3;
+
+// Upper bound on fixed steps per advance() call, so a long frame can't snowball into longer ones
+const MAX_STEPS_PER_ADVANCE: usize = 16;
+// Upper bound on the substeps a bullet body is split into per step
+const MAX_BULLET_SUBSTEPS: usize = 16;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub(crate) struct BodyHandle(usize);
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub(crate) struct JointHandle(usize);
+
+// Rigid body simulation stepped at a fixed timestep
+pub(crate) struct World {
+    bodies: Vec<RigidBody>,
+    gravity: Vec3,
+    timestep: f32,
+    integrator: Integrator,
+    accumulator: f32,
+    time: f32,
+    contacts: Vec<Contact>,
+    joints: Vec<Joint>,
+    islands: Vec<Vec<usize>>,
+    sleeping: bool,
+    broad_phase: Box<dyn BroadPhase>,
+    solver: Solver,
+}
+
+impl World {
+    pub(crate) fn new(gravity: Vec3) -> Self {
+        World {
+            bodies: Vec::new(),
+            gravity,
+            timestep: 1.0 / 120.0,
+            integrator: Integrator::SemiImplicitEuler,
+            accumulator: 0.0,
+            time: 0.0,
+            contacts: Vec::new(),
+            joints: Vec::new(),
+            islands: Vec::new(),
+            sleeping: true,
+            broad_phase: Box::new(SweepAndPrune::default()),
+            solver: Solver::default(),
+        }
+    }
+
+    #[allow(dead_code, reason = "the demo is happy with the default 120 steps per second")]
+    pub(crate) fn set_timestep(&mut self, timestep: f32) {
+        assert!(timestep > 0.0, "timestep must be positive");
+        self.timestep = timestep;
+    }
+
+    #[allow(dead_code, reason = "only tests compare integrators so far")]
+    pub(crate) fn set_integrator(&mut self, integrator: Integrator) {
+        self.integrator = integrator;
+    }
+
+    #[allow(dead_code, reason = "the demo keeps the default sweep and prune")]
+    pub(crate) fn set_broad_phase(&mut self, broad_phase: Box<dyn BroadPhase>) {
+        self.broad_phase = broad_phase;
+    }
+
+    // Velocity passes over all contacts and joints per step
+    #[allow(dead_code, reason = "trades accuracy for speed in big scenes; the demo's one ball is cheap either way")]
+    pub(crate) fn set_solver_iterations(&mut self, iterations: usize) {
+        self.solver.set_iterations(iterations);
+    }
+
+    #[allow(dead_code, reason = "switching it off is for comparing solver convergence, as the joint tests do")]
+    pub(crate) fn set_warm_starting(&mut self, warm_starting: bool) {
+        self.solver.set_warm_starting(warm_starting);
+    }
+
+    // Lets islands that have come to rest sleep until something disturbs them, on by default
+    #[allow(dead_code, reason = "the demo wants its ball to settle, so it leaves sleeping on")]
+    pub(crate) fn set_sleeping(&mut self, sleeping: bool) {
+        self.sleeping = sleeping;
+        if !sleeping {
+            for body in &mut self.bodies {
+                body.wake();
+            }
+        }
+    }
+
+    // Wakes every dynamic body when gravity changes, or resting ones would float on
+    #[allow(dead_code, reason = "the demo's gravity is fixed when the world is made")]
+    pub(crate) fn set_gravity(&mut self, gravity: Vec3) {
+        if gravity == self.gravity { return; }
+        self.gravity = gravity;
+        for body in self.bodies.iter_mut().filter(|b| !b.is_static()) {
+            body.wake();
+        }
+    }
+
+    pub(crate) fn timestep(&self) -> f32 { self.timestep }
+    pub(crate) fn time(&self) -> f32 { self.time }
+
+    // Moves the clock without stepping, e.g. to show a replayed pose at its recorded time
+    pub(crate) fn set_time(&mut self, time: f32) {
+        self.time = time;
+    }
+
+    pub(crate) fn add_body(&mut self, body: RigidBody) -> BodyHandle {
+        self.bodies.push(body);
+        BodyHandle(self.bodies.len() - 1)
+    }
+
+    #[cfg(test)]
+    pub(crate) fn body(&self, handle: BodyHandle) -> &RigidBody { &self.bodies[handle.0] }
+    pub(crate) fn body_mut(&mut self, handle: BodyHandle) -> &mut RigidBody { &mut self.bodies[handle.0] }
+    pub(crate) fn bodies(&self) -> &[RigidBody] { &self.bodies }
+    pub(crate) fn bodies_mut(&mut self) -> &mut [RigidBody] { &mut self.bodies }
+
+    // Anchors are world space points on each body in their current poses. Jointed bodies don't collide.
+    #[allow(dead_code, reason = "the demo has a lone ball, there is nothing to join it to")]
+    pub(crate) fn add_joint(&mut self, a: BodyHandle, b: BodyHandle, kind: JointKind, anchor_a: Vec3, anchor_b: Vec3) -> JointHandle {
+        self.joints.push(Joint::new(kind, &self.bodies, a.0, b.0, anchor_a, anchor_b));
+        JointHandle(self.joints.len() - 1)
+    }
+
+    #[cfg(test)]
+    pub(crate) fn joints(&self) -> &[Joint] { &self.joints }
+    // Contacts found during the last step
+    #[cfg(test)]
+    pub(crate) fn contacts(&self) -> &[Contact] { &self.contacts }
+
+    // One fixed step: integrate, find contacts, solve contacts and joints with impulses, then
+    // update which islands are asleep. Sleeping bodies are skipped until an island they join wakes.
+    pub(crate) fn step(&mut self) {
+        let dt = self.timestep;
+        for body in &mut self.bodies {
+            if body.is_bullet() || body.is_sleeping() { continue; }
+            integrate(body, self.gravity, dt, self.integrator);
+            body.clear_forces();
+            body.sync_shape();
+        }
+        // Bullets go last so they are swept against where everything else ended up
+        let jointed = self.jointed_pairs();
+        for index in 0..self.bodies.len() {
+            if self.bodies[index].is_bullet() && !self.bodies[index].is_sleeping() {
+                self.integrate_bullet(index, dt, &jointed);
+            }
+        }
+
+        self.contacts = self.find_contacts();
+        self.solver.solve(&mut self.bodies, &self.contacts, &mut self.joints, dt);
+        for contact in &self.contacts {
+            let (a, b) = pair_mut(&mut self.bodies, contact.a, contact.b);
+            correct_position(a, b, &contact.geometry);
+        }
+        for body in &mut self.bodies {
+            body.sync_shape();
+        }
+
+        let links = self.contacts.iter().map(|c| (c.a, c.b)).chain(self.joints.iter().map(Joint::bodies));
+        self.islands = islands(&self.bodies, links);
+        if self.sleeping {
+            update_sleep(&mut self.bodies, &self.islands, dt);
+        }
+        self.time += dt;
+    }
+
+    // Fast spheres move in substeps of about their radius, each swept against the other bodies.
+    // At the first impact the body stops in light contact with the obstacle and the rest of its
+    // motion this step is dropped, the contact solver then handles the bounce.
+    fn integrate_bullet(&mut self, index: usize, dt: f32, jointed: &HashSet<(usize, usize)>) {
+        let Shape::Sphere(sphere) = self.bodies[index].shape() else {
+            let body = &mut self.bodies[index];
+            integrate(body, self.gravity, dt, self.integrator);
+            body.clear_forces();
+            body.sync_shape();
+            return;
+        };
+        let radius = sphere.radius();
+        let travel = self.bodies[index].velocity.length() * dt;
+        let substeps = ((travel / radius).ceil() as usize).clamp(1, MAX_BULLET_SUBSTEPS);
+        let h = dt / substeps as f32;
+        for _ in 0..substeps {
+            let start = self.bodies[index].position;
+            integrate(&mut self.bodies[index], self.gravity, h, self.integrator);
+            let end = self.bodies[index].position;
+            let impact = self.bodies.iter().enumerate()
+                .filter(|&(other, _)| other != index && !jointed.contains(&(index.min(other), index.max(other))))
+                .filter_map(|(_, body)| sweep_sphere(radius, start, end, body.shape()))
+                .min_by(|x, y| x.fraction.total_cmp(&y.fraction));
+            if let Some(impact) = impact {
+                self.bodies[index].position = impact.position;
+                break;
+            }
+        }
+        let body = &mut self.bodies[index];
+        body.clear_forces();
+        body.sync_shape();
+    }
+
+    // Body index pairs (lower first) connected by a joint
+    fn jointed_pairs(&self) -> HashSet<(usize, usize)> {
+        self.joints.iter()
+            .map(|j| { let (a, b) = j.bodies(); (a.min(b), a.max(b)) })
+            .collect()
+    }
+
+    // Runs the narrow phase on the broad phase's candidate pairs that have at least one awake dynamic body
+    fn find_contacts(&mut self) -> Vec<Contact> {
+        let boxes: Vec<Option<Aabb>> = self.bodies.iter().map(|b| b.shape().hittable().bounding_box()).collect();
+        let jointed = self.jointed_pairs();
+        let mut contacts = Vec::new();
+        for (a, b) in self.broad_phase.pairs(&boxes) {
+            let (body_a, body_b) = (&self.bodies[a], &self.bodies[b]);
+            if !body_a.is_active() && !body_b.is_active() { continue; }
+            if jointed.contains(&(a, b)) { continue; }
+            if let Some(geometry) = collide(body_a.shape(), body_b.shape()) {
+                contacts.push(Contact { a, b, geometry });
+            }
+        }
+        contacts
+    }
+
+    // Consumes real elapsed time in fixed steps, returns how many steps were taken.
+    // Leftover time is carried into the next call.
+    pub(crate) fn advance(&mut self, elapsed: f32) -> usize {
+        self.accumulator += elapsed;
+        let mut steps = 0;
+        while self.accumulator >= self.timestep && steps < MAX_STEPS_PER_ADVANCE {
+            self.step();
+            self.accumulator -= self.timestep;
+            steps += 1;
+        }
+        if steps == MAX_STEPS_PER_ADVANCE {
+            self.accumulator = 0.0;
+        }
+        steps
+    }
+
+    // Snapshot of every body's geometry at its current state, ready for Camera::render_scene
+    pub(crate) fn hittables(&self) -> Vec<Box<dyn Hittable>> {
+        self.bodies.iter().map(|b| b.shape().to_hittable()).collect()
+    }
+
+    // Like hittables, but bodies moving now carry on at their current velocity through the
+    // shutter interval, for motion blur
+    pub(crate) fn hittables_in_motion(&self, shutter: (f32, f32)) -> Vec<Box<dyn Hittable>> {
+        self.bodies.iter()
+            .map(|b| {
+                let shape = b.shape().to_hittable();
+                if b.is_active() && b.velocity.length() > 0.0 {
+                    Box::new(Moving::new(shape, b.velocity, shutter)) as Box<dyn Hittable>
+                } else {
+                    shape
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Material;
+    use crate::objects::infinite_plane::InfinitePlane;
+    use crate::objects::sphere::Sphere;
+    use crate::physics::body::Shape;
+    use crate::ray::Ray;
+
+    fn ball(position: Vec3) -> RigidBody {
+        RigidBody::new(Shape::Sphere(Sphere::new(0.5, position, Material::default())), 2.0)
+    }
+
+    #[test]
+    fn free_fall_matches_analytic_solution() {
+        for integrator in [Integrator::SemiImplicitEuler, Integrator::Rk4] {
+            let mut world = World::new(Vec3::new(0.0, -9.81, 0.0));
+            world.set_integrator(integrator);
+            let h = world.add_body(ball(Vec3::new(0.0, 10.0, 0.0)));
+            world.body_mut(h).velocity = Vec3::new(1.0, 0.0, 0.0);
+            for _ in 0..120 {
+                world.step();
+            }
+            let t = world.time();
+            let expected = Vec3::new(t, 10.0 - 0.5 * 9.81 * t * t, 0.0);
+            let error = (world.body(h).position - expected).length();
+            let tolerance = if integrator == Integrator::Rk4 { 1e-3 } else { 0.1 };
+            assert!(error < tolerance, "{:?} error {}", integrator, error);
+        }
+    }
+
+    #[test]
+    fn torque_spins_up_a_sphere() {
+        let mut world = World::new(Vec3::new(0.0, 0.0, 0.0));
+        let h = world.add_body(ball(Vec3::new(0.0, 0.0, 0.0)));
+        // I = 2/5 * 2 * 0.25 = 0.2, so alpha = tau / I = 5 rad/s^2
+        world.body_mut(h).apply_torque(Vec3::new(0.0, 1.0, 0.0));
+        world.step();
+        let expected = 5.0 * world.timestep();
+        assert!((world.body(h).angular_velocity.y() - expected).abs() < 1e-5);
+        // The torque only lasts one step
+        world.step();
+        assert!((world.body(h).angular_velocity.y() - expected).abs() < 1e-5);
+    }
+
+    #[test]
+    fn small_light_sphere_spins_up_too() {
+        for integrator in [Integrator::SemiImplicitEuler, Integrator::Rk4] {
+            let mut world = World::new(Vec3::new(0.0, 0.0, 0.0));
+            world.set_integrator(integrator);
+            let h = world.add_body(RigidBody::new(Shape::Sphere(Sphere::new(0.05, Vec3::new(0.0, 0.0, 0.0), Material::default())), 0.01));
+            // I = 2/5 * 0.01 * 0.0025 = 1e-5, so alpha = tau / I = 1 rad/s^2 per 1e-5 of torque
+            world.body_mut(h).apply_torque(Vec3::new(0.0, 1e-5, 0.0));
+            world.step();
+            let expected = world.timestep();
+            let spin = world.body(h).angular_velocity.y();
+            assert!((spin - expected).abs() < 1e-4 * expected, "{:?} spun at {}", integrator, spin);
+        }
+    }
+
+    #[test]
+    fn advance_takes_fixed_steps_and_keeps_the_remainder() {
+        let mut world = World::new(Vec3::new(0.0, -9.81, 0.0));
+        world.set_timestep(0.01);
+        assert_eq!(world.advance(0.025), 2);
+        assert_eq!(world.advance(0.006), 1);
+    }
+
+    fn ground() -> RigidBody {
+        RigidBody::new_static(Shape::Plane(InfinitePlane::new(
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            Material::default(),
+        )))
+    }
+
+    #[test]
+    fn dropped_ball_bounces_lower_each_time() {
+        let mut world = World::new(Vec3::new(0.0, -9.81, 0.0));
+        world.add_body(ground());
+        let h = world.add_body(ball(Vec3::new(0.0, 5.0, 0.0)));
+        world.body_mut(h).set_restitution(0.6);
+
+        // Track the apex after the first bounce
+        let mut bounced = false;
+        let mut apex: f32 = 0.0;
+        for _ in 0..(3.0 / world.timestep()) as usize {
+            world.step();
+            let body = world.body(h);
+            assert!(body.position.y() > 0.3, "ball sank into the ground");
+            if body.velocity.y() > 0.0 { bounced = true; }
+            if bounced { apex = apex.max(body.position.y()); }
+        }
+        assert!(bounced);
+        // Drop of 4.5 above resting height, bounce height scales with e^2 = 0.36
+        let expected = 0.5 + 4.5 * 0.36;
+        assert!((apex - expected).abs() < 0.25, "apex {}", apex);
+    }
+
+    #[test]
+    fn sliding_ball_starts_rolling() {
+        let mut world = World::new(Vec3::new(0.0, -9.81, 0.0));
+        world.add_body(ground());
+        let h = world.add_body(ball(Vec3::new(0.0, 0.5, 0.0)));
+        world.body_mut(h).velocity = Vec3::new(3.0, 0.0, 0.0);
+        for _ in 0..240 {
+            world.step();
+        }
+        let body = world.body(h);
+        // Rolling without slipping: v = w x r, spinning about -Z when moving along +X
+        let contact_speed = body.velocity_at(body.position - Vec3::new(0.0, 0.5, 0.0)).length();
+        assert!(contact_speed < 0.05, "still slipping at {}", contact_speed);
+        assert!(body.angular_velocity.z() < 0.0);
+        // A solid sphere keeps 5/7 of its speed once rolling
+        assert!((body.velocity.x() - 3.0 * 5.0 / 7.0).abs() < 0.1, "speed {}", body.velocity.x());
+    }
+
+    #[test]
+    fn resting_ball_sleeps_until_pushed() {
+        let mut world = World::new(Vec3::new(0.0, -9.81, 0.0));
+        world.add_body(ground());
+        let h = world.add_body(ball(Vec3::new(0.0, 1.0, 0.0)));
+        for _ in 0..(2.0 / world.timestep()) as usize {
+            world.step();
+        }
+        assert!(world.body(h).is_sleeping());
+        let rest = world.body(h).position;
+        assert!((rest.y() - 0.5).abs() < 0.01, "{:?}", rest);
+        // Asleep on the ground: no narrow phase work and no drift
+        world.step();
+        assert!(world.contacts().is_empty());
+        assert_eq!(world.body(h).position, rest);
+
+        world.body_mut(h).apply_force(Vec3::new(200.0, 0.0, 0.0));
+        assert!(!world.body(h).is_sleeping());
+        world.step();
+        assert_eq!(world.contacts().len(), 1);
+        assert!(world.body(h).velocity.x() > 0.5);
+    }
+
+    #[test]
+    fn rolling_ball_wakes_a_sleeping_one() {
+        let mut world = World::new(Vec3::new(0.0, -9.81, 0.0));
+        world.add_body(ground());
+        let sleeper = world.add_body(ball(Vec3::new(0.0, 0.5, 0.0)));
+        for _ in 0..120 {
+            world.step();
+        }
+        assert!(world.body(sleeper).is_sleeping());
+
+        let roller = world.add_body(ball(Vec3::new(-3.0, 0.5, 0.0)));
+        world.body_mut(roller).velocity = Vec3::new(4.0, 0.0, 0.0);
+        for _ in 0..240 {
+            world.step();
+        }
+        assert!(world.body(sleeper).position.x() > 0.5, "{:?}", world.body(sleeper).position);
+    }
+
+    #[test]
+    fn changing_gravity_wakes_sleepers() {
+        let mut world = World::new(Vec3::new(0.0, -9.81, 0.0));
+        world.add_body(ground());
+        let h = world.add_body(ball(Vec3::new(0.0, 0.5, 0.0)));
+        for _ in 0..120 {
+            world.step();
+        }
+        assert!(world.body(h).is_sleeping());
+        world.set_gravity(Vec3::new(0.0, 9.81, 0.0));
+        assert!(!world.body(h).is_sleeping());
+        // Half a second of falling upwards
+        for _ in 0..(0.5 / world.timestep()) as usize {
+            world.step();
+        }
+        assert!(world.body(h).position.y() > 1.0, "{:?}", world.body(h).position);
+    }
+
+    #[test]
+    fn sleeping_can_be_turned_off() {
+        let mut world = World::new(Vec3::new(0.0, -9.81, 0.0));
+        world.set_sleeping(false);
+        world.add_body(ground());
+        let h = world.add_body(ball(Vec3::new(0.0, 0.5, 0.0)));
+        for _ in 0..240 {
+            world.step();
+        }
+        assert!(!world.body(h).is_sleeping());
+        assert_eq!(world.contacts().len(), 1);
+    }
+
+    fn pellet(world: &mut World, position: Vec3, velocity: Vec3, bullet: bool) -> BodyHandle {
+        let h = world.add_body(RigidBody::new(Shape::Sphere(Sphere::new(0.05, position, Material::default())), 0.01));
+        world.body_mut(h).velocity = velocity;
+        world.body_mut(h).set_bullet(bullet);
+        h
+    }
+
+    #[test]
+    fn bullet_does_not_tunnel_through_the_ground() {
+        // 2.5 units per step against a 0.05 radius: a discrete step jumps straight past the surface
+        for bullet in [false, true] {
+            let mut world = World::new(Vec3::new(0.0, -9.81, 0.0));
+            let g = world.add_body(ground());
+            world.body_mut(g).set_restitution(0.0);
+            let h = pellet(&mut world, Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, -300.0, 0.0), bullet);
+            world.body_mut(h).set_restitution(0.0);
+            let mut lowest: f32 = 1.0;
+            for _ in 0..60 {
+                world.step();
+                lowest = lowest.min(world.body(h).position.y());
+            }
+            assert_eq!(lowest > 0.0, bullet, "bullet {} reached {}", bullet, lowest);
+            if bullet {
+                assert!((world.body(h).position.y() - 0.05).abs() < 0.01, "{:?}", world.body(h).position);
+            }
+        }
+    }
+
+    #[test]
+    fn grazing_bullet_picks_up_spin_from_friction() {
+        let mut world = World::new(Vec3::new(0.0, -9.81, 0.0));
+        world.add_body(ground());
+        let h = pellet(&mut world, Vec3::new(0.0, 0.5, 0.0), Vec3::new(300.0, -30.0, 0.0), true);
+        for _ in 0..30 {
+            world.step();
+        }
+        // Skidding along +X, friction at the contact spins it about -Z
+        assert!(world.body(h).angular_velocity.z() < -1.0, "{:?}", world.body(h).angular_velocity);
+    }
+
+    #[test]
+    fn bullet_hits_a_small_moving_target() {
+        let mut world = World::new(Vec3::new(0.0, 0.0, 0.0));
+        let target = world.add_body(ball(Vec3::new(5.0, 0.0, 0.0)));
+        world.body_mut(target).velocity = Vec3::new(0.0, 0.5, 0.0);
+        let h = pellet(&mut world, Vec3::new(0.0, 0.0, 0.0), Vec3::new(400.0, 0.0, 0.0), true);
+        for _ in 0..30 {
+            world.step();
+        }
+        // The pellet bounced back and the target picked up some of its momentum
+        assert!(world.body(h).velocity.x() < 0.0, "{:?}", world.body(h).velocity);
+        assert!(world.body(target).velocity.x() > 0.0);
+    }
+
+    #[test]
+    fn rendered_geometry_follows_the_body() {
+        let mut world = World::new(Vec3::new(0.0, 0.0, 0.0));
+        let h = world.add_body(ball(Vec3::new(0.0, 0.0, 10.0)));
+        world.body_mut(h).velocity = Vec3::new(0.0, 0.0, 12.0);
+        world.set_timestep(0.5);
+        world.step();
+        let objects = world.hittables();
+        let ray = Ray::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 1.0));
+        let (t, _p, _n) = objects[0].intersects_ray(&ray).expect("hit");
+        assert!((t - 15.5).abs() < 1e-4);
+    }
+}
@@ -0,0 +1,192 @@
+use std::ops::{Add, Mul};
+use crate::vec3::Vec3;
+
+// Unit quaternion used for rotations: w + xi + yj + zk
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) struct Quat {
+    pub w: f32,
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+}
+
+impl Quat {
+    pub fn new(w: f32, x: f32, y: f32, z: f32) -> Self {
+        Quat { w, x, y, z }
+    }
+
+    pub fn identity() -> Self {
+        Quat { w: 1.0, x: 0.0, y: 0.0, z: 0.0 }
+    }
+
+    // Rotation of `angle` radians around `axis` (right-hand rule)
+    pub fn from_axis_angle(axis: Vec3, angle: f32) -> Self {
+        let axis = axis.normalized();
+        let (s, c) = (0.5 * angle).sin_cos();
+        Quat { w: c, x: axis.x() * s, y: axis.y() * s, z: axis.z() * s }
+    }
+
+    // Euler angles in degrees, applied as roll (Z), then pitch (X), then yaw (Y)
+    pub fn from_euler_degrees(angles: Vec3) -> Self {
+        let pitch = Quat::from_axis_angle(Vec3::new(1.0, 0.0, 0.0), angles.x().to_radians());
+        let yaw = Quat::from_axis_angle(Vec3::new(0.0, 1.0, 0.0), angles.y().to_radians());
+        let roll = Quat::from_axis_angle(Vec3::new(0.0, 0.0, 1.0), angles.z().to_radians());
+        yaw * pitch * roll
+    }
+
+    pub fn dot(&self, other: &Quat) -> f32 {
+        self.w * other.w + self.x * other.x + self.y * other.y + self.z * other.z
+    }
+
+    pub fn length(&self) -> f32 {
+        self.dot(self).sqrt()
+    }
+
+    pub fn normalized(&self) -> Quat {
+        let len = self.length();
+        if len == 0.0 {
+            Quat::identity()
+        } else {
+            Quat { w: self.w / len, x: self.x / len, y: self.y / len, z: self.z / len }
+        }
+    }
+
+    pub fn conjugate(&self) -> Quat {
+        Quat { w: self.w, x: -self.x, y: -self.y, z: -self.z }
+    }
+
+    pub fn rotate(&self, v: Vec3) -> Vec3 {
+        // v' = v + 2w (q x v) + 2 q x (q x v), with q the vector part
+        let q = Vec3::new(self.x, self.y, self.z);
+        let t = q.cross(&v) * 2.0;
+        v + t * self.w + q.cross(&t)
+    }
+
+    // Orientation after spinning with a world space angular velocity for dt seconds (q' = 0.5 w q)
+    pub fn integrated(&self, angular_velocity: Vec3, dt: f32) -> Quat {
+        (*self + self.derivative(angular_velocity) * dt).normalized()
+    }
+
+    // Time derivative of this orientation under a world space angular velocity
+    pub fn derivative(&self, angular_velocity: Vec3) -> Quat {
+        let w = Quat::new(0.0, angular_velocity.x(), angular_velocity.y(), angular_velocity.z());
+        (w * *self) * 0.5
+    }
+
+    // Spherical interpolation along the shortest arc
+    pub fn slerp(&self, other: &Quat, t: f32) -> Quat {
+        let mut cos_theta = self.dot(other);
+        let mut end = *other;
+        if cos_theta < 0.0 {
+            cos_theta = -cos_theta;
+            end = Quat { w: -end.w, x: -end.x, y: -end.y, z: -end.z };
+        }
+        if cos_theta > 0.9995 {
+            // Nearly parallel, fall back to normalized lerp
+            return Quat {
+                w: self.w + (end.w - self.w) * t,
+                x: self.x + (end.x - self.x) * t,
+                y: self.y + (end.y - self.y) * t,
+                z: self.z + (end.z - self.z) * t,
+            }.normalized();
+        }
+        let theta = cos_theta.acos();
+        let sin_theta = theta.sin();
+        let a = ((1.0 - t) * theta).sin() / sin_theta;
+        let b = (t * theta).sin() / sin_theta;
+        Quat {
+            w: self.w * a + end.w * b,
+            x: self.x * a + end.x * b,
+            y: self.y * a + end.y * b,
+            z: self.z * a + end.z * b,
+        }
+    }
+}
+
+impl Mul<Quat> for Quat {
+    type Output = Quat;
+    // Hamilton product: (self * other) applies `other` first, then `self`
+    fn mul(self, o: Quat) -> Quat {
+        Quat {
+            w: self.w * o.w - self.x * o.x - self.y * o.y - self.z * o.z,
+            x: self.w * o.x + self.x * o.w + self.y * o.z - self.z * o.y,
+            y: self.w * o.y - self.x * o.z + self.y * o.w + self.z * o.x,
+            z: self.w * o.z + self.x * o.y - self.y * o.x + self.z * o.w,
+        }
+    }
+}
+
+impl Mul<f32> for Quat {
+    type Output = Quat;
+    fn mul(self, scalar: f32) -> Quat {
+        Quat { w: self.w * scalar, x: self.x * scalar, y: self.y * scalar, z: self.z * scalar }
+    }
+}
+
+impl Add<Quat> for Quat {
+    type Output = Quat;
+    fn add(self, o: Quat) -> Quat {
+        Quat { w: self.w + o.w, x: self.x + o.x, y: self.y + o.y, z: self.z + o.z }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::{FRAC_PI_2, FRAC_PI_4};
+
+    fn close(a: Vec3, b: Vec3) -> bool {
+        (a - b).length() < 1e-5
+    }
+
+    #[test]
+    fn rotates_by_the_right_hand_rule() {
+        let q = Quat::from_axis_angle(Vec3::new(0.0, 0.0, 1.0), FRAC_PI_2);
+        assert!(close(q.rotate(Vec3::new(1.0, 0.0, 0.0)), Vec3::new(0.0, 1.0, 0.0)));
+        assert!(close(q.rotate(Vec3::new(0.0, 0.0, 2.0)), Vec3::new(0.0, 0.0, 2.0)));
+        // Undone by the conjugate
+        let v = Vec3::new(0.3, -1.2, 2.0);
+        assert!(close(q.conjugate().rotate(q.rotate(v)), v));
+    }
+
+    #[test]
+    fn euler_angles_apply_roll_then_pitch_then_yaw() {
+        let up = Vec3::new(0.0, 1.0, 0.0);
+        // Pitch tips +Y onto +Z, then yaw swings +Z onto +X
+        let q = Quat::from_euler_degrees(Vec3::new(90.0, 90.0, 0.0));
+        assert!(close(q.rotate(up), Vec3::new(1.0, 0.0, 0.0)));
+        // Roll turns +X onto +Y, which yaw then leaves alone
+        let q = Quat::from_euler_degrees(Vec3::new(0.0, 90.0, 90.0));
+        assert!(close(q.rotate(Vec3::new(1.0, 0.0, 0.0)), up));
+    }
+
+    #[test]
+    fn slerp_halfway() {
+        let a = Quat::identity();
+        let b = Quat::from_axis_angle(Vec3::new(0.0, 0.0, 1.0), FRAC_PI_2);
+        let half = a.slerp(&b, 0.5);
+        let expected = Quat::from_axis_angle(Vec3::new(0.0, 0.0, 1.0), FRAC_PI_4);
+        assert!((half.dot(&expected) - 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn slerp_ends_on_its_endpoints() {
+        let a = Quat::from_axis_angle(Vec3::new(1.0, 0.0, 0.0), 0.4);
+        let b = Quat::from_axis_angle(Vec3::new(0.0, 1.0, 1.0), 1.3);
+        assert!((a.slerp(&b, 0.0).dot(&a) - 1.0).abs() < 1e-5);
+        assert!((a.slerp(&b, 1.0).dot(&b) - 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn slerp_takes_the_shortest_path() {
+        // 270 degrees one way is 90 the other, so halfway is -45 degrees, not 135
+        let a = Quat::identity();
+        let b = Quat::from_axis_angle(Vec3::new(0.0, 0.0, 1.0), 3.0 * FRAC_PI_2);
+        let half = a.slerp(&b, 0.5);
+        let expected = Vec3::new(FRAC_PI_4.cos(), -FRAC_PI_4.sin(), 0.0);
+        assert!(close(half.rotate(Vec3::new(1.0, 0.0, 0.0)), expected));
+        // The same rotation with the opposite sign interpolates the same way
+        let flipped = b * -1.0;
+        assert!(close(a.slerp(&flipped, 0.5).rotate(Vec3::new(1.0, 0.0, 0.0)), expected));
+    }
+}
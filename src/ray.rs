@@ -2,22 +2,33 @@ use crate::vec3::Vec3;
 
 pub(crate) struct Ray {
     pub(crate) origin: Vec3,
-    pub(crate) direction: Vec3
+    pub(crate) direction: Vec3,
+    pub(crate) time: f32 // when the ray was cast, relative to the frame, for moving objects
 }
 
 impl Ray {
     pub fn new(origin: Vec3, direction: Vec3) -> Self {
-        Ray { origin, direction }
+        Ray { origin, direction, time: 0.0 }
+    }
+
+    pub fn at_time(origin: Vec3, direction: Vec3, time: f32) -> Self {
+        Ray { origin, direction, time }
     }
 }
 
 pub(crate) struct Segment {
     pub(crate) start: Vec3,
-    pub(crate) end: Vec3
+    pub(crate) end: Vec3,
+    pub(crate) time: f32
 }
 
 impl Segment {
     pub fn new(start: Vec3, end: Vec3) -> Self {
-        Segment { start, end }
+        Segment { start, end, time: 0.0 }
     }
-}
\ No newline at end of file
+
+    #[cfg(test)]
+    pub fn at_time(start: Vec3, end: Vec3, time: f32) -> Self {
+        Segment { start, end, time }
+    }
+}
@@ -0,0 +1,98 @@
+use crate::vec3::Vec3;
+
+// Small seeded PCG32 generator. Kept in-repo so simulations and renders are reproducible
+// from a seed, independent of platform or dependency versions.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Rng {
+    state: u64,
+}
+
+const MULTIPLIER: u64 = 6364136223846793005;
+const INCREMENT: u64 = 1442695040888963407;
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        let mut rng = Rng { state: 0 };
+        rng.next_u32();
+        rng.state = rng.state.wrapping_add(seed);
+        rng.next_u32();
+        rng
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        let old = self.state;
+        self.state = old.wrapping_mul(MULTIPLIER).wrapping_add(INCREMENT);
+        let xorshifted = (((old >> 18) ^ old) >> 27) as u32;
+        let rot = (old >> 59) as u32;
+        xorshifted.rotate_right(rot)
+    }
+
+    // Uniform in [0, 1)
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u32() >> 8) as f32 / (1u32 << 24) as f32
+    }
+
+    // Uniform in [lo, hi)
+    pub fn range(&mut self, lo: f32, hi: f32) -> f32 {
+        lo + (hi - lo) * self.next_f32()
+    }
+
+    // Uniform direction on the unit sphere
+    pub fn unit_vector(&mut self) -> Vec3 {
+        let z = self.range(-1.0, 1.0);
+        let phi = self.range(0.0, 2.0 * std::f32::consts::PI);
+        let r = (1.0 - z * z).max(0.0).sqrt();
+        Vec3::new(r * phi.cos(), r * phi.sin(), z)
+    }
+
+    // Uniform direction within `half_angle` radians of `axis`
+    pub fn in_cone(&mut self, axis: Vec3, half_angle: f32) -> Vec3 {
+        let axis = axis.normalized();
+        let cos_max = half_angle.clamp(0.0, std::f32::consts::PI).cos();
+        let cos_theta = self.range(cos_max, 1.0);
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let phi = self.range(0.0, 2.0 * std::f32::consts::PI);
+        let (t, b) = axis.orthonormal_basis();
+        t * (sin_theta * phi.cos()) + b * (sin_theta * phi.sin()) + axis * cos_theta
+    }
+
+    // Uniform point in the unit disc, as (x, y)
+    pub fn in_unit_disc(&mut self) -> (f32, f32) {
+        let r = self.next_f32().sqrt();
+        let phi = self.range(0.0, 2.0 * std::f32::consts::PI);
+        (r * phi.cos(), r * phi.sin())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_same_sequence() {
+        let mut a = Rng::new(42);
+        let mut b = Rng::new(42);
+        let mut c = Rng::new(43);
+        let xs: Vec<u32> = (0..8).map(|_| a.next_u32()).collect();
+        let ys: Vec<u32> = (0..8).map(|_| b.next_u32()).collect();
+        let zs: Vec<u32> = (0..8).map(|_| c.next_u32()).collect();
+        assert_eq!(xs, ys);
+        assert_ne!(xs, zs);
+    }
+
+    #[test]
+    fn samples_stay_in_range() {
+        let mut rng = Rng::new(7);
+        let axis = Vec3::new(0.0, 1.0, 0.0);
+        let mut mean = 0.0;
+        for _ in 0..1000 {
+            let x = rng.next_f32();
+            assert!((0.0..1.0).contains(&x));
+            mean += x / 1000.0;
+            assert!((rng.unit_vector().length() - 1.0).abs() < 1e-4);
+            let d = rng.in_cone(axis, 0.3);
+            assert!(d.dot(&axis) >= 0.3_f32.cos() - 1e-4);
+        }
+        assert!((mean - 0.5).abs() < 0.05);
+    }
+}
@@ -0,0 +1,147 @@
+// Real polynomial root finding for ray/surface intersections.
+//
+// Quadratics are solved in closed form. Higher degrees (the torus quartic) use root isolation:
+// the roots of the derivative split the real line into monotonic pieces, each piece with a sign
+// change holds exactly one root which is found by bisection. A derivative root where the
+// polynomial (almost) vanishes is a touching root, which is exactly what a grazing ray produces
+// and what closed-form quartic formulas tend to lose to cancellation.
+
+const BISECTION_STEPS: usize = 80;
+// Relative tolerance used to accept touching (double) roots and to drop vanishing coefficients
+const TOUCH_TOL: f64 = 1e-7;
+
+// Solves a*t^2 + b*t + c = 0, returns the real roots in ascending order.
+// A slightly negative discriminant (within `eps`) is treated as a grazing double root.
+pub fn solve_quadratic(a: f32, b: f32, c: f32, eps: f32) -> Vec<f32> {
+    if a.abs() < f32::EPSILON {
+        if b.abs() < f32::EPSILON { return Vec::new(); }
+        return vec![-c / b];
+    }
+    let mut discriminant = b * b - 4.0 * a * c;
+    if discriminant < 0.0 {
+        if discriminant > -eps { discriminant = 0.0; } else { return Vec::new(); }
+    }
+    let sqrtd = discriminant.sqrt();
+    // Numerically stable form, avoids subtracting nearly equal values
+    let q = -0.5 * (b + b.signum() * sqrtd);
+    let (t0, t1) = if q == 0.0 {
+        (0.0, 0.0)
+    } else {
+        (q / a, c / q)
+    };
+    if t0 < t1 { vec![t0, t1] } else { vec![t1, t0] }
+}
+
+// Solves a*t^4 + b*t^3 + c*t^2 + d*t + e = 0, returns the real roots in ascending order.
+pub fn solve_quartic(a: f64, b: f64, c: f64, d: f64, e: f64) -> Vec<f64> {
+    real_roots(&[a, b, c, d, e])
+}
+
+// Coefficients are ordered from the highest degree down.
+fn real_roots(coeffs: &[f64]) -> Vec<f64> {
+    let scale = coeffs.iter().fold(0.0_f64, |m, c| m.max(c.abs()));
+    if scale == 0.0 { return Vec::new(); }
+    // Drop leading coefficients that vanish relative to the rest
+    let first = coeffs.iter().position(|c| c.abs() > scale * 1e-12).unwrap_or(coeffs.len());
+    let coeffs = &coeffs[first..];
+
+    match coeffs.len() {
+        0 | 1 => Vec::new(),
+        2 => vec![-coeffs[1] / coeffs[0]],
+        _ => {
+            let degree = coeffs.len() - 1;
+            let derivative: Vec<f64> = coeffs[..degree]
+                .iter()
+                .enumerate()
+                .map(|(i, c)| c * (degree - i) as f64)
+                .collect();
+            let critical = real_roots(&derivative);
+
+            // Cauchy bound: every real root lies in [-bound, bound]
+            let bound = 1.0 + coeffs[1..].iter().fold(0.0_f64, |m, c| m.max((c / coeffs[0]).abs()));
+            let mut points = Vec::with_capacity(critical.len() + 2);
+            points.push(-bound);
+            points.extend(critical.iter().copied().filter(|x| x.abs() < bound));
+            points.push(bound);
+
+            let tol = TOUCH_TOL * scale;
+            let mut roots: Vec<f64> = Vec::new();
+            for pair in points.windows(2) {
+                let (lo, hi) = (pair[0], pair[1]);
+                let (f_lo, f_hi) = (eval(coeffs, lo), eval(coeffs, hi));
+                if f_lo.abs() <= tol && lo > -bound {
+                    push_unique(&mut roots, lo, tol);
+                }
+                if f_lo.signum() != f_hi.signum() && f_lo.abs() > tol && f_hi.abs() > tol {
+                    push_unique(&mut roots, bisect(coeffs, lo, hi, f_lo), tol);
+                }
+            }
+            roots
+        }
+    }
+}
+
+fn eval(coeffs: &[f64], x: f64) -> f64 {
+    coeffs.iter().fold(0.0, |acc, c| acc * x + c)
+}
+
+fn bisect(coeffs: &[f64], mut lo: f64, mut hi: f64, f_lo: f64) -> f64 {
+    let lo_sign = f_lo.signum();
+    for _ in 0..BISECTION_STEPS {
+        let mid = 0.5 * (lo + hi);
+        if mid == lo || mid == hi { break; }
+        if eval(coeffs, mid).signum() == lo_sign { lo = mid; } else { hi = mid; }
+    }
+    0.5 * (lo + hi)
+}
+
+fn push_unique(roots: &mut Vec<f64>, x: f64, tol: f64) {
+    if roots.last().is_none_or(|last| (x - last).abs() > tol.sqrt().max(1e-9)) {
+        roots.push(x);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_roots(actual: &[f64], expected: &[f64]) {
+        assert_eq!(actual.len(), expected.len(), "roots {:?}, expected {:?}", actual, expected);
+        for (a, e) in actual.iter().zip(expected) {
+            assert!((a - e).abs() < 1e-6, "roots {:?}, expected {:?}", actual, expected);
+        }
+    }
+
+    #[test]
+    fn quadratic_two_roots() {
+        let roots = solve_quadratic(1.0, -3.0, 2.0, 1e-6);
+        assert_eq!(roots, vec![1.0, 2.0]);
+    }
+
+    #[test]
+    fn quadratic_grazing_is_double_root() {
+        let roots = solve_quadratic(1.0, -2.0, 1.0 + 1e-7, 1e-4);
+        assert_eq!(roots.len(), 2);
+        assert!((roots[0] - 1.0).abs() < 1e-3 && (roots[1] - 1.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn quartic_four_simple_roots() {
+        // (t-1)(t-2)(t-3)(t-4)
+        let roots = solve_quartic(1.0, -10.0, 35.0, -50.0, 24.0);
+        assert_roots(&roots, &[1.0, 2.0, 3.0, 4.0]);
+    }
+
+    #[test]
+    fn quartic_double_roots() {
+        // (t-1)^2 (t-3)^2, what a ray tangent to both sides of a torus tube produces
+        let roots = solve_quartic(1.0, -8.0, 22.0, -24.0, 9.0);
+        assert_roots(&roots, &[1.0, 3.0]);
+    }
+
+    #[test]
+    fn quartic_no_real_roots() {
+        // (t^2 + 1)(t^2 + 4)
+        assert!(solve_quartic(1.0, 0.0, 5.0, 0.0, 4.0).is_empty());
+    }
+}
@@ -0,0 +1,42 @@
+use crate::aov::AovBuffers;
+use crate::camera::Camera;
+use crate::environment::Environment;
+use crate::light::{AmbientLight, Light};
+use crate::medium::Medium;
+use crate::objects::hittable::Hittable;
+use crate::physics::world::World;
+
+// Everything needed to simulate and render one frame
+pub(crate) struct Scene {
+    pub world: World,
+    pub lights: Vec<Box<dyn Light>>,
+    pub ambient_light: AmbientLight,
+    pub environment: Environment,
+    pub media: Vec<Medium>, // fog, smoke and the like, empty for clear air
+    pub camera: Camera,
+}
+
+impl Scene {
+    pub(crate) fn render(&self) -> Vec<u8> {
+        self.render_with(&self.camera)
+    }
+
+    // Same scene through another camera, e.g. one eye of a stereo pair
+    pub(crate) fn render_with(&self, camera: &Camera) -> Vec<u8> {
+        camera.render_scene(&self.objects_for(camera), &self.lights, &self.ambient_light, &self.environment, &self.media)
+    }
+
+    // The image along with its depth, normal, albedo, object id and shadow passes
+    pub(crate) fn render_with_aovs(&self) -> (Vec<u8>, AovBuffers) {
+        let camera = &self.camera;
+        camera.render_scene_with_aovs(&self.objects_for(camera), &self.lights, &self.ambient_light, &self.environment, &self.media)
+    }
+
+    fn objects_for(&self, camera: &Camera) -> Vec<Box<dyn Hittable>> {
+        if camera.has_motion_blur() {
+            self.world.hittables_in_motion(camera.shutter())
+        } else {
+            self.world.hittables()
+        }
+    }
+}
@@ -0,0 +1,144 @@
+// Signed distance functions and operators for building DistanceField objects.
+// Negative inside, positive outside. Primitives return closures so they compose:
+//   op_smooth_union(sd_sphere(a, 1.0), sd_box(b, half), 0.3)
+#![allow(dead_code, reason = "a toolbox of distance functions; each scene picks the few it needs")]
+
+use crate::vec3::Vec3;
+
+fn max_components(v: Vec3, floor: f32) -> Vec3 {
+    Vec3::new(v.x().max(floor), v.y().max(floor), v.z().max(floor))
+}
+
+fn abs(v: Vec3) -> Vec3 {
+    Vec3::new(v.x().abs(), v.y().abs(), v.z().abs())
+}
+
+pub fn sd_sphere(centre: Vec3, radius: f32) -> impl Fn(Vec3) -> f32 {
+    move |p| (p - centre).length() - radius
+}
+
+// Box with the given half extents along each axis
+pub fn sd_box(centre: Vec3, half_extents: Vec3) -> impl Fn(Vec3) -> f32 {
+    move |p| {
+        let q = abs(p - centre) - half_extents;
+        let inside = q.x().max(q.y()).max(q.z()).min(0.0);
+        max_components(q, 0.0).length() + inside
+    }
+}
+
+pub fn sd_round_box(centre: Vec3, half_extents: Vec3, rounding: f32) -> impl Fn(Vec3) -> f32 {
+    let inner = sd_box(centre, half_extents - rounding);
+    move |p| inner(p) - rounding
+}
+
+// Torus around the Y axis
+pub fn sd_torus(centre: Vec3, major_radius: f32, minor_radius: f32) -> impl Fn(Vec3) -> f32 {
+    move |p| {
+        let q = p - centre;
+        let ring = (q.x() * q.x() + q.z() * q.z()).sqrt() - major_radius;
+        (ring * ring + q.y() * q.y()).sqrt() - minor_radius
+    }
+}
+
+pub fn sd_capsule(start: Vec3, end: Vec3, radius: f32) -> impl Fn(Vec3) -> f32 {
+    move |p| {
+        let pa = p - start;
+        let ba = end - start;
+        let h = (pa.dot(&ba) / ba.dot(&ba)).clamp(0.0, 1.0);
+        (pa - ba * h).length() - radius
+    }
+}
+
+// Capped cylinder around the Y axis, centred on `centre`
+pub fn sd_cylinder(centre: Vec3, radius: f32, half_height: f32) -> impl Fn(Vec3) -> f32 {
+    move |p| {
+        let q = p - centre;
+        let dx = (q.x() * q.x() + q.z() * q.z()).sqrt() - radius;
+        let dy = q.y().abs() - half_height;
+        dx.max(dy).min(0.0) + (dx.max(0.0).powi(2) + dy.max(0.0).powi(2)).sqrt()
+    }
+}
+
+// Half-space behind a plane through `point`
+pub fn sd_plane(point: Vec3, normal: Vec3) -> impl Fn(Vec3) -> f32 {
+    let n = normal.normalized();
+    move |p| (p - point).dot(&n)
+}
+
+pub fn op_union(a: impl Fn(Vec3) -> f32, b: impl Fn(Vec3) -> f32) -> impl Fn(Vec3) -> f32 {
+    move |p| a(p).min(b(p))
+}
+
+pub fn op_intersect(a: impl Fn(Vec3) -> f32, b: impl Fn(Vec3) -> f32) -> impl Fn(Vec3) -> f32 {
+    move |p| a(p).max(b(p))
+}
+
+// `a` with `b` cut away
+pub fn op_subtract(a: impl Fn(Vec3) -> f32, b: impl Fn(Vec3) -> f32) -> impl Fn(Vec3) -> f32 {
+    move |p| a(p).max(-b(p))
+}
+
+// Polynomial smooth minimum, `k` is the blend radius
+pub fn smooth_min(a: f32, b: f32, k: f32) -> f32 {
+    if k <= 0.0 { return a.min(b); }
+    let h = (0.5 + 0.5 * (b - a) / k).clamp(0.0, 1.0);
+    b + (a - b) * h - k * h * (1.0 - h)
+}
+
+pub fn op_smooth_union(a: impl Fn(Vec3) -> f32, b: impl Fn(Vec3) -> f32, k: f32) -> impl Fn(Vec3) -> f32 {
+    move |p| smooth_min(a(p), b(p), k)
+}
+
+pub fn op_smooth_subtract(a: impl Fn(Vec3) -> f32, b: impl Fn(Vec3) -> f32, k: f32) -> impl Fn(Vec3) -> f32 {
+    move |p| -smooth_min(-a(p), b(p), k)
+}
+
+pub fn op_smooth_intersect(a: impl Fn(Vec3) -> f32, b: impl Fn(Vec3) -> f32, k: f32) -> impl Fn(Vec3) -> f32 {
+    move |p| -smooth_min(-a(p), -b(p), k)
+}
+
+// Infinite repetition with the given cell size per axis, 0 leaves that axis unrepeated.
+// The shape should fit inside one cell centred on the origin.
+pub fn op_repeat(f: impl Fn(Vec3) -> f32, period: Vec3) -> impl Fn(Vec3) -> f32 {
+    let wrap = |v: f32, c: f32| if c > 0.0 { v - c * (v / c).round() } else { v };
+    move |p| f(Vec3::new(wrap(p.x(), period.x()), wrap(p.y(), period.y()), wrap(p.z(), period.z())))
+}
+
+pub fn op_translate(f: impl Fn(Vec3) -> f32, offset: Vec3) -> impl Fn(Vec3) -> f32 {
+    move |p| f(p - offset)
+}
+
+// Inflates a shape by `radius`, rounding its edges
+pub fn op_round(f: impl Fn(Vec3) -> f32, radius: f32) -> impl Fn(Vec3) -> f32 {
+    move |p| f(p) - radius
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn box_distances() {
+        let b = sd_box(Vec3::new(0.0, 0.0, 0.0), Vec3::new(1.0, 2.0, 3.0));
+        assert!((b(Vec3::new(3.0, 0.0, 0.0)) - 2.0).abs() < 1e-6);
+        assert!((b(Vec3::new(0.0, 0.0, 0.0)) + 1.0).abs() < 1e-6);
+        assert!((b(Vec3::new(2.0, 3.0, 0.0)) - 2.0_f32.sqrt()).abs() < 1e-6);
+    }
+
+    #[test]
+    fn smooth_union_blends_below_hard_union() {
+        let a = sd_sphere(Vec3::new(-1.0, 0.0, 0.0), 1.0);
+        let b = sd_sphere(Vec3::new(1.0, 0.0, 0.0), 1.0);
+        let p = Vec3::new(0.0, 1.0, 0.0);
+        let hard = a(p).min(b(p));
+        let smooth = op_smooth_union(a, b, 0.5)(p);
+        assert!(smooth < hard);
+    }
+
+    #[test]
+    fn repeat_wraps_into_the_central_cell() {
+        let spheres = op_repeat(sd_sphere(Vec3::new(0.0, 0.0, 0.0), 0.5), Vec3::new(4.0, 0.0, 0.0));
+        assert!((spheres(Vec3::new(8.0, 0.0, 0.0)) + 0.5).abs() < 1e-6);
+        assert!((spheres(Vec3::new(8.0, 3.0, 0.0)) - 2.5).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,253 @@
+use crate::color::Color;
+use crate::consts::EPS;
+use crate::environment::Rgb;
+use crate::light::{arriving_color, AmbientLight, VisibleLight};
+use crate::material::Material;
+use crate::phong::attenuation;
+use crate::vec3::Vec3;
+
+// How a material turns incoming light into colour. Models only describe the response to a single
+// light; ambient, attenuation and summing over lights are shared.
+pub(crate) trait ShadingModel {
+    // Light reflected towards the viewer per unit of light from `light_dir`, before the light's
+    // colour, intensity and attenuation. All directions are unit vectors pointing away from the surface.
+    fn reflect(&self, normal: Vec3, view_dir: Vec3, light_dir: Vec3, material: &Material) -> Rgb;
+
+    fn shade(&self, normal: Vec3, view_dir: Vec3, point: Vec3, lights: &[VisibleLight], material: &Material, ambient_light: &AmbientLight) -> Color {
+        sum_lights(self, normal, view_dir, point, lights, material, ambient_light)
+    }
+
+    // Whether the point is drawn as a flat outline, which no other light (the environment
+    // included) should brighten
+    fn is_outline(&self, _normal: Vec3, _view_dir: Vec3) -> bool {
+        false
+    }
+}
+
+// Ambient plus every light's attenuated response, clamped, for whichever model
+fn sum_lights<M: ShadingModel + ?Sized>(model: &M, normal: Vec3, view_dir: Vec3, point: Vec3, lights: &[VisibleLight], material: &Material, ambient_light: &AmbientLight) -> Color {
+    let n = normal.normalized();
+    let v = view_dir.normalized();
+    let mut acc = [
+        ambient_light.color.r * ambient_light.intensity * material.ambient.r,
+        ambient_light.color.g * ambient_light.intensity * material.ambient.g,
+        ambient_light.color.b * ambient_light.intensity * material.ambient.b,
+    ];
+    for visible in lights {
+        let ls = visible.0;
+        let to_light = *ls.position() - point;
+        let dist = to_light.length().max(EPS); // avoid divide-by-zero
+        let light_dir = to_light / dist;
+        if n.dot(&light_dir) <= 0.0 { continue; }
+        let reflected = model.reflect(n, v, light_dir, material);
+        let scale = attenuation(dist) * ls.intensity();
+        for (a, (r, c)) in acc.iter_mut().zip(reflected.into_iter().zip(arriving_color(visible))) {
+            *a += r * c * scale;
+        }
+    }
+    // Clamp final accumulated color to [0,1]
+    Color::new(acc[0].clamp(0.0, 1.0), acc[1].clamp(0.0, 1.0), acc[2].clamp(0.0, 1.0), 1.0)
+}
+
+fn diffuse(material: &Material, amount: f32) -> Rgb {
+    [material.diffuse.r * amount, material.diffuse.g * amount, material.diffuse.b * amount]
+}
+
+fn with_specular(diffuse: Rgb, material: &Material, amount: f32) -> Rgb {
+    [
+        diffuse[0] + material.specular.r * amount,
+        diffuse[1] + material.specular.g * amount,
+        diffuse[2] + material.specular.b * amount,
+    ]
+}
+
+// Matte, no highlight
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) struct Lambert;
+
+impl ShadingModel for Lambert {
+    fn reflect(&self, normal: Vec3, _view_dir: Vec3, light_dir: Vec3, material: &Material) -> Rgb {
+        diffuse(material, normal.dot(&light_dir).max(0.0))
+    }
+}
+
+// Classic Phong: the highlight follows the mirror direction of the light
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) struct Phong;
+
+impl ShadingModel for Phong {
+    fn reflect(&self, normal: Vec3, view_dir: Vec3, light_dir: Vec3, material: &Material) -> Rgb {
+        let ndotl = normal.dot(&light_dir).max(0.0);
+        let reflect = (normal * (2.0 * ndotl) - light_dir).normalized();
+        let spec = reflect.dot(&view_dir).max(0.0).powf(material.shininess.max(0.0));
+        with_specular(diffuse(material, ndotl), material, spec)
+    }
+}
+
+// Highlight from the half vector between light and viewer. Broader than Phong for the same
+// shininess and doesn't cut off at grazing angles; about 4x the Phong exponent looks alike.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) struct BlinnPhong;
+
+impl ShadingModel for BlinnPhong {
+    fn reflect(&self, normal: Vec3, view_dir: Vec3, light_dir: Vec3, material: &Material) -> Rgb {
+        let ndotl = normal.dot(&light_dir).max(0.0);
+        let half = (light_dir + view_dir).normalized();
+        let spec = normal.dot(&half).max(0.0).powf(material.shininess.max(0.0));
+        with_specular(diffuse(material, ndotl), material, spec)
+    }
+}
+
+// Rough diffuse (clay, plaster, the moon) that stays brighter towards its edges than Lambert.
+// `roughness` is the slope deviation of the surface facets in radians, 0 is Lambert.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) struct OrenNayar {
+    pub roughness: f32,
+}
+
+impl ShadingModel for OrenNayar {
+    fn reflect(&self, normal: Vec3, view_dir: Vec3, light_dir: Vec3, material: &Material) -> Rgb {
+        let s2 = self.roughness * self.roughness;
+        let a = 1.0 - 0.5 * s2 / (s2 + 0.33);
+        let b = 0.45 * s2 / (s2 + 0.09);
+        let cos_l = normal.dot(&light_dir).clamp(0.0, 1.0);
+        let cos_v = normal.dot(&view_dir).clamp(0.0, 1.0);
+        // Cosine of the azimuth between light and viewer, from their projections on the surface
+        let on_surface = |d: Vec3, cos: f32| d - normal * cos;
+        let (pl, pv) = (on_surface(light_dir, cos_l), on_surface(view_dir, cos_v));
+        let lengths = pl.length() * pv.length();
+        let cos_phi = if lengths > EPS { (pl.dot(&pv) / lengths).max(0.0) } else { 0.0 };
+        let (theta_l, theta_v) = (cos_l.acos(), cos_v.acos());
+        let (alpha, beta) = (theta_l.max(theta_v), theta_l.min(theta_v));
+        diffuse(material, cos_l * (a + b * cos_phi * alpha.sin() * beta.tan()))
+    }
+}
+
+// Cel shading: diffuse light snapped to `bands` flat levels, a hard-edged highlight, and an
+// outline wherever the surface turns away from the viewer. `outline` is how far from edge-on
+// (as the cosine between normal and view) still counts as silhouette, 0 for none.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) struct Toon {
+    pub bands: u32,
+    pub outline: f32,
+}
+
+impl ShadingModel for Toon {
+    fn reflect(&self, normal: Vec3, view_dir: Vec3, light_dir: Vec3, material: &Material) -> Rgb {
+        let bands = self.bands.max(1) as f32;
+        let ndotl = normal.dot(&light_dir).max(0.0);
+        let level = (ndotl * bands).ceil() / bands;
+        let half = (light_dir + view_dir).normalized();
+        let spec = normal.dot(&half).max(0.0).powf(material.shininess.max(0.0));
+        with_specular(diffuse(material, level), material, if spec > 0.5 { 1.0 } else { 0.0 })
+    }
+
+    fn shade(&self, normal: Vec3, view_dir: Vec3, point: Vec3, lights: &[VisibleLight], material: &Material, ambient_light: &AmbientLight) -> Color {
+        if self.is_outline(normal, view_dir) {
+            return Color::new(0.0, 0.0, 0.0, 1.0);
+        }
+        sum_lights(self, normal, view_dir, point, lights, material, ambient_light)
+    }
+
+    fn is_outline(&self, normal: Vec3, view_dir: Vec3) -> bool {
+        normal.normalized().dot(&view_dir.normalized()) < self.outline
+    }
+}
+
+// The shading model a material picks, kept as plain data so materials stay Copy
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+#[allow(dead_code, reason = "the demo's materials all shade with the default Phong")]
+pub(crate) enum Shading {
+    Lambert,
+    #[default]
+    Phong,
+    BlinnPhong,
+    OrenNayar(OrenNayar),
+    Toon(Toon),
+}
+
+impl Shading {
+    pub(crate) fn model(&self) -> &dyn ShadingModel {
+        match self {
+            Shading::Lambert => &Lambert,
+            Shading::Phong => &Phong,
+            Shading::BlinnPhong => &BlinnPhong,
+            Shading::OrenNayar(model) => model,
+            Shading::Toon(model) => model,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::light::PointLight;
+
+    fn material(shading: Shading) -> Material {
+        let black = Color::new(0.0, 0.0, 0.0, 1.0);
+        let mut m = Material::new(black, Color::new(0.8, 0.8, 0.8, 1.0), Color::new(1.0, 1.0, 1.0, 1.0), 16.0);
+        m.shading = shading;
+        m
+    }
+
+    #[test]
+    fn highlights_and_their_absence() {
+        let n = Vec3::new(0.0, 0.0, 1.0);
+        let l = Vec3::new(0.0, 0.6, 0.8);
+        let mirror = Vec3::new(0.0, -0.6, 0.8);
+        let off = Vec3::new(0.6, 0.0, 0.8);
+        let lambert = Lambert.reflect(n, mirror, l, &material(Shading::Lambert));
+        assert!((lambert[0] - 0.8 * 0.8).abs() < 1e-5);
+        // Both highlights peak in the mirror direction, Blinn's falls off more slowly
+        let m = material(Shading::Phong);
+        let (phong_peak, phong_off) = (Phong.reflect(n, mirror, l, &m)[0], Phong.reflect(n, off, l, &m)[0]);
+        let (blinn_peak, blinn_off) = (BlinnPhong.reflect(n, mirror, l, &m)[0], BlinnPhong.reflect(n, off, l, &m)[0]);
+        assert!((phong_peak - (lambert[0] + 1.0)).abs() < 1e-4);
+        assert!((blinn_peak - (lambert[0] + 1.0)).abs() < 1e-4);
+        assert!(blinn_off > phong_off && phong_off > lambert[0]);
+    }
+
+    #[test]
+    fn oren_nayar_is_lambert_when_smooth_and_flatter_when_rough() {
+        let n = Vec3::new(0.0, 0.0, 1.0);
+        let v = Vec3::new(0.0, 0.0, 1.0);
+        let m = material(Shading::Lambert);
+        let smooth = OrenNayar { roughness: 0.0 };
+        let rough = OrenNayar { roughness: 0.8 };
+        let head_on = Vec3::new(0.0, 0.0, 1.0);
+        let grazing = Vec3::new(0.0, 0.95, 0.3122).normalized();
+        for l in [head_on, grazing] {
+            assert!((smooth.reflect(n, v, l, &m)[0] - Lambert.reflect(n, v, l, &m)[0]).abs() < 1e-5);
+        }
+        // Backscatter: with light and viewer together at a grazing angle, the rough surface is
+        // relatively brighter than Lambert predicts
+        let ratio = |model: &dyn ShadingModel| model.reflect(n, grazing, grazing, &m)[0] / model.reflect(n, head_on, head_on, &m)[0];
+        assert!(ratio(&rough) > ratio(&smooth));
+    }
+
+    #[test]
+    fn toon_bands_and_outline() {
+        let toon = Toon { bands: 3, outline: 0.2 };
+        let m = material(Shading::Toon(toon));
+        let light = PointLight::new(Vec3::new(0.0, 0.0, 0.0), Color::new(1.0, 1.0, 1.0, 1.0), 1.0);
+        let lights: Vec<VisibleLight> = vec![(&light, [1.0; 3])];
+        let ambient = AmbientLight::new(Color::new(0.0, 0.0, 0.0, 1.0), 0.0);
+        // A normal sweeping away from the light only ever lands on a few distinct levels
+        let matte = Material { specular: Color::new(0.0, 0.0, 0.0, 1.0), ..m };
+        let mut levels: Vec<u32> = Vec::new();
+        for i in 0..50 {
+            let angle = i as f32 / 50.0 * 1.5;
+            let normal = Vec3::new(angle.sin(), 0.0, -angle.cos());
+            let lit = toon.reflect(normal, Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0), &matte);
+            let level = (lit[0] * 1000.0).round() as u32;
+            if !levels.contains(&level) { levels.push(level); }
+        }
+        assert_eq!(levels.len(), 3, "{:?}", levels);
+        // Edge-on to the viewer is drawn as outline, facing it isn't
+        let point = Vec3::new(0.0, 0.0, 1.0);
+        let edge = m.shading.model().shade(Vec3::new(1.0, 0.0, -0.1), Vec3::new(0.0, 0.0, -1.0), point, &lights, &m, &ambient);
+        assert_eq!((edge.r, edge.g, edge.b), (0.0, 0.0, 0.0));
+        let facing = m.shading.model().shade(Vec3::new(0.0, 0.0, -1.0), Vec3::new(0.0, 0.0, -1.0), point, &lights, &m, &ambient);
+        assert!(facing.r > 0.5);
+    }
+}
@@ -0,0 +1,349 @@
+use std::io;
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use crate::aov::Aov;
+use crate::color::Color;
+use crate::physics::world::BodyHandle;
+use crate::quat::Quat;
+use crate::scene::Scene;
+use crate::vec3::Vec3;
+
+// Values a keyframe track can blend between
+pub(crate) trait Lerp: Copy {
+    fn lerp(&self, other: &Self, t: f32) -> Self;
+}
+
+impl Lerp for f32 {
+    fn lerp(&self, other: &Self, t: f32) -> Self { self + (other - self) * t }
+}
+
+impl Lerp for Vec3 {
+    fn lerp(&self, other: &Self, t: f32) -> Self { *self + (*other - *self) * t }
+}
+
+impl Lerp for Color {
+    // Between two valid colours, so no need for Color::new's range checks
+    fn lerp(&self, other: &Self, t: f32) -> Self {
+        Color { r: self.r.lerp(&other.r, t), g: self.g.lerp(&other.g, t), b: self.b.lerp(&other.b, t), a: self.a.lerp(&other.a, t) }
+    }
+}
+
+impl Lerp for Quat {
+    fn lerp(&self, other: &Self, t: f32) -> Self { self.slerp(other, t) }
+}
+
+// How a value travels from one key to the next
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[allow(dead_code, reason = "Step and Linear are there for timeline authors; the demo eases everything smoothly")]
+pub(crate) enum Easing {
+    Step,   // holds until the next key
+    Linear,
+    Smooth, // eases in and out, smoothstep
+}
+
+impl Easing {
+    fn apply(&self, t: f32) -> f32 {
+        match self {
+            Easing::Step => 0.0,
+            Easing::Linear => t,
+            Easing::Smooth => t * t * (3.0 - 2.0 * t),
+        }
+    }
+}
+
+// Values at points in time. Before the first key and after the last the value is held.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Keyframes<T> {
+    keys: Vec<(f32, T, Easing)>, // sorted by time, easing is for the segment after the key
+}
+
+impl<T: Lerp> Keyframes<T> {
+    pub(crate) fn new() -> Self {
+        Keyframes { keys: Vec::new() }
+    }
+
+    // Adds a key, replacing any already at that time
+    pub(crate) fn insert(&mut self, time: f32, value: T, easing: Easing) {
+        match self.keys.binary_search_by(|k| k.0.total_cmp(&time)) {
+            Ok(i) => self.keys[i] = (time, value, easing),
+            Err(i) => self.keys.insert(i, (time, value, easing)),
+        }
+    }
+
+
+    pub(crate) fn sample(&self, time: f32) -> Option<T> {
+        let first = self.keys.first()?;
+        if time <= first.0 { return Some(first.1); }
+        let next = self.keys.partition_point(|k| k.0 <= time);
+        if next == self.keys.len() { return self.keys.last().map(|k| k.1); }
+        let (t0, v0, easing) = self.keys[next - 1];
+        let (t1, v1, _) = self.keys[next];
+        Some(v0.lerp(&v1, easing.apply((time - t0) / (t1 - t0))))
+    }
+}
+
+impl<T: Lerp> Default for Keyframes<T> {
+    fn default() -> Self { Keyframes::new() }
+}
+
+// An animated property of something in the scene
+#[derive(Debug, Clone)]
+#[allow(dead_code, reason = "the demo animates only its camera path and light, not fov, intensity or bodies")]
+pub(crate) enum Track {
+    CameraPosition(Keyframes<Vec3>),
+    CameraRotation(Keyframes<Vec3>), // Euler degrees
+    CameraFov(Keyframes<f32>),
+    LightPosition(usize, Keyframes<Vec3>),
+    LightIntensity(usize, Keyframes<f32>),
+    // Drives a body along a path. Its velocity follows the path too, so it pushes other bodies
+    // around as it moves.
+    BodyPosition(BodyHandle, Keyframes<Vec3>),
+}
+
+impl Track {
+    fn apply(&self, scene: &mut Scene, time: f32) {
+        match self {
+            Track::CameraPosition(keys) => if let Some(p) = keys.sample(time) { scene.camera.set_position(p) },
+            Track::CameraRotation(keys) => if let Some(r) = keys.sample(time) { scene.camera.set_rotation(r) },
+            Track::CameraFov(keys) => if let Some(fov) = keys.sample(time) { scene.camera.set_fov(fov) },
+            // Tracks for lights that have since been removed do nothing
+            Track::LightPosition(light, keys) => if let (Some(p), Some(l)) = (keys.sample(time), scene.lights.get_mut(*light)) { l.set_position(p) },
+            Track::LightIntensity(light, keys) => if let (Some(i), Some(l)) = (keys.sample(time), scene.lights.get_mut(*light)) { l.set_intensity(i) },
+            Track::BodyPosition(handle, keys) => if let (Some(p), Some(ahead)) = (keys.sample(time), keys.sample(time + 1e-2)) {
+                let body = scene.world.body_mut(*handle);
+                body.wake();
+                body.position = p;
+                body.velocity = (ahead - p) / 1e-2;
+                body.sync_shape();
+            },
+        }
+    }
+}
+
+// Keyframed properties played alongside the physics. Frames are numbered from 1 and frame n
+// shows the scene at (n - 1) / fps seconds.
+#[derive(Debug, Clone)]
+pub(crate) struct Timeline {
+    fps: f32,
+    tracks: Vec<Track>,
+}
+
+impl Timeline {
+    pub(crate) fn new(fps: f32) -> Self {
+        assert!(fps > 0.0, "fps must be positive");
+        Timeline { fps, tracks: Vec::new() }
+    }
+
+    pub(crate) fn add_track(&mut self, track: Track) {
+        self.tracks.push(track);
+    }
+
+    pub(crate) fn frame_time(&self, frame: u32) -> f32 {
+        frame.saturating_sub(1) as f32 / self.fps
+    }
+
+    // Poses everything with a track as it is at `time`
+    pub(crate) fn apply(&self, scene: &mut Scene, time: f32) {
+        for track in &self.tracks {
+            track.apply(scene, time);
+        }
+    }
+
+    // Steps the physics up to `time` then applies the tracks. Time only moves forwards.
+    pub(crate) fn advance_to(&self, scene: &mut Scene, time: f32) {
+        let dt = scene.world.timestep();
+        while scene.world.time() + 0.5 * dt < time {
+            scene.world.step();
+        }
+        self.apply(scene, time);
+    }
+
+    // Renders `frames` to numbered PNGs in `output`, creating it if needed, with each of `aovs`
+    // alongside as frame_0001_depth.exr, frame_0001_normal.png and so on. The scene starts at time zero and frames before
+    // the range are still simulated, so a range renders the same pixels as those frames of a full run.
+    pub(crate) fn render_sequence(&self, scene: &mut Scene, frames: RangeInclusive<u32>, output: &Path, aovs: &[Aov]) -> io::Result<Vec<PathBuf>> {
+        std::fs::create_dir_all(output)?;
+        let (width, height) = scene.camera.resolution();
+        let mut written = Vec::new();
+        for frame in 1..=*frames.end() {
+            self.advance_to(scene, self.frame_time(frame));
+            if frame < *frames.start() { continue; }
+            let stem = format!("frame_{:04}", frame);
+            let path = output.join(format!("{}.png", stem));
+            let image = if aovs.is_empty() {
+                scene.render()
+            } else {
+                let (image, buffers) = scene.render_with_aovs();
+                written.extend(buffers.write(aovs, output, &stem)?);
+                image
+            };
+            image::save_buffer(&path, &image, width, height, image::ColorType::Rgba8)
+                .map_err(io::Error::other)?;
+            written.push(path);
+        }
+        Ok(written)
+    }
+}
+
+// Command line options for rendering an image sequence without opening a window:
+//   --frames 1-120 (or a single frame), --fps 24, --out frames, --aovs depth,normal (or all)
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct SequenceSettings {
+    pub frames: RangeInclusive<u32>,
+    pub fps: f32,
+    pub output: PathBuf,
+    pub aovs: Vec<Aov>,
+}
+
+impl Default for SequenceSettings {
+    fn default() -> Self {
+        SequenceSettings { frames: 1..=48, fps: 24.0, output: PathBuf::from("frames"), aovs: Vec::new() }
+    }
+}
+
+impl SequenceSettings {
+    // None if no sequence options were given, in which case the app runs in a window
+    pub(crate) fn from_args(args: impl IntoIterator<Item = String>) -> Result<Option<Self>, String> {
+        let mut settings = SequenceSettings::default();
+        let mut any = false;
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or_else(|| format!("{} needs a value", arg));
+            match arg.as_str() {
+                "--frames" => settings.frames = parse_frames(&value()?)?,
+                "--fps" => {
+                    let text = value()?;
+                    settings.fps = text.parse().ok().filter(|fps: &f32| *fps > 0.0).ok_or_else(|| format!("bad fps '{}'", text))?;
+                }
+                "--out" => settings.output = PathBuf::from(value()?),
+                "--aovs" => settings.aovs = parse_aovs(&value()?)?,
+                _ => return Err(format!("unknown option '{}'", arg)),
+            }
+            any = true;
+        }
+        Ok(any.then_some(settings))
+    }
+}
+
+fn parse_aovs(text: &str) -> Result<Vec<Aov>, String> {
+    if text == "all" { return Ok(Aov::ALL.to_vec()); }
+    text.split(',')
+        .map(|name| Aov::from_name(name.trim()).ok_or_else(|| format!("unknown pass '{}', expected depth, depth_preview, normal, albedo, id, shadow, uv or all", name)))
+        .collect()
+}
+
+fn parse_frames(text: &str) -> Result<RangeInclusive<u32>, String> {
+    let bad = || format!("bad frame range '{}', expected e.g. 1-120", text);
+    let (start, end) = text.split_once('-').unwrap_or((text, text));
+    let start: u32 = start.trim().parse().map_err(|_| bad())?;
+    let end: u32 = end.trim().parse().map_err(|_| bad())?;
+    if start == 0 || end < start { return Err(bad()); }
+    Ok(start..=end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::Camera;
+    use crate::environment::Environment;
+    use crate::light::{AmbientLight, Light, PointLight};
+    use crate::material::Material;
+    use crate::objects::sphere::Sphere;
+    use crate::physics::body::{RigidBody, Shape};
+    use crate::physics::world::World;
+
+    #[test]
+    fn keyframes_hold_blend_and_step() {
+        let mut keys = Keyframes::new();
+        assert_eq!(keys.sample(1.0), None);
+        keys.insert(2.0, 10.0, Easing::Linear);
+        keys.insert(0.0, 0.0, Easing::Smooth);
+        keys.insert(3.0, 20.0, Easing::Step);
+        keys.insert(4.0, 30.0, Easing::Linear);
+        assert_eq!(keys.keys.len(), 4);
+        // Held outside the keys
+        assert_eq!(keys.sample(-1.0), Some(0.0));
+        assert_eq!(keys.sample(9.0), Some(30.0));
+        // Smooth is slow near the keys and halfway at the midpoint
+        assert!((keys.sample(1.0).unwrap() - 5.0).abs() < 1e-5);
+        assert!(keys.sample(0.2).unwrap() < 1.0);
+        assert!((keys.sample(2.5).unwrap() - 15.0).abs() < 1e-5);
+        assert_eq!(keys.sample(3.9), Some(20.0));
+        // Replacing a key
+        keys.insert(4.0, 40.0, Easing::Linear);
+        assert_eq!(keys.keys.len(), 4);
+        assert_eq!(keys.sample(4.0), Some(40.0));
+    }
+
+    #[test]
+    fn parses_sequence_options() {
+        let args = |s: &str| s.split_whitespace().map(String::from).collect::<Vec<_>>();
+        assert_eq!(SequenceSettings::from_args(args("")), Ok(None));
+        let settings = SequenceSettings::from_args(args("--frames 10-20 --fps 30 --out shots")).unwrap().unwrap();
+        assert_eq!(settings, SequenceSettings { frames: 10..=20, fps: 30.0, output: PathBuf::from("shots"), aovs: Vec::new() });
+        assert_eq!(SequenceSettings::from_args(args("--aovs depth,id")).unwrap().unwrap().aovs, [Aov::Depth, Aov::ObjectId]);
+        assert_eq!(SequenceSettings::from_args(args("--aovs all")).unwrap().unwrap().aovs, Aov::ALL);
+        assert!(SequenceSettings::from_args(args("--aovs depth,colour")).is_err());
+        assert_eq!(SequenceSettings::from_args(args("--frames 7")).unwrap().unwrap().frames, 7..=7);
+        assert!(SequenceSettings::from_args(args("--frames 20-10")).is_err());
+        assert!(SequenceSettings::from_args(args("--frames 0-3")).is_err());
+        assert!(SequenceSettings::from_args(args("--fps -1")).is_err());
+        assert!(SequenceSettings::from_args(args("--fps")).is_err());
+        assert!(SequenceSettings::from_args(args("--loud")).is_err());
+    }
+
+    fn falling_ball() -> Scene {
+        let mut world = World::new(Vec3::new(0.0, -9.81, 0.0));
+        world.add_body(RigidBody::new(Shape::Sphere(Sphere::new(1.0, Vec3::new(0.0, 0.0, 10.0), Material::default())), 1.0));
+        let lights: Vec<Box<dyn Light>> = vec![Box::new(PointLight::new(Vec3::new(0.0, 5.0, 0.0), Color::new(1.0, 1.0, 1.0, 1.0), 1.0))];
+        Scene {
+            world,
+            lights,
+            environment: Environment::Black,
+            media: Vec::new(),
+            ambient_light: AmbientLight::new(Color::new(1.0, 1.0, 1.0, 1.0), 0.1),
+            camera: Camera::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 0.0), 60.0, (8, 8)),
+        }
+    }
+
+    #[test]
+    fn tracks_pose_the_scene() {
+        let mut scene = falling_ball();
+        let mut timeline = Timeline::new(24.0);
+        let mut camera = Keyframes::new();
+        camera.insert(0.0, Vec3::new(0.0, 0.0, 0.0), Easing::Linear);
+        camera.insert(1.0, Vec3::new(0.0, 0.0, -4.0), Easing::Linear);
+        timeline.add_track(Track::CameraPosition(camera));
+        let mut intensity = Keyframes::new();
+        intensity.insert(0.0, 1.0, Easing::Linear);
+        intensity.insert(1.0, 0.0, Easing::Linear);
+        timeline.add_track(Track::LightIntensity(0, intensity.clone()));
+        // A track for a light that isn't there is skipped
+        timeline.add_track(Track::LightIntensity(3, intensity));
+
+        timeline.advance_to(&mut scene, 0.5);
+        assert!((scene.camera.position() - Vec3::new(0.0, 0.0, -2.0)).length() < 1e-5);
+        assert!((scene.lights[0].intensity() - 0.5).abs() < 1e-5);
+        // Physics caught up with the timeline
+        assert!((scene.world.time() - 0.5).abs() < scene.world.timestep());
+        assert!(scene.world.bodies()[0].position.y() < -1.0);
+    }
+
+    #[test]
+    fn renders_numbered_frames_in_range() {
+        let output = std::env::temp_dir().join(format!("timeline_{}", std::process::id()));
+        let timeline = Timeline::new(10.0);
+        let mut scene = falling_ball();
+        let written = timeline.render_sequence(&mut scene, 3..=5, &output, &[]).unwrap();
+        let names: Vec<String> = written.iter().map(|p| p.file_name().unwrap().to_string_lossy().into_owned()).collect();
+        assert_eq!(names, ["frame_0003.png", "frame_0004.png", "frame_0005.png"]);
+        assert!(written.iter().all(|p| p.exists()));
+        // Frame 5 is at 0.4 s, and matches frame 5 of a full run
+        assert!((scene.world.time() - 0.4).abs() < scene.world.timestep());
+        let partial = image::open(&written[2]).unwrap().to_rgba8().into_raw();
+        let mut full = falling_ball();
+        timeline.advance_to(&mut full, timeline.frame_time(5));
+        assert_eq!(partial, full.render());
+        let _ = std::fs::remove_dir_all(&output);
+    }
+}
@@ -12,6 +12,10 @@ impl Vec3 {
         Vec3 { x, y, z }
     }
 
+    pub fn x(&self) -> f32 { self.x }
+    pub fn y(&self) -> f32 { self.y }
+    pub fn z(&self) -> f32 { self.z }
+
     pub fn dot(&self, other: &Vec3) -> f32 {
         self.x * other.x + self.y * other.y + self.z * other.z
     }
@@ -44,6 +48,17 @@ impl Vec3 {
             z: -self.z
         }
     }
+
+    // Two unit vectors that together with this (unit) vector form a right-handed orthonormal basis
+    // (Duff et al. 2017, "Building an Orthonormal Basis, Revisited")
+    pub fn orthonormal_basis(&self) -> (Vec3, Vec3) {
+        let sign = 1.0_f32.copysign(self.z);
+        let a = -1.0 / (sign + self.z);
+        let b = self.x * self.y * a;
+        let tangent = Vec3::new(1.0 + sign * self.x * self.x * a, sign * b, -sign * self.x);
+        let bitangent = Vec3::new(b, sign + self.y * self.y * a, -self.y);
+        (tangent, bitangent)
+    }
 }
 
 impl Add<Vec3> for Vec3 {